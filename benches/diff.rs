@@ -0,0 +1,72 @@
+//! how long diffing takes on a big board, with and without an index
+//!
+//! run with `cargo bench --bench diff`
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use hdget::lb::{DiffOptions, Index, Leaderboard};
+use serde_json::json;
+
+/// about as big as a board gets, ranks are a u16
+const SIZE: u32 = 60_000;
+const ROUNDS: u32 = 20;
+
+/// a board with `users` in rank order
+fn board(timestamp: u64, users: &[u32]) -> Leaderboard {
+    let entries: Vec<_> = users
+        .iter()
+        .zip(1..)
+        .map(|(&user_id, rank)| {
+            json!({
+                "rank": rank,
+                "name": format!("player {}", user_id),
+                "user_id": user_id,
+                "run_id": user_id,
+                "score": 1000.0 - rank as f32 / 1000.0,
+            })
+        })
+        .collect();
+
+    serde_json::from_value(json!({ "timestamp": timestamp, "entries": entries })).unwrap()
+}
+
+/// a board per poll, with a few people climbing each time
+fn polls() -> Vec<Leaderboard> {
+    let mut users: Vec<u32> = (1..=SIZE).collect();
+    let mut boards = vec![board(0, &users)];
+    for round in 1..=ROUNDS {
+        for i in 0..5 {
+            let climber = users.remove((SIZE - 1 - round * 10 - i) as usize);
+            users.insert((round * 100 + i) as usize, climber);
+        }
+        boards.push(board(round as u64 * 600, &users));
+    }
+    boards
+}
+
+fn time(name: &str, mut f: impl FnMut()) {
+    let start = Instant::now();
+    f();
+    let took = start.elapsed();
+    println!("{:>10}: {:?} per poll", name, took / ROUNDS);
+}
+
+fn main() {
+    let boards = polls();
+    let options = DiffOptions::default();
+
+    time("rebuilt", || {
+        for pair in boards.windows(2) {
+            black_box(pair[0].events_with(&pair[1], &options));
+        }
+    });
+
+    let mut index = Index::new(&boards[0]);
+    time("indexed", || {
+        for pair in boards.windows(2) {
+            black_box(pair[0].events_indexed(&index, &pair[1], &options));
+            index.update(&pair[0], &pair[1]);
+        }
+    });
+}
@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{self, AsyncWriteExt};
+
+use crate::lb::now;
+
+/// a note about something that happened to the game at some point,
+/// like a patch coming out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub text: String,
+    /// unix time the annotation applies from
+    pub at: u64,
+}
+
+/// parse a date given on the command line
+///
+/// takes either a plain `2024-05-01` (midnight utc), a full rfc 3339
+/// time, or how long ago it was, like `30m`, `24h`, `7d` or `2w`
+pub fn parse_date(s: &str) -> Option<u64> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return time.timestamp().try_into().ok();
+    }
+    if let Some(ago) = parse_ago(s) {
+        return now().as_secs().checked_sub(ago);
+    }
+
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+    date.and_hms_opt(0, 0, 0)?
+        .and_utc()
+        .timestamp()
+        .try_into()
+        .ok()
+}
+
+/// seconds in something like `24h`
+fn parse_ago(s: &str) -> Option<u64> {
+    let unit = match s.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 7 * 86400,
+        _ => return None,
+    };
+    let n: u64 = s[..s.len() - 1].parse().ok()?;
+    n.checked_mul(unit)
+}
+
+/// every annotation, one json object per line
+pub struct Annotations {
+    path: PathBuf,
+}
+
+impl Annotations {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub async fn add(&self, annotation: &Annotation) -> io::Result<()> {
+        let mut line = serde_json::to_string(annotation)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await
+    }
+
+    /// every annotation, oldest first
+    pub async fn all(&self) -> io::Result<Vec<Annotation>> {
+        let text = match fs::read_to_string(&self.path).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut all = text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| serde_json::from_str(l).map_err(io::Error::from))
+            .collect::<io::Result<Vec<Annotation>>>()?;
+        all.sort_by_key(|a| a.at);
+
+        Ok(all)
+    }
+
+    /// annotations in `from..to`, for marking up charts and digests
+    pub async fn between(&self, from: u64, to: u64) -> io::Result<Vec<Annotation>> {
+        let mut all = self.all().await?;
+        all.retain(|a| (from..to).contains(&a.at));
+        Ok(all)
+    }
+
+    /// the latest annotation at or before `at`, ie. the patch
+    /// something happened on
+    pub async fn current(&self, at: u64) -> io::Result<Option<Annotation>> {
+        Ok(self.all().await?.into_iter().rev().find(|a| a.at <= at))
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use tokio::fs;
+use tokio::io::{self, AsyncWriteExt};
+
+use crate::baseline::safe_name;
+use crate::lb::{Encoding, Leaderboard};
+
+mod delta;
+
+use delta::Delta;
+
+/// every snapshot of every board the bot accepted
+///
+/// stored in the cache format at `<dir>/<board>/<timestamp>`,
+/// compressed if `compress_cache` is set.
+///
+/// with `archive_full_every` set, only every so many snapshots
+/// are kept whole. the ones in between are kept as what changed
+/// since the one before, at `<dir>/<board>/<timestamp>.delta`
+///
+/// ```toml
+/// archive = "archive"
+/// archive_full_every = 24
+/// ```
+pub struct Archive {
+    dir: PathBuf,
+    compress: bool,
+    full_every: u32,
+    /// the last snapshot saved of each board, and how many deltas
+    /// there are since a whole one, so it doesn't have to be read back
+    last: HashMap<String, (Leaderboard, u32)>,
+}
+
+impl Archive {
+    pub fn new(dir: impl AsRef<Path>, compress: bool) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            compress,
+            full_every: 1,
+            last: HashMap::new(),
+        }
+    }
+
+    /// keep every `n`th snapshot whole, and deltas in between
+    pub fn full_every(mut self, n: Option<u32>) -> Self {
+        self.full_every = n.unwrap_or(1).max(1);
+        self
+    }
+
+    /// where a board's snapshots live
+    fn board_dir(&self, board: &str) -> io::Result<PathBuf> {
+        if !safe_name(board) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "board names can only have letters, numbers, '-', '_' and '.' in them",
+            ));
+        }
+
+        Ok(self.dir.join(board))
+    }
+
+    /// keep a snapshot, under its timestamp
+    pub async fn save(&mut self, board: &str, lb: &Leaderboard) -> io::Result<()> {
+        let dir = self.board_dir(board)?;
+        fs::create_dir_all(&dir).await?;
+        let timestamp = lb.timestamp.as_secs();
+
+        if self.full_every > 1 {
+            // forgotten until this save works, the next one
+            // can't be a delta against something not on disk
+            let last = match self.last.remove(board) {
+                Some(last) => Some(last),
+                None => self.newest(board).await?,
+            };
+
+            if let Some((prev, deltas)) = last {
+                let delta = (deltas + 1 < self.full_every && prev.timestamp < lb.timestamp)
+                    .then(|| Delta::between(&prev, lb))
+                    .flatten();
+                if let Some(delta) = delta {
+                    let bytes = delta.to_bytes(self.compress).await?;
+                    write(&dir.join(format!("{}.delta", timestamp)), &bytes).await?;
+                    self.last.insert(board.to_string(), (copy(lb), deltas + 1));
+                    return Ok(());
+                }
+            }
+        }
+
+        // snapshots are never written twice, so they don't need locking
+        lb.write_unlocked(
+            &dir.join(timestamp.to_string()),
+            Encoding::Binary,
+            self.compress,
+        )
+        .await?;
+        if self.full_every > 1 {
+            self.last.insert(board.to_string(), (copy(lb), 0));
+        }
+
+        Ok(())
+    }
+
+    /// the newest snapshot of a board, and how many deltas it's past a whole one
+    async fn newest(&self, board: &str) -> io::Result<Option<(Leaderboard, u32)>> {
+        let files = self.files(board).await?;
+        let Some(&(timestamp, _)) = files.last() else {
+            return Ok(None);
+        };
+
+        let deltas = files.iter().rev().take_while(|f| f.1).count() as u32;
+        Ok(Some((self.load(board, timestamp).await?, deltas)))
+    }
+
+    /// read the snapshot taken at `timestamp`
+    pub async fn load(&self, board: &str, timestamp: u64) -> io::Result<Leaderboard> {
+        let dir = self.board_dir(board)?;
+        let files = self.files(board).await?;
+        let Some(i) = files.iter().position(|f| f.0 == timestamp) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("nothing archived for {} at {}", board, timestamp),
+            ));
+        };
+
+        // start from the last whole snapshot, and work forward
+        let start = files[..=i].iter().rposition(|f| !f.1).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the archive of {} starts with a delta", board),
+            )
+        })?;
+        // snapshots from older versions stay in whatever format they were
+        let path = dir.join(files[start].0.to_string());
+        let mut lb = Leaderboard::read_unlocked(&path).await?.0;
+        for (t, _) in &files[start + 1..=i] {
+            let bytes = fs::read(dir.join(format!("{}.delta", t))).await?;
+            lb = Delta::from_bytes(&bytes).await?.apply(&lb);
+        }
+
+        Ok(lb)
+    }
+
+    /// the last snapshot taken at or before `timestamp`
+    pub async fn at(&self, board: &str, timestamp: u64) -> io::Result<Option<Leaderboard>> {
+        let list = self.list(board).await?;
+        match list.into_iter().rev().find(|&t| t <= timestamp) {
+            Some(t) => self.load(board, t).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// the snapshots at either end of a range, to diff with
+    /// `Leaderboard::events`
+    ///
+    /// those are the last ones taken at or before `from` and `to`,
+    /// none if nothing was archived as far back as `from`
+    pub async fn diff_range(
+        &self,
+        board: &str,
+        from: u64,
+        to: u64,
+    ) -> io::Result<Option<(Leaderboard, Leaderboard)>> {
+        if from > to {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the range ends before it starts",
+            ));
+        }
+
+        let Some(old) = self.at(board, from).await? else {
+            return Ok(None);
+        };
+        // there's at least `old` by then
+        let new = match self.at(board, to).await? {
+            Some(new) => new,
+            None => copy(&old),
+        };
+
+        Ok(Some((old, new)))
+    }
+
+    /// delete a snapshot
+    ///
+    /// if the one after it is a delta, it gets kept whole instead first
+    pub async fn remove(&self, board: &str, timestamp: u64) -> io::Result<()> {
+        let dir = self.board_dir(board)?;
+        let files = self.files(board).await?;
+        let Some(i) = files.iter().position(|f| f.0 == timestamp) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("nothing archived for {} at {}", board, timestamp),
+            ));
+        };
+
+        if let Some(&(next, true)) = files.get(i + 1) {
+            let lb = self.load(board, next).await?;
+            lb.write_unlocked(&dir.join(next.to_string()), Encoding::Binary, self.compress)
+                .await?;
+            fs::remove_file(dir.join(format!("{}.delta", next))).await?;
+        }
+
+        fs::remove_file(dir.join(file_name(files[i]))).await
+    }
+
+    /// timestamps of every snapshot of a board, oldest first
+    pub async fn list(&self, board: &str) -> io::Result<Vec<u64>> {
+        Ok(self.files(board).await?.into_iter().map(|f| f.0).collect())
+    }
+
+    /// every snapshot of a board and whether it's a delta, oldest first
+    async fn files(&self, board: &str) -> io::Result<Vec<(u64, bool)>> {
+        let mut files = Vec::new();
+        let mut dir = match fs::read_dir(self.board_dir(board)?).await {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(files),
+            Err(e) => return Err(e),
+        };
+
+        while let Some(entry) = dir.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (t, delta) = match name.strip_suffix(".delta") {
+                Some(t) => (t, true),
+                None => (name.as_str(), false),
+            };
+            // skips anything half written
+            if let Ok(t) = t.parse() {
+                files.push((t, delta));
+            }
+        }
+        files.sort();
+
+        Ok(files)
+    }
+}
+
+fn file_name((timestamp, delta): (u64, bool)) -> String {
+    match delta {
+        true => format!("{}.delta", timestamp),
+        false => timestamp.to_string(),
+    }
+}
+
+fn copy(lb: &Leaderboard) -> Leaderboard {
+    Leaderboard {
+        timestamp: lb.timestamp,
+        entries: lb.entries.clone(),
+    }
+}
+
+/// write a file next to where it goes, then move it there
+async fn write(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp).await?;
+    file.write_all(bytes).await?;
+    file.sync_all().await?;
+    fs::rename(&tmp, path).await
+}
+
+/// how long to keep snapshots around, in days
+///
+/// every snapshot is kept for `all` days, then one an hour until
+/// `hourly` days, then one a day until `daily` days, or forever
+/// if that isn't set.
+///
+/// ```toml
+/// [retention]
+/// all = 7
+/// hourly = 30
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Retention {
+    pub all: u64,
+    pub hourly: u64,
+    pub daily: Option<u64>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            all: 7,
+            hourly: 30,
+            daily: None,
+        }
+    }
+}
+
+impl Retention {
+    /// which of the snapshots taken at `timestamps` can go
+    ///
+    /// the first snapshot of each hour or day is the one that stays,
+    /// and the newest snapshot always stays, however old it is
+    pub fn expired(&self, timestamps: &[u64], now: u64) -> Vec<u64> {
+        const DAY: u64 = 86400;
+
+        let mut timestamps = timestamps.to_vec();
+        timestamps.sort();
+        let newest = timestamps.last().copied();
+
+        let mut expired = Vec::new();
+        let mut last_bucket = None;
+        for t in timestamps {
+            let age = now.saturating_sub(t);
+            let bucket = if age < self.all * DAY {
+                continue;
+            } else if age < self.hourly * DAY {
+                Some((3600, t / 3600))
+            } else if self.daily.is_none_or(|d| age < d * DAY) {
+                Some((DAY, t / DAY))
+            } else {
+                None
+            };
+
+            let keep = match bucket {
+                Some(bucket) => last_bucket.replace(bucket) != Some(bucket),
+                // past every limit
+                None => false,
+            };
+            if !keep && Some(t) != newest {
+                expired.push(t);
+            }
+        }
+
+        expired
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::lb::Entry;
+
+    #[tokio::test]
+    async fn test_archive() {
+        let dir = std::env::temp_dir().join(format!("hdget-archive-{}", std::process::id()));
+        let mut archive = Archive::new(&dir, true);
+
+        for t in [600, 1200, 1800] {
+            let lb = Leaderboard {
+                timestamp: Duration::from_secs(t),
+                entries: vec![],
+            };
+            archive.save("main", &lb).await.unwrap();
+        }
+
+        assert_eq!(archive.list("main").await.unwrap(), [600, 1200, 1800]);
+        assert!(archive.list("other").await.unwrap().is_empty());
+        let lb = archive.at("main", 1500).await.unwrap().unwrap();
+        assert_eq!(lb.timestamp, Duration::from_secs(1200));
+        assert!(archive.at("main", 0).await.unwrap().is_none());
+
+        let (old, new) = archive
+            .diff_range("main", 700, 1800)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (old.timestamp.as_secs(), new.timestamp.as_secs()),
+            (600, 1800)
+        );
+        assert!(archive.diff_range("main", 0, 1800).await.unwrap().is_none());
+        assert!(archive.diff_range("main", 1800, 600).await.is_err());
+        assert!(archive.save("../main", &lb).await.is_err());
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    fn entry(rank: u16, user_id: u32, run_id: u32, score: f32) -> Entry {
+        Entry {
+            rank,
+            name: format!("player {}", user_id),
+            user_id,
+            run_id,
+            score,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deltas() {
+        let dir = std::env::temp_dir().join(format!("hdget-deltas-{}", std::process::id()));
+        let mut archive = Archive::new(&dir, false).full_every(Some(3));
+
+        let mut boards = Vec::new();
+        let mut entries: Vec<_> = (1..=100)
+            .map(|r| entry(r, r as u32, r as u32, 500.0 - r as f32))
+            .collect();
+        for t in 1..=7u64 {
+            // someone climbs from the back to the middle, pushing
+            // everyone in between down one
+            let mut climber = entries.pop().unwrap();
+            climber.run_id += 1000;
+            climber.score = 450.0 + t as f32 / 10.0;
+            climber.rank = 50;
+            for e in &mut entries[49..] {
+                e.rank += 1;
+            }
+            entries.insert(49, climber);
+            if t == 4 {
+                // and someone drops off the board
+                entries.pop();
+            }
+
+            let lb = Leaderboard {
+                timestamp: Duration::from_secs(t * 600),
+                entries: entries.clone(),
+            };
+            archive.save("main", &lb).await.unwrap();
+            boards.push(lb);
+        }
+
+        let files = archive.files("main").await.unwrap();
+        let deltas: Vec<_> = files.iter().map(|f| f.1).collect();
+        assert_eq!(deltas, [false, true, true, false, true, true, false]);
+
+        let check = |lb: &Leaderboard, expected: &Leaderboard| {
+            assert_eq!(lb.timestamp, expected.timestamp);
+            assert_eq!(lb.entries.len(), expected.entries.len());
+            for (a, b) in lb.entries.iter().zip(&expected.entries) {
+                assert_eq!((a.rank, a.user_id, a.run_id), (b.rank, b.user_id, b.run_id));
+            }
+        };
+        for lb in &boards {
+            check(
+                &archive.load("main", lb.timestamp.as_secs()).await.unwrap(),
+                lb,
+            );
+        }
+
+        // the delta after a removed snapshot gets kept whole
+        archive.remove("main", 600).await.unwrap();
+        archive.remove("main", 1800).await.unwrap();
+        let files = archive.files("main").await.unwrap();
+        assert_eq!(files[0], (1200, false));
+        assert_eq!(files[1], (2400, false));
+        for lb in &boards[1..] {
+            if lb.timestamp.as_secs() != 1800 {
+                check(
+                    &archive.load("main", lb.timestamp.as_secs()).await.unwrap(),
+                    lb,
+                );
+            }
+        }
+
+        // a fresh archive picks up where the last one left off
+        let mut archive = Archive::new(&dir, false).full_every(Some(3));
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(8 * 600),
+            entries,
+        };
+        archive.save("main", &lb).await.unwrap();
+        assert_eq!(
+            archive.files("main").await.unwrap().last(),
+            Some(&(4800, true))
+        );
+        check(&archive.load("main", 4800).await.unwrap(), &lb);
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn test_retention() {
+        const DAY: u64 = 86400;
+        let now = 100 * DAY;
+        let ago = |secs| now - secs;
+
+        let retention = Retention {
+            all: 1,
+            hourly: 2,
+            daily: Some(3),
+        };
+        let timestamps = [
+            // too old for anything
+            ago(5 * DAY),
+            // daily, only the first of the day stays
+            ago(2 * DAY + 3 * 3600),
+            ago(2 * DAY + 3 * 3600 - 600),
+            // hourly, only the first of the hour stays
+            ago(DAY + 1200),
+            ago(DAY + 600),
+            // recent, everything stays
+            ago(600),
+            ago(0),
+        ];
+
+        assert_eq!(
+            retention.expired(&timestamps, now),
+            [ago(5 * DAY), ago(2 * DAY + 3 * 3600 - 600), ago(DAY + 600)]
+        );
+
+        // the newest snapshot stays, even if it's past every limit
+        assert!(retention.expired(&[ago(5 * DAY)], now).is_empty());
+    }
+}
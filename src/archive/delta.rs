@@ -0,0 +1,221 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+
+use crate::lb::{Entry, Leaderboard};
+
+/// what every delta starts with
+const DELTA_MAGIC: &[u8; 4] = b"HDGD";
+
+const DELTA_VERSION: u8 = 1;
+
+/// what a zstd frame starts with
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// entries in `from..=to` moved `shift` ranks, without changing otherwise
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Move {
+    from: u16,
+    to: u16,
+    shift: i16,
+}
+
+/// what changed between two snapshots of a board
+///
+/// a pb moves everyone between the old and new rank down one, so
+/// entries that only moved are kept as ranges of ranks. the rest
+/// of the entries that changed are kept whole.
+///
+/// laid out as the magic, the version, the timestamp, the removed
+/// user ids, the moves and the changed entries, each after their
+/// count, then a crc32 of everything before it
+#[derive(Debug)]
+pub(super) struct Delta {
+    timestamp: u64,
+    removed: Vec<u32>,
+    moves: Vec<Move>,
+    changed: Vec<Entry>,
+}
+
+impl Delta {
+    /// the delta that turns `old` into `new`
+    ///
+    /// `None` if a delta can't get there exactly, ie. if there
+    /// are ties in the ranks, then it's best kept whole
+    pub fn between(old: &Leaderboard, new: &Leaderboard) -> Option<Self> {
+        let by_user: HashMap<_, _> = old.entries.iter().map(|e| (e.user_id, e)).collect();
+        let users: HashSet<_> = new.entries.iter().map(|e| e.user_id).collect();
+
+        let removed = old
+            .entries
+            .iter()
+            .filter(|e| !users.contains(&e.user_id))
+            .map(|e| e.user_id)
+            .collect();
+
+        let mut changed = Vec::new();
+        // old rank and how far it moved, of everything that only moved
+        let mut moved = Vec::new();
+        for e in &new.entries {
+            match by_user.get(&e.user_id) {
+                Some(old) if same_run(old, e) => moved.push((
+                    old.rank,
+                    i16::try_from(e.rank as i32 - old.rank as i32).ok()?,
+                )),
+                _ => changed.push(e.clone()),
+            }
+        }
+        moved.sort_by_key(|m| m.0);
+
+        let mut moves: Vec<Move> = Vec::new();
+        let mut open = false;
+        for (rank, shift) in moved {
+            // anything that stayed put splits the ranges up
+            if shift == 0 {
+                open = false;
+                continue;
+            }
+            match moves.last_mut() {
+                Some(m) if open && m.shift == shift => m.to = rank,
+                _ => moves.push(Move {
+                    from: rank,
+                    to: rank,
+                    shift,
+                }),
+            }
+            open = true;
+        }
+
+        let delta = Self {
+            timestamp: new.timestamp.as_secs(),
+            removed,
+            moves,
+            changed,
+        };
+        let check = delta.apply(old);
+        let exact = check.entries.len() == new.entries.len()
+            && check
+                .entries
+                .iter()
+                .zip(&new.entries)
+                .all(|(a, b)| a.rank == b.rank && same_run(a, b));
+
+        exact.then_some(delta)
+    }
+
+    /// rebuild the snapshot this delta was made from
+    pub fn apply(&self, old: &Leaderboard) -> Leaderboard {
+        let removed: HashSet<_> = self.removed.iter().collect();
+        let changed: HashSet<_> = self.changed.iter().map(|e| e.user_id).collect();
+
+        let mut entries: Vec<_> = old
+            .entries
+            .iter()
+            .filter(|e| !removed.contains(&e.user_id) && !changed.contains(&e.user_id))
+            .cloned()
+            .map(|mut e| {
+                if let Some(m) = self
+                    .moves
+                    .iter()
+                    .find(|m| (m.from..=m.to).contains(&e.rank))
+                {
+                    e.rank = (e.rank as i32 + m.shift as i32) as u16;
+                }
+                e
+            })
+            .chain(self.changed.iter().cloned())
+            .collect();
+        entries.sort_by_key(|e| e.rank);
+
+        Leaderboard {
+            timestamp: Duration::from_secs(self.timestamp),
+            entries,
+        }
+    }
+
+    pub async fn to_bytes(&self, compress: bool) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.write_all(DELTA_MAGIC).await?;
+        buf.write_u8(DELTA_VERSION).await?;
+        buf.write_u64_le(self.timestamp).await?;
+
+        buf.write_u32_le(self.removed.len() as u32).await?;
+        for id in &self.removed {
+            buf.write_u32_le(*id).await?;
+        }
+        buf.write_u32_le(self.moves.len() as u32).await?;
+        for m in &self.moves {
+            buf.write_u16_le(m.from).await?;
+            buf.write_u16_le(m.to).await?;
+            buf.write_i16_le(m.shift).await?;
+        }
+        buf.write_u32_le(self.changed.len() as u32).await?;
+        for e in &self.changed {
+            e.write(&mut buf).await?;
+        }
+
+        let sum = crc32fast::hash(&buf);
+        buf.write_u32_le(sum).await?;
+
+        if compress {
+            buf = zstd::encode_all(&buf[..], 0)?;
+        }
+        Ok(buf)
+    }
+
+    pub async fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.starts_with(&ZSTD_MAGIC) {
+            return Box::pin(Self::from_bytes(&zstd::decode_all(bytes)?)).await;
+        }
+
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let Some(rest) = bytes.strip_prefix(DELTA_MAGIC) else {
+            return Err(invalid("not a delta"));
+        };
+        let (data, sum) = rest.split_at(rest.len().saturating_sub(4));
+        let found = sum.try_into().map(u32::from_le_bytes).ok();
+        if found != Some(crc32fast::hash(&bytes[..bytes.len() - 4])) {
+            return Err(invalid("the delta is corrupt"));
+        }
+
+        let mut r = data;
+        let version = r.read_u8().await?;
+        if version != DELTA_VERSION {
+            return Err(invalid(&format!("unknown delta version {}", version)));
+        }
+        let timestamp = r.read_u64_le().await?;
+
+        let mut removed = Vec::new();
+        for _ in 0..r.read_u32_le().await? {
+            removed.push(r.read_u32_le().await?);
+        }
+        let mut moves = Vec::new();
+        for _ in 0..r.read_u32_le().await? {
+            moves.push(Move {
+                from: r.read_u16_le().await?,
+                to: r.read_u16_le().await?,
+                shift: r.read_i16_le().await?,
+            });
+        }
+        let mut changed = Vec::new();
+        for _ in 0..r.read_u32_le().await? {
+            changed.push(Entry::read(&mut r).await?);
+        }
+
+        Ok(Self {
+            timestamp,
+            removed,
+            moves,
+            changed,
+        })
+    }
+}
+
+/// same run, same everything, apart from the rank
+fn same_run(a: &Entry, b: &Entry) -> bool {
+    a.user_id == b.user_id
+        && a.run_id == b.run_id
+        && a.name == b.name
+        && a.score.to_bits() == b.score.to_bits()
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{self, AsyncWriteExt};
+
+use crate::lb::{Entry, Event, EventKind};
+use crate::report::CycleReport;
+
+/// a snapshot of an Entry, as the diff saw it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Seen {
+    pub rank: u16,
+    pub run_id: u32,
+    pub score: f32,
+}
+
+impl From<&Entry> for Seen {
+    fn from(e: &Entry) -> Self {
+        Self {
+            rank: e.rank,
+            run_id: e.run_id,
+            score: e.score,
+        }
+    }
+}
+
+impl fmt::Display for Seen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "run {}, rank #{}, score {}",
+            self.run_id, self.rank, self.score
+        )
+    }
+}
+
+/// what ended up happening to an event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Decision {
+    /// the board's filter dropped it
+    Filtered,
+    /// the same run was sent recently
+    Suppressed,
+    /// one of the board's rules said not to send it, see `rules::Rule`
+    Rule,
+    /// the board only gets a digest, see `digest::DigestConfig`
+    Digest,
+    /// the baseline was stale, so it went out in a summary, see
+    /// `config::Config::stale_after`
+    CaughtUp,
+    /// it's quiet hours, so it's in the outbox until they're
+    /// over, see `quiet::QuietHours`
+    Held,
+    /// it went out
+    Sent { event_id: String, message_id: u64 },
+    /// it should have gone out, but sending failed
+    Failed { error: String },
+}
+
+/// one line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    /// a whole cycle, written once it's done
+    Cycle(CycleReport),
+    /// an event found during a cycle, and what happened to it
+    Event {
+        board: String,
+        cycle: u64,
+        user_id: u32,
+        name: String,
+        old: Option<Seen>,
+        new: Seen,
+        kind: EventKind,
+        /// whether the board's filter let it through
+        filter: bool,
+        /// where the event was going to be sent
+        targets: Vec<String>,
+        decision: Decision,
+    },
+}
+
+impl Record {
+    /// the record of an event
+    pub fn event(
+        board: &str,
+        cycle: u64,
+        pb: &Event,
+        filter: bool,
+        targets: Vec<String>,
+        decision: Decision,
+    ) -> Self {
+        Self::Event {
+            board: board.to_string(),
+            cycle,
+            user_id: pb.entry().user_id,
+            name: pb.entry().name.clone(),
+            old: pb.previous().map(Seen::from),
+            new: Seen::from(pb.entry()),
+            kind: pb.kind(),
+            filter,
+            targets,
+            decision,
+        }
+    }
+
+    pub fn board(&self) -> &str {
+        match self {
+            Self::Cycle(report) => &report.board,
+            Self::Event { board, .. } => board,
+        }
+    }
+
+    pub fn cycle(&self) -> u64 {
+        match self {
+            Self::Cycle(report) => report.cycle,
+            Self::Event { cycle, .. } => *cycle,
+        }
+    }
+}
+
+/// everything the bot decided, and why
+///
+/// one json object per line. used by `hdget explain` to answer
+/// "why didn't the bot post my pb".
+pub struct Audit {
+    path: PathBuf,
+}
+
+impl Audit {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub async fn append(&self, records: &[Record]) -> io::Result<()> {
+        let mut buf = String::new();
+        for record in records {
+            buf.push_str(&serde_json::to_string(record)?);
+            buf.push('\n');
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(buf.as_bytes()).await
+    }
+
+    pub async fn read_all(&self) -> io::Result<Vec<Record>> {
+        let text = match fs::read_to_string(&self.path).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        text.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| serde_json::from_str(l).map_err(io::Error::from))
+            .collect()
+    }
+
+    /// the number of the last cycle a board ran, so cycle numbers
+    /// keep counting up across restarts
+    pub async fn last_cycle(&self, board: &str) -> io::Result<u64> {
+        Ok(self
+            .read_all()
+            .await?
+            .iter()
+            .filter(|r| r.board() == board)
+            .map(Record::cycle)
+            .max()
+            .unwrap_or(0))
+    }
+}
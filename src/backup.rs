@@ -0,0 +1,352 @@
+use std::path::{Path, PathBuf};
+
+use tokio::fs;
+use tokio::io;
+use tracing::warn;
+
+use crate::baseline::safe_name;
+use crate::config::Config;
+use crate::lb::Leaderboard;
+use crate::store::{Backend, Store};
+
+/// what went into a backup, or came out of one
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Contents {
+    pub caches: usize,
+    pub snapshots: usize,
+    pub store: bool,
+}
+
+/// something to put in the tarball
+enum Item {
+    Bytes(Vec<u8>),
+    File(PathBuf),
+}
+
+/// name of the store in the tarball, so it can't get restored
+/// into the wrong kind of store
+fn store_name(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Sqlite => "store.sqlite",
+        Backend::Redb => "store.redb",
+    }
+}
+
+/// pack the caches, the archive and the store into a tar.zst at `path`
+///
+/// laid out as `caches/<board>.cache` (and the journal and outbox
+/// next to it), `archive/<board>/<snapshot>` and `store.<backend>`.
+/// safe to take while the bot is running, unless the store is in
+/// redb, which only lets one process have it open
+pub async fn backup(config: &Config, path: &Path) -> io::Result<Contents> {
+    let mut contents = Contents::default();
+    let mut items = Vec::new();
+
+    for board in &config.boards {
+        let cache = board.cache_path();
+        if !fs::try_exists(&cache).await? {
+            continue;
+        }
+        items.push((
+            format!("caches/{}.cache", board.name),
+            Item::Bytes(Leaderboard::read_raw(&cache).await?),
+        ));
+        contents.caches += 1;
+        for ext in ["journal", "outbox"] {
+            let file = cache.with_extension(ext);
+            if fs::try_exists(&file).await? {
+                items.push((format!("caches/{}.{}", board.name, ext), Item::File(file)));
+            }
+        }
+    }
+
+    if let Some(archive) = &config.archive {
+        for board in list(archive).await? {
+            for name in list(&archive.join(&board)).await? {
+                // half written, or a lock
+                if name.ends_with(".tmp") || name.ends_with(".lock") {
+                    continue;
+                }
+                let file = archive.join(&board).join(&name);
+                items.push((format!("archive/{}/{}", board, name), Item::File(file)));
+                contents.snapshots += 1;
+            }
+        }
+    }
+
+    // the store's copied first, so it isn't caught halfway through a write
+    let copy = path.with_extension("store.tmp");
+    if let Some(store) = &config.store {
+        if fs::try_exists(store).await? {
+            let _ = fs::remove_file(&copy).await;
+            Store::open(store, config.store_backend)
+                .await?
+                .copy_to(&copy)
+                .await?;
+            let name = store_name(config.store_backend).to_string();
+            items.push((name, Item::File(copy.clone())));
+            contents.store = true;
+        }
+    }
+
+    let path = path.to_path_buf();
+    let res = tokio::task::spawn_blocking(move || {
+        let tmp = path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp)?;
+        let mut tar = tar::Builder::new(zstd::Encoder::new(file, 0)?);
+        for (name, item) in items {
+            match item {
+                Item::Bytes(bytes) => {
+                    let mut header = tar::Header::new_gnu();
+                    header.set_size(bytes.len() as u64);
+                    header.set_mode(0o644);
+                    tar.append_data(&mut header, name, &bytes[..])?
+                }
+                Item::File(file) => tar.append_path_with_name(file, name)?,
+            }
+        }
+        tar.into_inner()?.finish()?.sync_all()?;
+        std::fs::rename(tmp, path)
+    })
+    .await?;
+    if contents.store {
+        fs::remove_file(&copy).await?;
+    }
+    res?;
+
+    Ok(contents)
+}
+
+/// names of everything in a directory, none if it isn't there
+async fn list(dir: &Path) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(names),
+        Err(e) => return Err(e),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// where something in a backup goes, according to the config
+fn destination(config: &Config, name: &str) -> io::Result<Option<PathBuf>> {
+    let parts: Vec<_> = name.split('/').collect();
+    if !parts.iter().all(|p| safe_name(p)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} doesn't belong in a backup", name),
+        ));
+    }
+
+    Ok(match parts[..] {
+        ["caches", file] => {
+            let (board, ext) = file.rsplit_once('.').unwrap_or((file, ""));
+            match (config.board(board), ext) {
+                (None, _) => {
+                    warn!(board, "skipping a board that isn't in the config");
+                    None
+                }
+                // the cache can be called anything, the rest are named after it
+                (Some(board), "cache") => Some(board.cache_path()),
+                (Some(board), "journal" | "outbox") => Some(board.cache_path().with_extension(ext)),
+                (Some(_), _) => {
+                    warn!(name, "skipping something unknown in the backup");
+                    None
+                }
+            }
+        }
+        ["archive", board, file] => config.archive.as_ref().map(|a| a.join(board).join(file)),
+        [file] if file.starts_with("store.") => {
+            if file != store_name(config.store_backend) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("the backup has a {}, but store_backend doesn't match", file),
+                ));
+            }
+            config.store.clone()
+        }
+        _ => {
+            warn!(name, "skipping something unknown in the backup");
+            None
+        }
+    })
+}
+
+/// unpack a backup made by `backup` to where the config keeps things
+///
+/// boards that aren't in the config are skipped, and so is the archive
+/// or the store if the config doesn't have one. nothing that's already
+/// there gets replaced, unless `force` is set
+pub async fn restore(config: &Config, path: &Path, force: bool) -> io::Result<Contents> {
+    // everything's read twice, to check before anything's written
+    let names = {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut tar = tar::Archive::new(zstd::Decoder::new(std::fs::File::open(path)?)?);
+            let mut names = Vec::new();
+            for entry in tar.entries()? {
+                names.push(entry?.path()?.to_string_lossy().into_owned());
+            }
+            Ok::<_, io::Error>(names)
+        })
+        .await??
+    };
+
+    let mut contents = Contents::default();
+    let mut files = Vec::new();
+    for name in names {
+        let Some(to) = destination(config, &name)? else {
+            continue;
+        };
+        if !force && fs::try_exists(&to).await? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is already there", to.display()),
+            ));
+        }
+
+        if name.ends_with(".cache") {
+            contents.caches += 1;
+        } else if name.starts_with("archive/") {
+            contents.snapshots += 1;
+        } else if name.starts_with("store.") {
+            contents.store = true;
+        }
+        files.push((name, to));
+    }
+
+    for (name, to) in &files {
+        if let Some(dir) = to.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        // would get applied to the restored store otherwise
+        if name.starts_with("store.") {
+            for ext in ["-wal", "-shm"] {
+                let mut wal = to.clone().into_os_string();
+                wal.push(ext);
+                let _ = fs::remove_file(wal).await;
+            }
+        }
+    }
+
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut tar = tar::Archive::new(zstd::Decoder::new(std::fs::File::open(path)?)?);
+        let mut files = files.into_iter().peekable();
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            if let Some((_, to)) = files.next_if(|(n, _)| *n == name) {
+                let tmp = to.with_extension("restore");
+                entry.unpack(&tmp)?;
+                std::fs::rename(tmp, to)?;
+            }
+        }
+        Ok::<_, io::Error>(())
+    })
+    .await??;
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::archive::Archive;
+    use crate::lb::{Encoding, Entry};
+
+    fn config(dir: &Path) -> Config {
+        let mut config: Config = toml::from_str(&format!(
+            "archive = {:?}\nstore = {:?}\n[[board]]\nname = \"main\"\nwebhook = \"\"\ncache = {:?}",
+            dir.join("archive"),
+            dir.join("store.sqlite"),
+            dir.join("main.cache"),
+        ))
+        .unwrap();
+        config.memory_only = true;
+        config
+    }
+
+    #[tokio::test]
+    async fn test_backup() {
+        let dir = std::env::temp_dir().join(format!("hdget-backup-{}", std::process::id()));
+        let (from, to) = (dir.join("from"), dir.join("to"));
+        fs::create_dir_all(&from).await.unwrap();
+        let old = config(&from);
+
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![Entry {
+                rank: 1,
+                name: "someone".to_string(),
+                user_id: 1,
+                run_id: 2,
+                score: 300.0,
+            }],
+        };
+        lb.cache_as(old.boards[0].cache_path(), Encoding::Binary, false)
+            .await
+            .unwrap();
+        let mut archive = Archive::new(old.archive.as_ref().unwrap(), false);
+        archive.save("main", &lb).await.unwrap();
+        let store = Store::open(old.store.as_ref().unwrap(), Backend::Sqlite)
+            .await
+            .unwrap();
+        store.save("main", &lb, &[]).await.unwrap();
+
+        let tarball = dir.join("hdget.tar.zst");
+        let contents = backup(&old, &tarball).await.unwrap();
+        let expected = Contents {
+            caches: 1,
+            snapshots: 1,
+            store: true,
+        };
+        assert_eq!(contents, expected);
+
+        let new = config(&to);
+        assert_eq!(restore(&new, &tarball, false).await.unwrap(), expected);
+        let cache = Leaderboard::from_cache(new.boards[0].cache_path())
+            .await
+            .unwrap();
+        assert_eq!(cache.entries[0].name, "someone");
+        let archive = Archive::new(new.archive.as_ref().unwrap(), false);
+        assert_eq!(archive.list("main").await.unwrap(), [600]);
+        let store = Store::open(new.store.as_ref().unwrap(), Backend::Sqlite)
+            .await
+            .unwrap();
+        assert!(store.latest("main").await.unwrap().is_some());
+
+        // nothing gets replaced by accident
+        let err = restore(&new, &tarball, false).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        restore(&new, &tarball, true).await.unwrap();
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn test_destination() {
+        let config: Config = toml::from_str(
+            "[[board]]\nname = \"main\"\nwebhook = \"\"\ncache = \"/srv/hd/main.bin\"\n\
+             [[board]]\nname = \"old\"\nwebhook = \"\"\ncache = \"cache\"",
+        )
+        .unwrap();
+        let dest = |name| destination(&config, name).unwrap();
+
+        assert_eq!(dest("caches/main.cache"), Some("/srv/hd/main.bin".into()));
+        assert_eq!(
+            dest("caches/main.journal"),
+            Some("/srv/hd/main.journal".into())
+        );
+        assert_eq!(dest("caches/old.cache"), Some("cache".into()));
+        assert_eq!(dest("caches/old.outbox"), Some("cache.outbox".into()));
+        assert_eq!(dest("caches/other.cache"), None);
+        assert!(destination(&config, "caches/../main.cache").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+use tokio::fs;
+use tokio::io;
+
+use crate::lb::{CacheFormat, Leaderboard};
+
+/// check that a name is safe to use in a path
+///
+/// names end up in paths, so keep them boring
+pub(crate) fn safe_name(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with('.')
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// named snapshots of boards, kept around on purpose
+///
+/// stored in the same format as the cache, at
+/// `<dir>/<board>/<name>`.
+pub struct Baselines {
+    dir: PathBuf,
+}
+
+impl Baselines {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// where a baseline lives
+    fn path(&self, board: &str, name: &str) -> io::Result<PathBuf> {
+        if !safe_name(board) || !safe_name(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "baseline and board names can only have letters, numbers, '-', '_' and '.' in them",
+            ));
+        }
+
+        Ok(self.dir.join(board).join(name))
+    }
+
+    /// pin a leaderboard under a name, replacing any old one
+    pub async fn save(&self, board: &str, name: &str, lb: &Leaderboard) -> io::Result<()> {
+        let path = self.path(board, name)?;
+        fs::create_dir_all(self.dir.join(board)).await?;
+        lb.cache(path).await
+    }
+
+    /// read a pinned leaderboard
+    pub async fn load(&self, board: &str, name: &str) -> io::Result<Leaderboard> {
+        Leaderboard::from_cache(self.path(board, name)?).await
+    }
+
+    /// rewrite a baseline in the current cache format
+    pub async fn migrate(&self, board: &str, name: &str) -> io::Result<CacheFormat> {
+        Leaderboard::migrate(self.path(board, name)?).await
+    }
+
+    /// names of every baseline saved for a board
+    pub async fn list(&self, board: &str) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut dir = match fs::read_dir(self.dir.join(board)).await {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(names),
+            Err(e) => return Err(e),
+        };
+
+        while let Some(entry) = dir.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // see `lb::CacheLock`
+            if !name.ends_with(".lock") {
+                names.push(name);
+            }
+        }
+        names.sort();
+
+        Ok(names)
+    }
+}
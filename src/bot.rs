@@ -0,0 +1,396 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use serenity::all::{
+    Command, CommandInteraction, CommandOptionType, Context, CreateAllowedMentions, CreateCommand,
+    CreateCommandOption, CreateInteractionResponse, CreateInteractionResponseMessage, EventHandler,
+    GatewayIntents, GuildId, Interaction, Ready,
+};
+use serenity::Client;
+use tokio::io;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::config::BotConfig;
+use crate::history::History;
+use crate::lb::{now, Entry, Leaderboard};
+use crate::live::Live;
+use crate::render::discord::escape_markdown;
+use crate::store::{Store, User};
+
+/// how many entries `/top` shows
+const TOP: usize = 10;
+
+/// how many days back `/compare` looks at who improved more
+const COMPARE_DAYS: u64 = 30;
+
+/// a discord bot people can ask about the boards with slash
+/// commands, answered from what the pollers last saw instead of
+/// asking the site
+///
+/// it only needs the `applications.commands` and `bot` scopes,
+/// and permission to send messages in the channels it posts in
+pub struct Bot {
+    token: String,
+    handler: Handler,
+}
+
+struct Handler {
+    guild: Option<u64>,
+    /// the first one is what commands use when no board is given
+    boards: Vec<String>,
+    live: Arc<Live>,
+    /// for the history in `/compare`, which is left off without it
+    store: Option<Store>,
+}
+
+impl Bot {
+    pub fn new(
+        config: &BotConfig,
+        boards: Vec<String>,
+        live: Arc<Live>,
+        store: Option<Store>,
+    ) -> Self {
+        Self {
+            token: config.token.clone(),
+            handler: Handler {
+                guild: config.guild,
+                boards,
+                live,
+                store,
+            },
+        }
+    }
+
+    /// connect to the gateway, and answer commands until `shutdown`
+    pub async fn run(self, mut shutdown: watch::Receiver<bool>) -> io::Result<()> {
+        // commands come in as interactions, so it doesn't need to
+        // see any messages
+        let mut client = Client::builder(&self.token, GatewayIntents::empty())
+            .event_handler(self.handler)
+            .await
+            .map_err(io::Error::other)?;
+
+        let shards = client.shard_manager.clone();
+        tokio::spawn(async move {
+            let _ = shutdown.wait_for(|stop| *stop).await;
+            shards.shutdown_all().await;
+        });
+
+        client.start().await.map_err(io::Error::other)
+    }
+}
+
+impl Handler {
+    async fn answer(&self, command: &CommandInteraction) -> String {
+        let option = |name| {
+            let option = command.data.options.iter().find(|o| o.name == name);
+            option.and_then(|o| o.value.as_str())
+        };
+        let Some(board) = option("board").or(self.boards.first().map(String::as_str)) else {
+            return "There aren't any boards.".to_string();
+        };
+        let Some(lb) = self.live.get(board) else {
+            return format!("{} hasn't been fetched yet.", board);
+        };
+
+        match command.data.name.as_str() {
+            "top" => top(&lb, board),
+            "rank" => rank(&lb, option("player").unwrap_or_default()),
+            "compare" => {
+                let player = option("player").unwrap_or_default();
+                let other = option("other").unwrap_or_default();
+                self.compare(&lb, board, player, other).await
+            }
+            name => format!("I don't know /{}.", name),
+        }
+    }
+
+    /// `/compare`, with who improved more if there's a store to ask
+    async fn compare(&self, lb: &Leaderboard, board: &str, player: &str, other: &str) -> String {
+        let find = |name: &str| {
+            let entry = lb
+                .entries()
+                .iter()
+                .find(|e| e.name.eq_ignore_ascii_case(name));
+            entry.ok_or_else(|| format!("{} isn't on the board.", escape_markdown(name)))
+        };
+        let (a, b) = match (find(player), find(other)) {
+            (Ok(a), Ok(b)) => (a, b),
+            (Err(e), _) | (_, Err(e)) => return e,
+        };
+
+        let improved = match &self.store {
+            Some(store) => {
+                let since = now().as_secs().saturating_sub(COMPARE_DAYS * 86400);
+                let res = tokio::try_join!(
+                    improved(store, board, a, since),
+                    improved(store, board, b, since),
+                );
+                // still worth answering without it
+                res.inspect_err(|e| warn!(error = %e, "couldn't read the history"))
+                    .ok()
+            }
+            None => None,
+        };
+        compare(a, b, improved)
+    }
+}
+
+/// how much someone's pbs on a board added up to since `since`
+async fn improved(store: &Store, board: &str, entry: &Entry, since: u64) -> io::Result<f32> {
+    let events = store
+        .events_of(User::Id(entry.user_id), Some(board))
+        .await?;
+    Ok(History::new(events).improved_since(board, since))
+}
+
+#[serenity::async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        let commands = commands(&self.boards);
+        let res = match self.guild {
+            Some(guild) => GuildId::new(guild).set_commands(&ctx.http, commands).await,
+            None => Command::set_global_commands(&ctx.http, commands).await,
+        };
+        match res {
+            Ok(_) => info!(name = %ready.user.name, "bot connected"),
+            Err(e) => warn!(error = %e, "couldn't register the slash commands"),
+        }
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        let Interaction::Command(command) = interaction else {
+            return;
+        };
+        let message = CreateInteractionResponseMessage::new()
+            .content(self.answer(&command).await)
+            // answers are about people, not for them
+            .allowed_mentions(CreateAllowedMentions::new());
+        let response = CreateInteractionResponse::Message(message);
+        if let Err(e) = command.create_response(&ctx.http, response).await {
+            warn!(command = %command.data.name, error = %e, "couldn't answer a command");
+        }
+    }
+}
+
+/// the slash commands, with the boards to pick from
+fn commands(boards: &[String]) -> Vec<CreateCommand> {
+    let board = || {
+        let option = CreateCommandOption::new(
+            CommandOptionType::String,
+            "board",
+            "which board, the first one by default",
+        );
+        boards
+            .iter()
+            .fold(option, |option, b| option.add_string_choice(b, b))
+    };
+
+    // discord wants the options that are required first
+    let player = |name, description| {
+        CreateCommandOption::new(CommandOptionType::String, name, description).required(true)
+    };
+
+    vec![
+        CreateCommand::new("top")
+            .description("the top of a board")
+            .add_option(board()),
+        CreateCommand::new("rank")
+            .description("where someone is on a board")
+            .add_option(player("player", "their name"))
+            .add_option(board()),
+        CreateCommand::new("compare")
+            .description("how two people are doing against each other")
+            .add_option(player("player", "one of their names"))
+            .add_option(player("other", "the other one's name"))
+            .add_option(board()),
+    ]
+}
+
+/// the answer to `/top`
+fn top(lb: &Leaderboard, board: &str) -> String {
+    let mut s = format!("**Top {} on {}**\n", TOP, escape_markdown(board));
+    // writing to a String can't fail
+    for entry in lb.entries().iter().take(TOP) {
+        writeln!(
+            s,
+            "#{} **{}** {}",
+            entry.rank,
+            escape_markdown(&entry.name),
+            entry.score
+        )
+        .unwrap();
+    }
+    writeln!(s, "As of <t:{}:R>", lb.timestamp().as_secs()).unwrap();
+    s
+}
+
+/// the answer to `/rank`, with how far they are from the next rank up
+fn rank(lb: &Leaderboard, player: &str) -> String {
+    let entries = lb.entries();
+    let Some(i) = entries
+        .iter()
+        .position(|e| e.name.eq_ignore_ascii_case(player))
+    else {
+        return format!("{} isn't on the board.", escape_markdown(player));
+    };
+    let entry = &entries[i];
+    // whoever they're tied with is at the same rank
+    let ahead = entries[..i].iter().rev().find(|e| e.rank < entry.rank);
+
+    let mut s = format!(
+        "**{}** is #{} with {}",
+        escape_markdown(&entry.name),
+        entry.rank,
+        entry.score
+    );
+    match ahead {
+        Some(ahead) => writeln!(
+            s,
+            ", {} behind **{}** at #{}.",
+            ahead.score - entry.score,
+            escape_markdown(&ahead.name),
+            ahead.rank
+        ),
+        None => writeln!(s, ", the world record."),
+    }
+    .unwrap();
+    writeln!(s, "Watch in-game: {}", entry.run_link()).unwrap();
+    s
+}
+
+/// the answer to `/compare`, with how much each of them improved in
+/// the last `COMPARE_DAYS` if that's known
+fn compare(a: &Entry, b: &Entry, improved: Option<(f32, f32)>) -> String {
+    let mut s = String::new();
+    let name = |e: &Entry| escape_markdown(&e.name);
+    // writing to a String can't fail
+    for e in [a, b] {
+        writeln!(s, "**{}** is #{} with {}", name(e), e.rank, e.score).unwrap();
+    }
+    let (ahead, behind) = if a.score >= b.score { (a, b) } else { (b, a) };
+    match ahead.score == behind.score {
+        true => writeln!(s, "They're tied."),
+        false => writeln!(
+            s,
+            "**{}** is {} ahead.",
+            name(ahead),
+            ahead.score - behind.score
+        ),
+    }
+    .unwrap();
+
+    let Some((by_a, by_b)) = improved else {
+        return s;
+    };
+    let (more, by_more, by_less) = if by_a >= by_b {
+        (a, by_a, by_b)
+    } else {
+        (b, by_b, by_a)
+    };
+    match by_more == by_less {
+        true if by_more == 0.0 => writeln!(
+            s,
+            "Neither of them got a pb in the last {} days.",
+            COMPARE_DAYS
+        ),
+        true => writeln!(
+            s,
+            "They both improved by {} in the last {} days.",
+            by_more, COMPARE_DAYS
+        ),
+        false => writeln!(
+            s,
+            "**{}** improved more in the last {} days, by {} to {}.",
+            name(more),
+            COMPARE_DAYS,
+            by_more,
+            by_less
+        ),
+    }
+    .unwrap();
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::lb::Entry;
+
+    fn entry(rank: u16, name: &str, score: f32) -> Entry {
+        Entry {
+            rank,
+            name: name.to_string(),
+            user_id: rank as u32,
+            run_id: rank as u32,
+            score,
+        }
+    }
+
+    #[test]
+    fn test_top() {
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(1_700_000_000),
+            entries: vec![entry(1, "possm", 410.0), entry(2, "fenne_kal", 399.5)],
+        };
+
+        assert_eq!(
+            top(&lb, "main"),
+            "**Top 10 on main**\n\
+             #1 **possm** 410\n\
+             #2 **fenne\\_kal** 399.5\n\
+             As of <t:1700000000:R>\n"
+        );
+    }
+
+    #[test]
+    fn test_rank() {
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(1_700_000_000),
+            entries: vec![
+                entry(1, "possm", 410.0),
+                entry(2, "fennekal", 399.5),
+                entry(2, "tied", 399.5),
+                entry(4, "bintr", 396.0),
+            ],
+        };
+
+        assert_eq!(
+            rank(&lb, "Bintr"),
+            "**bintr** is #4 with 396, 3.5 behind **tied** at #2.\n\
+             Watch in-game: hyperdemon://run/4\n"
+        );
+        assert_eq!(
+            rank(&lb, "tied"),
+            "**tied** is #2 with 399.5, 10.5 behind **possm** at #1.\n\
+             Watch in-game: hyperdemon://run/2\n"
+        );
+        assert!(rank(&lb, "possm").contains(", the world record."));
+        assert_eq!(rank(&lb, "nobody"), "nobody isn't on the board.");
+    }
+
+    #[test]
+    fn test_compare() {
+        let (a, b) = (entry(4, "bintr", 396.0), entry(1, "possm", 410.0));
+
+        assert_eq!(
+            compare(&a, &b, Some((12.5, 3.0))),
+            "**bintr** is #4 with 396\n\
+             **possm** is #1 with 410\n\
+             **possm** is 14 ahead.\n\
+             **bintr** improved more in the last 30 days, by 12.5 to 3.\n"
+        );
+        // without a store
+        assert_eq!(
+            compare(&a, &a, None),
+            "**bintr** is #4 with 396\n\
+             **bintr** is #4 with 396\n\
+             They're tied.\n"
+        );
+        assert!(compare(&a, &b, Some((0.0, 0.0)))
+            .ends_with("Neither of them got a pb in the last 30 days.\n"));
+    }
+}
@@ -0,0 +1,248 @@
+use std::env;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::io;
+
+use crate::baseline::safe_name;
+use crate::lb::{Encoding, Leaderboard};
+
+/// where to keep caches and snapshots when there's no disk to keep them on
+///
+/// anything that speaks s3 works, the bucket is addressed by path
+/// so it doesn't need dns set up for it. the keys can come from the
+/// environment like webhooks, and fall back to `AWS_ACCESS_KEY_ID`
+/// and `AWS_SECRET_ACCESS_KEY`.
+///
+/// ```toml
+/// [s3]
+/// endpoint = "https://s3.eu-central-1.amazonaws.com"
+/// bucket = "hdget"
+/// region = "eu-central-1"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BucketConfig {
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+/// the latest cache of every board, and every snapshot, in a bucket
+///
+/// the cache goes at `<board>/latest`, snapshots at
+/// `<board>/snapshots/<timestamp>`, both in the cache format
+#[derive(Clone)]
+pub struct Bucket {
+    client: Client,
+    config: BucketConfig,
+    access_key: String,
+    secret_key: String,
+    compress: bool,
+}
+
+impl Bucket {
+    pub fn new(config: &BucketConfig, compress: bool) -> io::Result<Self> {
+        let key = |set: &Option<String>, var| {
+            set.clone().or_else(|| env::var(var).ok()).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("no s3 credentials, set them in the config or {}", var),
+                )
+            })
+        };
+
+        Ok(Self {
+            client: Client::new(),
+            access_key: key(&config.access_key, "AWS_ACCESS_KEY_ID")?,
+            secret_key: key(&config.secret_key, "AWS_SECRET_ACCESS_KEY")?,
+            config: config.clone(),
+            compress,
+        })
+    }
+
+    /// the last cache that got uploaded for a board
+    pub async fn latest(&self, board: &str) -> io::Result<Option<Leaderboard>> {
+        match self.get(&key(board, "latest")?).await? {
+            Some(bytes) => Leaderboard::from_bytes(&bytes).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// replace the cache of a board
+    pub async fn save_latest(&self, board: &str, lb: &Leaderboard) -> io::Result<()> {
+        let bytes = lb.to_bytes(Encoding::Binary, self.compress).await?;
+        self.put(&key(board, "latest")?, bytes).await
+    }
+
+    /// keep a snapshot, under its timestamp
+    pub async fn save_snapshot(&self, board: &str, lb: &Leaderboard) -> io::Result<()> {
+        let bytes = lb.to_bytes(Encoding::Binary, self.compress).await?;
+        let name = format!("snapshots/{}", lb.timestamp().as_secs());
+        self.put(&key(board, &name)?, bytes).await
+    }
+
+    async fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        let res = self.request(Method::GET, key, Vec::new()).await?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let res = res.error_for_status().map_err(io::Error::other)?;
+        let bytes = res.bytes().await.map_err(io::Error::other)?;
+        Ok(Some(bytes.to_vec()))
+    }
+
+    async fn put(&self, key: &str, body: Vec<u8>) -> io::Result<()> {
+        self.request(Method::PUT, key, body)
+            .await?
+            .error_for_status()
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    /// send a request, signed with sigv4
+    async fn request(
+        &self,
+        method: Method,
+        key: &str,
+        body: Vec<u8>,
+    ) -> io::Result<reqwest::Response> {
+        let path = format!("/{}/{}", self.config.bucket, key);
+        let url = format!("{}{}", self.config.endpoint.trim_end_matches('/'), path);
+        let host = reqwest::Url::parse(&url)
+            .ok()
+            .and_then(|u| {
+                let host = u.host_str()?.to_string();
+                Some(match u.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host,
+                })
+            })
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} isn't a valid s3 endpoint", self.config.endpoint),
+                )
+            })?;
+
+        let now = Utc::now();
+        let datetime = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload = hex(&Sha256::digest(&body));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method,
+            encode_path(&path),
+            host,
+            payload,
+            datetime,
+            signed_headers,
+            payload
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            datetime,
+            scope,
+            hex(&Sha256::digest(canonical.as_bytes()))
+        );
+        let signing = signing_key(&self.secret_key, &date, &self.config.region, "s3");
+        let signature = hex(&hmac(&signing, to_sign.as_bytes()));
+
+        self.client
+            .request(method, &url)
+            .header("x-amz-date", datetime)
+            .header("x-amz-content-sha256", payload)
+            .header(
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key, scope, signed_headers, signature
+                ),
+            )
+            .body(body)
+            .send()
+            .await
+            .map_err(io::Error::other)
+    }
+}
+
+/// where something of a board goes in the bucket
+fn key(board: &str, name: &str) -> io::Result<String> {
+    if !safe_name(board) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "board names can only have letters, numbers, '-', '_' and '.' in them",
+        ));
+    }
+
+    Ok(format!("{}/{}", board, name))
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac takes any key size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let key = hmac(&key, region.as_bytes());
+    let key = hmac(&key, service.as_bytes());
+    hmac(&key, b"aws4_request")
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// percent encode a path the way sigv4 wants it
+fn encode_path(path: &str) -> String {
+    let mut out = String::new();
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_signing() {
+        // the example from the aws docs
+        let signing = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&signing),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+
+        assert_eq!(encode_path("/hdget/main/latest"), "/hdget/main/latest");
+        assert_eq!(encode_path("/a b/c+d"), "/a%20b/c%2Bd");
+        assert!(key("../main", "latest").is_err());
+    }
+}
@@ -0,0 +1,808 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use tokio::io;
+use tracing::info;
+
+use crate::archive::Retention;
+use crate::bucket::BucketConfig;
+use crate::digest::DigestConfig;
+use crate::filter::EventFilter;
+use crate::hook::Thread;
+use crate::lb::{DiffOptions, Encoding, Event, EventKind, Severity};
+use crate::mentions::Mentions;
+use crate::notify;
+use crate::notify::http::{self, HttpConfig};
+use crate::notify::mastodon::{self, MastodonConfig};
+use crate::notify::ntfy::NtfyConfig;
+use crate::notify::telegram::{self, TelegramConfig};
+use crate::quiet::QuietHours;
+use crate::render::{Locale, Templates};
+use crate::replay::Replays;
+use crate::rivals::Rivalry;
+use crate::rules::Rule;
+use crate::source::Source;
+use crate::store::Backend;
+use crate::validate::Strictness;
+
+/// the whole config file
+///
+/// read from `hdget.toml` by default. every `[[board]]` table
+/// describes one leaderboard that gets polled on its own.
+///
+/// ```toml
+/// [[board]]
+/// name = "main"
+/// webhook = "https://discord.com/api/webhooks/..."
+/// ```
+///
+/// webhooks can come from the environment instead, so they
+/// don't have to be in the file, like `webhook = "env:MAIN_WEBHOOK"`
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    #[serde(rename = "board")]
+    pub boards: Vec<BoardConfig>,
+    /// where to log sent messages, see `sent::SentLog`
+    #[serde(default = "default_sent_log")]
+    pub sent_log: PathBuf,
+    /// where to log every decision the bot makes, see `audit::Audit`
+    #[serde(default = "default_audit_log")]
+    pub audit_log: PathBuf,
+    /// where to write every event as json, see `sink::Sink`
+    pub event_sink: Option<PathBuf>,
+    /// seconds during which a run won't be announced twice
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: u64,
+    /// seconds old the baseline can be at startup before what's found
+    /// against it goes out as one summary, instead of a message for
+    /// every event. 0 turns it off
+    #[serde(default = "default_stale_after")]
+    pub stale_after: u64,
+    /// how many requests per second to send to the site, across all boards
+    #[serde(default = "default_requests_per_second")]
+    pub requests_per_second: f64,
+    /// where to keep the latest cycle reports, for `hdget status`
+    #[serde(default = "default_status_file")]
+    pub status_file: PathBuf,
+    /// channel for the people running the bot
+    pub operator: Option<OperatorConfig>,
+    /// bot for boards that send to telegram chats
+    pub telegram: Option<TelegramConfig>,
+    /// account for targets that post to mastodon
+    pub mastodon: Option<MastodonConfig>,
+    /// addresses to email digests to
+    pub email: Option<EmailConfig>,
+    /// discord bot that answers slash commands, and posts for
+    /// boards with a `bot:` channel as their webhook
+    pub bot: Option<BotConfig>,
+    /// json endpoints boards can send to
+    #[serde(default)]
+    pub http: Vec<HttpConfig>,
+    /// server for targets that push to ntfy topics
+    #[serde(default)]
+    pub ntfy: NtfyConfig,
+    /// discord users who get pinged about their pbs
+    #[serde(default)]
+    pub mentions: Mentions,
+    /// if set, pages that couldn't be parsed get saved here
+    pub debug_dir: Option<PathBuf>,
+    /// where named baselines are kept
+    #[serde(default = "default_baselines_dir")]
+    pub baselines_dir: PathBuf,
+    /// seconds between full writes of the cache, changes in between
+    /// only go to the journal. 0 writes the cache every cycle
+    #[serde(default = "default_persist_interval")]
+    pub persist_interval: u64,
+    /// where annotations like patch releases are kept
+    #[serde(default = "default_annotations")]
+    pub annotations: PathBuf,
+    /// how to write the caches, see `lb::Encoding`
+    #[serde(default)]
+    pub cache_format: Encoding,
+    /// compress the caches with zstd
+    #[serde(default)]
+    pub compress_cache: bool,
+    /// where the caches go, unless a board sets its own.
+    /// defaults to `$XDG_DATA_HOME/hdget` or wherever the
+    /// platform keeps that kind of thing
+    #[serde(default = "default_data_dir")]
+    pub data_dir: PathBuf,
+    /// keep the baselines in memory only, and never write a cache,
+    /// journal or outbox. everything starts over on a restart.
+    /// also set by `--no-cache`
+    #[serde(default)]
+    pub memory_only: bool,
+    /// where to keep every snapshot, see `archive::Archive`
+    pub archive: Option<PathBuf>,
+    /// keep every nth snapshot whole, and only what changed in between.
+    /// every snapshot is kept whole by default
+    pub archive_full_every: Option<u32>,
+    /// how long archived snapshots are kept by `hdget prune`
+    #[serde(default)]
+    pub retention: Retention,
+    /// sqlite database to keep the history in, see `store::Store`
+    pub store: Option<PathBuf>,
+    /// what the store is kept in, see `store::Backend`
+    #[serde(default)]
+    pub store_backend: Backend,
+    /// postgres to share baselines with other instances through,
+    /// see `shared::Shared`. needs the `postgres` feature. it has
+    /// the password in it, so it can come from the environment
+    pub postgres: Option<String>,
+    /// bucket to keep caches and snapshots in, see `bucket::Bucket`
+    pub s3: Option<BucketConfig>,
+}
+
+/// where to tell the people running the bot about things
+///
+/// removed scores go here too, instead of to the board's webhook
+#[derive(Debug, Clone, Deserialize)]
+pub struct OperatorConfig {
+    /// discord webhook of the operator channel
+    pub webhook: String,
+    /// post a report after every single cycle
+    #[serde(default)]
+    pub verbose: bool,
+}
+
+/// where digests get emailed, on top of going to the board's
+/// webhook. for the moderators who don't watch the chat.
+/// needs the `email` feature, see `notify::email::Email`
+///
+/// ```toml
+/// [email]
+/// server = "smtp.example.com"
+/// username = "hdget@example.com"
+/// password = "env:SMTP_PASSWORD"
+/// from = "hdget <hdget@example.com>"
+/// to = ["mods@example.com"]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    /// talked to with starttls
+    pub server: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    /// can come from the environment like webhooks
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+/// a discord bot, connected to the gateway instead of posting
+/// through webhooks, so people can ask it things with slash
+/// commands. needs the `bot` feature, see `bot::Bot`
+///
+/// boards post through it with `bot:<channel id>` as the webhook
+///
+/// ```toml
+/// [bot]
+/// token = "env:DISCORD_TOKEN"
+/// guild = 123456789012345678
+///
+/// [[board]]
+/// webhook = "bot:123456789012345678"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BotConfig {
+    /// can come from the environment like webhooks
+    pub token: String,
+    /// server to register the commands in, where they show up
+    /// right away. they're global otherwise, which takes a while
+    pub guild: Option<u64>,
+}
+
+/// config for a single leaderboard
+#[derive(Debug, Clone, Deserialize)]
+pub struct BoardConfig {
+    /// name of the board, used in logs and as the default cache name
+    pub name: String,
+    /// where to get the leaderboard from, scrapes the site by default
+    #[serde(default)]
+    pub source: Source,
+    /// where to keep the cache, defaults to `<name>.cache` in `data_dir`
+    pub cache: Option<PathBuf>,
+    /// discord or slack webhook, or telegram chat, to send
+    /// notifications to
+    pub webhook: String,
+    /// thread of the discord webhook's channel to send into,
+    /// see `hook::Thread`
+    pub thread: Option<Thread>,
+    /// more webhooks that get a copy of what goes to this one
+    #[serde(default)]
+    pub targets: Vec<Target>,
+    /// seconds to wait between polls
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    /// how many entries the board should have
+    ///
+    /// defaults to however many the last snapshot had
+    pub expected_size: Option<usize>,
+    /// how far off the expected size a board can be before
+    /// it gets warned about, as a fraction of the expected size
+    #[serde(default = "default_size_tolerance")]
+    pub size_tolerance: f64,
+    /// what counts as a pb
+    #[serde(default)]
+    pub diff: DiffOptions,
+    /// which events get sent, everything by default
+    #[serde(default)]
+    pub filter: EventFilter,
+    /// what to do with leaderboards that fail validation
+    #[serde(default)]
+    pub validation: Strictness,
+    /// if set, replays of new pbs get archived
+    pub replays: Option<Replays>,
+    /// if set, a summary gets posted every day or week.
+    /// needs the store
+    pub digest: Option<DigestConfig>,
+    /// send everything from a cycle together, in as few messages
+    /// as it fits in, world records first. correcting one event
+    /// edits the whole message it went out in
+    #[serde(default)]
+    pub batch: bool,
+    /// pairs of players whose pbs say how they're doing against
+    /// each other, see `rivals::Rivalry`
+    #[serde(default)]
+    pub rivals: Vec<Rivalry>,
+    /// improvements say when someone's on a streak of pbs, like
+    /// 3 days in a row. needs the store
+    #[serde(default)]
+    pub streaks: bool,
+    /// send discord embeds, or slack blocks, instead of plain
+    /// text. batches, and messages a script wrote, are still text
+    #[serde(default)]
+    pub embeds: bool,
+    /// keep a message up to date with the current world record,
+    /// see `pinned::Pinned`
+    #[serde(default)]
+    pub pinned_record: bool,
+    /// a discord role that gets pinged for world records, and
+    /// nothing else
+    pub record_role: Option<u64>,
+    /// put a graph of the player's pbs so far on their pb embeds,
+    /// out of the store. needs the `graphs` feature
+    #[serde(default)]
+    pub graphs: bool,
+    /// hold events in the outbox during these hours, and send
+    /// them all in a batch once they're over
+    pub quiet_hours: Option<QuietHours>,
+    /// the board's own wording for text messages, see
+    /// `render::Templates`
+    #[serde(default)]
+    pub templates: Templates,
+    /// the language of the built in text messages
+    #[serde(default)]
+    pub locale: Locale,
+    /// other webhooks for bigger events, the board's webhook
+    /// gets whatever doesn't make any of them
+    #[serde(default)]
+    pub routes: Vec<Route>,
+    /// what to do with events that match, on top of the filter,
+    /// see `rules::Rule`
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// a rhai script that gets a say after the rules, see
+    /// `script::Script`. needs the `script` feature
+    pub script: Option<PathBuf>,
+}
+
+/// another webhook that gets a copy of whatever the board sends,
+/// or of as much of it as gets through its own filter
+///
+/// ```toml
+/// [[board.targets]]
+/// name = "possm-fans"
+/// webhook = "env:FANS_WEBHOOK"
+/// filter = { watchlist = ["possm"] }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    /// what the sent log and audit log call it
+    pub name: String,
+    pub webhook: String,
+    #[serde(default)]
+    pub filter: EventFilter,
+}
+
+/// a webhook for events that are at least so big of a deal, or
+/// for certain kinds of them
+///
+/// each event goes to the first route with its kind, or else the
+/// route with the highest `min_severity` it makes
+///
+/// ```toml
+/// [[board.routes]]
+/// name = "records"
+/// min_severity = "milestone"
+/// webhook = "https://discord.com/api/webhooks/..."
+///
+/// [[board.routes]]
+/// name = "ranks"
+/// kinds = ["rank_change", "entered_top"]
+/// webhook = "https://discord.com/api/webhooks/..."
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Route {
+    /// what the sent log and audit log call it
+    pub name: String,
+    pub min_severity: Option<Severity>,
+    pub kinds: Option<HashSet<EventKind>>,
+    pub webhook: String,
+}
+
+fn default_interval() -> u64 {
+    600
+}
+
+fn default_size_tolerance() -> f64 {
+    0.05
+}
+
+fn default_sent_log() -> PathBuf {
+    PathBuf::from("sent.jsonl")
+}
+
+fn default_audit_log() -> PathBuf {
+    PathBuf::from("audit.jsonl")
+}
+
+fn default_dedup_window() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_stale_after() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_requests_per_second() -> f64 {
+    1.0
+}
+
+fn default_status_file() -> PathBuf {
+    PathBuf::from("status.json")
+}
+
+fn default_baselines_dir() -> PathBuf {
+    PathBuf::from("baselines")
+}
+
+fn default_persist_interval() -> u64 {
+    60 * 60
+}
+
+fn default_annotations() -> PathBuf {
+    PathBuf::from("annotations.jsonl")
+}
+
+/// falls back to the working directory, ie. in the container
+/// where there's no home
+fn default_data_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|d| d.join("hdget"))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+impl Config {
+    /// read and parse a config file
+    pub async fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = tokio::fs::read_to_string(path).await?;
+        let mut config: Self =
+            toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        if config.boards.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "config doesn't define any boards",
+            ));
+        }
+
+        if let Some(operator) = &mut config.operator {
+            from_env(&mut operator.webhook, "the operator webhook")?;
+        }
+        for board in &mut config.boards {
+            from_env(&mut board.webhook, "a webhook")?;
+            // the board's own templates win over the locale's
+            let mut templates = board.locale.templates();
+            templates.extend(std::mem::take(&mut board.templates));
+            board.templates = templates;
+            for route in &mut board.routes {
+                from_env(&mut route.webhook, "a route")?;
+            }
+            for target in &mut board.targets {
+                from_env(&mut target.webhook, "a target")?;
+            }
+        }
+        if let Some(telegram) = &mut config.telegram {
+            from_env(&mut telegram.token, "the telegram token")?;
+        }
+        if let Some(mastodon) = &mut config.mastodon {
+            from_env(&mut mastodon.token, "the mastodon token")?;
+        }
+        if let Some(password) = config.email.as_mut().and_then(|e| e.password.as_mut()) {
+            from_env(password, "the email password")?;
+        }
+        for http in &mut config.http {
+            for value in http.headers.values_mut().chain(&mut http.secret) {
+                from_env(value, "an http header or secret")?;
+            }
+        }
+        if let Some(token) = &mut config.ntfy.token {
+            from_env(token, "the ntfy token")?;
+        }
+        if let Some(bot) = &mut config.bot {
+            from_env(&mut bot.token, "the bot token")?;
+        }
+        if let Some(postgres) = &mut config.postgres {
+            from_env(postgres, "postgres")?;
+        }
+        if let Some(s3) = &mut config.s3 {
+            for key in s3.access_key.iter_mut().chain(&mut s3.secret_key) {
+                from_env(key, "an s3 key")?;
+            }
+        }
+
+        // telegram chats, mastodon, json endpoints and the bot need to be set up
+        let operator = config.operator.iter().map(|o| &o.webhook);
+        let boards = config.boards.iter().flat_map(|b| {
+            let routes = b.routes.iter().map(|r| &r.webhook);
+            let targets = b.targets.iter().map(|t| &t.webhook);
+            [&b.webhook].into_iter().chain(routes).chain(targets)
+        });
+        for webhook in operator.chain(boards) {
+            if notify::is_desktop(webhook) && !cfg!(feature = "desktop") {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "desktop notifications need hdget built with the desktop feature",
+                ));
+            }
+            if notify::bot_channel(webhook).is_some() && !cfg!(feature = "bot") {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "bot channels need hdget built with the bot feature",
+                ));
+            }
+            let missing = match () {
+                _ if notify::bot_channel(webhook).is_some() && config.bot.is_none() => "[bot]",
+                _ if telegram::chat(webhook).is_some() && config.telegram.is_none() => {
+                    "[telegram] bot"
+                }
+                _ if mastodon::is_account(webhook) && config.mastodon.is_none() => {
+                    "[mastodon] account"
+                }
+                _ if http::endpoint(webhook)
+                    .is_some_and(|name| !config.http.iter().any(|h| h.name == name)) =>
+                {
+                    "[[http]] endpoint with that name"
+                }
+                _ => continue,
+            };
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} needs a {} in the config", webhook, missing),
+            ));
+        }
+
+        for board in &config.boards {
+            let discord_only = match () {
+                _ if board.thread.is_some() => Some("a thread"),
+                _ if board.pinned_record => Some("a pinned record"),
+                _ if board.record_role.is_some() => Some("a record role"),
+                _ => None,
+            };
+            if let (Some(what), false) = (discord_only, notify::is_discord(&board.webhook)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} has {}, which only works with discord", board.name, what),
+                ));
+            }
+
+            for route in &board.routes {
+                if route.min_severity.is_none() && route.kinds.is_none() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{} has a route with no min_severity or kinds: {}",
+                            board.name, route.name
+                        ),
+                    ));
+                }
+            }
+
+            let mut names = HashSet::new();
+            let targets = board.targets.iter().map(|t| &t.name);
+            for name in board.routes.iter().map(|r| &r.name).chain(targets) {
+                if !names.insert(name) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{} has more than one route or target called {}",
+                            board.name, name
+                        ),
+                    ));
+                }
+            }
+
+            let routes = board.rules.iter().filter_map(|r| r.then.send_to.as_ref());
+            for route in routes {
+                if !board.routes.iter().any(|r| &r.name == route) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{} has a rule for a route it doesn't have: {}",
+                            board.name, route
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for board in &mut config.boards {
+            if board.cache.is_none() {
+                board.cache = Some(config.data_dir.join(format!("{}.cache", board.name)));
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// make the data dir, and move caches over from where older
+    /// versions kept them
+    ///
+    /// those went in the working directory, as `<name>.cache`,
+    /// or just `cache` from back when there was a single board.
+    /// a cache that's already in place is never replaced
+    pub async fn adopt_old_caches(&self) -> io::Result<()> {
+        tokio::fs::create_dir_all(&self.data_dir).await?;
+
+        for (i, board) in self.boards.iter().enumerate() {
+            let cache = board.cache_path();
+            if tokio::fs::try_exists(&cache).await? {
+                continue;
+            }
+
+            let mut old = vec![PathBuf::from(format!("{}.cache", board.name))];
+            if i == 0 {
+                old.push(PathBuf::from("cache"));
+            }
+            for old in old {
+                if old == cache || !tokio::fs::try_exists(&old).await? {
+                    continue;
+                }
+
+                info!(board = %board.name, from = %old.display(), to = %cache.display(), "moving cache");
+                move_file(&old, &cache).await?;
+                // these only make sense next to the cache they're for
+                for ext in ["journal", "outbox"] {
+                    let from = old.with_extension(ext);
+                    if tokio::fs::try_exists(&from).await? {
+                        move_file(&from, &cache.with_extension(ext)).await?;
+                    }
+                }
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// look up a board by name
+    pub fn board(&self, name: &str) -> Option<&BoardConfig> {
+        self.boards.iter().find(|b| b.name == name)
+    }
+
+    /// either one board by name, or all of them if there's no name
+    pub fn select(&self, name: Option<&str>) -> io::Result<Vec<&BoardConfig>> {
+        match name {
+            None => Ok(self.boards.iter().collect()),
+            Some(name) => match self.board(name) {
+                Some(board) => Ok(vec![board]),
+                None => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("there's no board called {}", name),
+                )),
+            },
+        }
+    }
+}
+
+/// swap `env:NAME` for the NAME environment variable, `what` says
+/// which setting it was for if it isn't set
+fn from_env(value: &mut String, what: &str) -> io::Result<()> {
+    if let Some(var) = value.strip_prefix("env:") {
+        *value = std::env::var(var).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the environment variable {} for {} isn't set", var, what),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// rename, or copy if it's going to another filesystem
+async fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if tokio::fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+    tokio::fs::copy(from, to).await?;
+    tokio::fs::remove_file(from).await
+}
+
+impl BoardConfig {
+    /// path of the cache file for this board
+    pub fn cache_path(&self) -> PathBuf {
+        self.cache
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}.cache", self.name)))
+    }
+
+    /// path of the journal of changes since the cache was written
+    pub fn journal_path(&self) -> PathBuf {
+        self.cache_path().with_extension("journal")
+    }
+
+    /// path of the messages that still have to go out
+    pub fn outbox_path(&self) -> PathBuf {
+        self.cache_path().with_extension("outbox")
+    }
+
+    /// path of the id of the pinned record message
+    pub fn pinned_path(&self) -> PathBuf {
+        self.cache_path().with_extension("pinned")
+    }
+
+    /// where an event goes, none for the board's webhook
+    pub fn route(&self, pb: &Event) -> Option<&Route> {
+        let kind = pb.kind();
+        let has_kind = |r: &&Route| r.kinds.as_ref().is_some_and(|k| k.contains(&kind));
+        if let Some(route) = self.routes.iter().find(has_kind) {
+            return Some(route);
+        }
+        self.routes
+            .iter()
+            .filter_map(|r| Some((r, r.min_severity?)))
+            .filter(|(_, min)| pb.severity() >= *min)
+            .max_by_key(|(_, min)| *min)
+            .map(|(r, _)| r)
+    }
+
+    /// the webhook of a route or target by name, or the board's own.
+    /// ones that aren't in the config anymore go to the board's as well
+    pub fn webhook(&self, route: Option<&str>) -> &str {
+        let routes = self.routes.iter().map(|r| (&r.name, &r.webhook));
+        let mut all = routes.chain(self.targets.iter().map(|t| (&t.name, &t.webhook)));
+        all.find(|(name, _)| Some(name.as_str()) == route)
+            .map_or(&self.webhook, |(_, webhook)| webhook)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// load a config file with `text` in it
+    async fn load(text: &str) -> io::Result<Config> {
+        static N: AtomicUsize = AtomicUsize::new(0);
+        let n = N.fetch_add(1, Ordering::Relaxed);
+        let path =
+            std::env::temp_dir().join(format!("hdget-config-{}-{}.toml", std::process::id(), n));
+        tokio::fs::write(&path, text).await?;
+        let config = Config::load(&path).await;
+        tokio::fs::remove_file(&path).await?;
+        config
+    }
+
+    /// the error loading a config with a board that has `board` in it
+    async fn error(board: &str) -> String {
+        let text = format!("[[board]]\nname = \"main\"\n{}", board);
+        load(&text).await.unwrap_err().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_from_env() {
+        std::env::set_var("HDGET_TEST_WEBHOOK", "https://discord.com/api/webhooks/1/a");
+        let config = load("[[board]]\nname = \"main\"\nwebhook = \"env:HDGET_TEST_WEBHOOK\"")
+            .await
+            .unwrap();
+        assert_eq!(
+            config.boards[0].webhook,
+            "https://discord.com/api/webhooks/1/a"
+        );
+
+        let mut value = "env:HDGET_TEST_UNSET".to_string();
+        let e = from_env(&mut value, "the telegram token").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            e.to_string(),
+            "the environment variable HDGET_TEST_UNSET for the telegram token isn't set"
+        );
+        // anything else stays as it is
+        let mut value = "environment".to_string();
+        from_env(&mut value, "postgres").unwrap();
+        assert_eq!(value, "environment");
+    }
+
+    #[tokio::test]
+    async fn test_names() {
+        let route = "name = \"records\"\nmin_severity = \"milestone\"\nwebhook = \"\"";
+        assert_eq!(
+            error(&format!(
+                "webhook = \"\"\n[[board.routes]]\n{0}\n[[board.routes]]\n{0}",
+                route
+            ))
+            .await,
+            "main has more than one route or target called records"
+        );
+        assert_eq!(
+            error(&format!(
+                "webhook = \"\"\n[[board.routes]]\n{}\n[[board.targets]]\nname = \"records\"\nwebhook = \"\"",
+                route
+            ))
+            .await,
+            "main has more than one route or target called records"
+        );
+        assert_eq!(
+            error(&format!(
+                "webhook = \"\"\n[[board.routes]]\n{}\n[[board.rules]]\nthen = {{ send_to = \"ranks\" }}",
+                route
+            ))
+            .await,
+            "main has a rule for a route it doesn't have: ranks"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_sections() {
+        assert_eq!(
+            error("webhook = \"telegram:@possm\"").await,
+            "telegram:@possm needs a [telegram] bot in the config"
+        );
+        assert_eq!(
+            error("webhook = \"mastodon\"").await,
+            "mastodon needs a [mastodon] account in the config"
+        );
+        assert_eq!(
+            error("webhook = \"\"\n[[board.targets]]\nname = \"site\"\nwebhook = \"http:site\"")
+                .await,
+            "http:site needs a [[http]] endpoint with that name in the config"
+        );
+        let config = load(
+            "[[board]]\nname = \"main\"\nwebhook = \"http:site\"\n\
+             [[http]]\nname = \"site\"\nurl = \"https://example.com/hook\"",
+        )
+        .await
+        .unwrap();
+        assert_eq!(config.http[0].name, "site");
+    }
+
+    #[tokio::test]
+    async fn test_features() {
+        let bot = error("webhook = \"bot:123456789012345678\"").await;
+        match cfg!(feature = "bot") {
+            true => assert_eq!(bot, "bot:123456789012345678 needs a [bot] in the config"),
+            false => assert_eq!(bot, "bot channels need hdget built with the bot feature"),
+        }
+
+        let desktop = load("[[board]]\nname = \"main\"\nwebhook = \"desktop\"").await;
+        match cfg!(feature = "desktop") {
+            true => assert_eq!(desktop.unwrap().boards[0].webhook, "desktop"),
+            false => assert_eq!(
+                desktop.unwrap_err().to_string(),
+                "desktop notifications need hdget built with the desktop feature"
+            ),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+use crate::annotate::Annotation;
+use crate::history::span;
+use crate::lb::EventKind;
+use crate::store::Emitted;
+
+/// a summary of a board posted every so often, out of the store.
+/// it goes out at midnight utc, and weekly ones on mondays
+///
+/// ```toml
+/// [board.digest]
+/// every = "day"
+/// instead = true
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DigestConfig {
+    pub every: Period,
+    /// only post the digest, and none of the events as they happen
+    #[serde(default)]
+    pub instead: bool,
+}
+
+/// how often a digest goes out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Day,
+    Week,
+}
+
+impl Period {
+    pub fn secs(self) -> u64 {
+        match self {
+            Self::Day => 86400,
+            Self::Week => 7 * 86400,
+        }
+    }
+
+    /// the last whole day or week before `now`, in utc, with
+    /// weeks starting on monday
+    pub fn window(self, now: u64) -> (u64, u64) {
+        let to = now - self.since_start(now);
+        (to.saturating_sub(self.secs()), to)
+    }
+
+    /// seconds from `now` until the next day or week starts
+    pub fn until_next(self, now: u64) -> u64 {
+        self.secs() - self.since_start(now)
+    }
+
+    /// seconds since the day or week `now` is in started.
+    /// the epoch was on a thursday
+    fn since_start(self, now: u64) -> u64 {
+        match self {
+            Self::Day => now % 86400,
+            Self::Week => (now + 3 * 86400) % (7 * 86400),
+        }
+    }
+
+    fn word(self) -> &'static str {
+        match self {
+            Self::Day => "today",
+            Self::Week => "this week",
+        }
+    }
+}
+
+/// the message for everything that happened on a board over a period,
+/// none if nothing did. `annotations` from the period go at the end,
+/// for what might've had something to do with it
+pub fn digest(
+    board: &str,
+    period: Period,
+    events: &[Emitted],
+    annotations: &[Annotation],
+) -> Option<String> {
+    let mut s = summary(events, |pbs| {
+        format!("[{}] {} pbs {}", board, pbs, period.word())
+    })?;
+    for a in annotations {
+        writeln!(s, "also {}: {}", period.word(), a.text).unwrap();
+    }
+    Some(s)
+}
+
+/// the one message for everything found against a baseline that
+/// was `gap` seconds old, instead of a flood of them
+pub fn catch_up(board: &str, gap: u64, events: &[Emitted]) -> Option<String> {
+    summary(events, |pbs| {
+        format!(
+            "[{}] the last board was {} old, so here's everything since in one go: {} pbs",
+            board,
+            span(gap),
+            pbs
+        )
+    })
+}
+
+/// the pbs in `events` added up, after a first line that gets how many there were
+fn summary(events: &[Emitted], intro: impl FnOnce(usize) -> String) -> Option<String> {
+    // only the ones where someone did something
+    let pbs: Vec<_> = events.iter().filter(|e| e.kind.is_pb()).collect();
+    if pbs.is_empty() {
+        return None;
+    }
+
+    // writing to a String can't fail
+    let mut s = intro(pbs.len());
+    let biggest = pbs
+        .iter()
+        .filter_map(|e| Some((e, e.score - e.old_score?)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((e, gain)) = biggest {
+        write!(s, ", biggest gain: {} +{}", e.name, gain).unwrap();
+    }
+    writeln!(s).unwrap();
+
+    let count = |kind| pbs.iter().filter(|e| e.kind == kind).count();
+    let new = count(EventKind::NewPlayer);
+    if new > 0 {
+        let plural = if new == 1 { "" } else { "s" };
+        writeln!(s, "{} new player{}", new, plural).unwrap();
+    }
+    for wr in pbs.iter().filter(|e| e.kind == EventKind::WorldRecord) {
+        writeln!(s, "new world record: {} with {}", wr.name, wr.score).unwrap();
+    }
+
+    Some(s)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(kind: EventKind, name: &str, score: f32, old_score: Option<f32>) -> Emitted {
+        Emitted {
+            board: "main".to_string(),
+            timestamp: 600,
+            kind,
+            user_id: 1,
+            name: name.to_string(),
+            run_id: 1,
+            rank: 1,
+            score,
+            old_rank: old_score.map(|_| 2),
+            old_score,
+        }
+    }
+
+    #[test]
+    fn test_window() {
+        // thursday 2026-10-15, at noon
+        let now = 1792065600;
+        assert_eq!(Period::Day.window(now), (1791936000, 1792022400));
+        assert_eq!(Period::Day.until_next(now), 43200);
+        // the week before, monday to monday
+        assert_eq!(Period::Week.window(now), (1791158400, 1791763200));
+        assert_eq!(Period::Week.until_next(now), 1792368000 - now);
+        // right at midnight, it's the day that just ended
+        assert_eq!(Period::Day.window(1792022400), (1791936000, 1792022400));
+    }
+
+    #[test]
+    fn test_digest() {
+        let events = [
+            event(EventKind::Pb, "possm", 390.5, Some(390.0)),
+            event(EventKind::WorldRecord, "fennekal", 411.0, Some(400.0)),
+            event(EventKind::NewPlayer, "someone", 300.0, None),
+            event(EventKind::RankChange, "possm", 390.5, Some(390.5)),
+        ];
+
+        let patch = [Annotation {
+            text: "patch 1.2 came out".to_string(),
+            at: 300,
+        }];
+        assert_eq!(
+            digest("main", Period::Day, &events, &patch).unwrap(),
+            "[main] 3 pbs today, biggest gain: fennekal +11\n\
+             1 new player\n\
+             new world record: fennekal with 411\n\
+             also today: patch 1.2 came out\n"
+        );
+        assert_eq!(digest("main", Period::Week, &events[3..], &patch), None);
+        assert!(catch_up("main", 3 * 86400, &events)
+            .unwrap()
+            .starts_with("[main] the last board was 3 days old, so here's everything since in one go: 3 pbs, biggest gain"));
+    }
+}
@@ -0,0 +1,202 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::lb::Leaderboard;
+
+#[cfg(feature = "parquet")]
+mod parquet;
+
+#[cfg(feature = "parquet")]
+pub use parquet::Parquet;
+
+/// what `hdget export` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Csv,
+    /// a board per line, like a json cache with the board's name in it
+    Json,
+    /// needs the `parquet` feature
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+/// writes boards out in any of the formats
+pub enum Exporter<W: Write + Send> {
+    Csv {
+        w: W,
+        header: bool,
+    },
+    Json(W),
+    #[cfg(feature = "parquet")]
+    Parquet(Parquet<W>),
+}
+
+impl<W: Write + Send> Exporter<W> {
+    pub fn new(format: Format, w: W) -> io::Result<Self> {
+        Ok(match format {
+            Format::Csv => Self::Csv { w, header: true },
+            Format::Json => Self::Json(w),
+            #[cfg(feature = "parquet")]
+            Format::Parquet => Self::Parquet(Parquet::new(w)?),
+        })
+    }
+
+    pub fn write(&mut self, board: &str, lb: &Leaderboard) -> io::Result<()> {
+        match self {
+            Self::Csv { w, header } => {
+                csv(w, board, lb, *header)?;
+                *header = false;
+                Ok(())
+            }
+            Self::Json(w) => json(w, board, lb),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(p) => p.write(board, lb),
+        }
+    }
+
+    /// write out anything that's left
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Csv { mut w, .. } | Self::Json(mut w) => w.flush(),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(p) => p.close(),
+        }
+    }
+}
+
+/// write boards out as csv, one row per entry
+///
+/// the header is only written if `header` is set, so
+/// several boards can go into the same file
+pub fn csv(w: &mut impl Write, board: &str, lb: &Leaderboard, header: bool) -> io::Result<()> {
+    if header {
+        writeln!(w, "board,timestamp,rank,name,user_id,run_id,score")?;
+    }
+
+    let timestamp = lb.timestamp.as_secs();
+    for e in &lb.entries {
+        writeln!(
+            w,
+            "{},{},{},{},{},{},{}",
+            csv_field(board),
+            timestamp,
+            e.rank,
+            csv_field(&e.name),
+            e.user_id,
+            e.run_id,
+            e.score
+        )?;
+    }
+
+    Ok(())
+}
+
+/// write a board out as a line of json
+pub fn json(w: &mut impl Write, board: &str, lb: &Leaderboard) -> io::Result<()> {
+    #[derive(Serialize)]
+    struct Board<'a> {
+        board: &'a str,
+        #[serde(flatten)]
+        lb: &'a Leaderboard,
+    }
+
+    serde_json::to_writer(&mut *w, &Board { board, lb })?;
+    writeln!(w)
+}
+
+/// quote a field if it needs it
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::lb::Entry;
+
+    #[test]
+    fn test_csv() {
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![Entry {
+                rank: 1,
+                name: "possm, \"the\" possum".to_string(),
+                user_id: 1,
+                run_id: 2,
+                score: 400.5,
+            }],
+        };
+
+        let mut out = Vec::new();
+        csv(&mut out, "main", &lb, true).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "board,timestamp,rank,name,user_id,run_id,score\n\
+             main,600,1,\"possm, \"\"the\"\" possum\",1,2,400.5\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![Entry {
+                rank: 1,
+                name: "possm".to_string(),
+                user_id: 1,
+                run_id: 2,
+                score: 400.5,
+            }],
+        };
+
+        let mut exporter = Exporter::new(Format::Json, Vec::new()).unwrap();
+        exporter.write("main", &lb).unwrap();
+        exporter.write("main", &lb).unwrap();
+        let Exporter::Json(out) = exporter else {
+            unreachable!()
+        };
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 2);
+        // reads back like a json cache
+        let read: Leaderboard = serde_json::from_str(out.lines().next().unwrap()).unwrap();
+        assert_eq!(read.timestamp, lb.timestamp);
+        assert_eq!(read.entries[0].name, "possm");
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet() {
+        use ::parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![Entry {
+                rank: 1,
+                name: "possm".to_string(),
+                user_id: 1,
+                run_id: 2,
+                score: 400.5,
+            }],
+        };
+
+        let path = std::env::temp_dir().join(format!("hdget-{}.parquet", std::process::id()));
+        let mut exporter =
+            Exporter::new(Format::Parquet, std::fs::File::create(&path).unwrap()).unwrap();
+        exporter.write("main", &lb).unwrap();
+        exporter.write("main", &lb).unwrap();
+        exporter.finish().unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+    }
+}
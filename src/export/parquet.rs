@@ -0,0 +1,89 @@
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, DataType, FloatType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+use parquet::schema::parser::parse_message_type;
+
+use crate::lb::Leaderboard;
+
+const SCHEMA: &str = "
+message leaderboard {
+    REQUIRED BYTE_ARRAY board (UTF8);
+    REQUIRED INT64 timestamp;
+    REQUIRED INT32 rank;
+    REQUIRED BYTE_ARRAY name (UTF8);
+    REQUIRED INT64 user_id;
+    REQUIRED INT64 run_id;
+    REQUIRED FLOAT score;
+}
+";
+
+/// writes boards to a parquet file, with the same columns as the csv
+///
+/// every board goes in its own row group. nothing is readable until
+/// `close` is called.
+pub struct Parquet<W: Write + Send> {
+    writer: SerializedFileWriter<W>,
+}
+
+impl<W: Write + Send> Parquet<W> {
+    pub fn new(w: W) -> io::Result<Self> {
+        let schema = Arc::new(parse_message_type(SCHEMA).map_err(io::Error::other)?);
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer =
+            SerializedFileWriter::new(w, schema, Arc::new(props)).map_err(io::Error::other)?;
+
+        Ok(Self { writer })
+    }
+
+    pub fn write(&mut self, board: &str, lb: &Leaderboard) -> io::Result<()> {
+        let n = lb.entries.len();
+        let mut rg = self.writer.next_row_group().map_err(io::Error::other)?;
+
+        column::<ByteArrayType, _>(&mut rg, &vec![ByteArray::from(board); n])?;
+        column::<Int64Type, _>(&mut rg, &vec![lb.timestamp.as_secs() as i64; n])?;
+        let ranks: Vec<_> = lb.entries.iter().map(|e| e.rank as i32).collect();
+        column::<Int32Type, _>(&mut rg, &ranks)?;
+        let names: Vec<_> = lb
+            .entries
+            .iter()
+            .map(|e| ByteArray::from(e.name.as_str()))
+            .collect();
+        column::<ByteArrayType, _>(&mut rg, &names)?;
+        let users: Vec<_> = lb.entries.iter().map(|e| e.user_id as i64).collect();
+        column::<Int64Type, _>(&mut rg, &users)?;
+        let runs: Vec<_> = lb.entries.iter().map(|e| e.run_id as i64).collect();
+        column::<Int64Type, _>(&mut rg, &runs)?;
+        let scores: Vec<_> = lb.entries.iter().map(|e| e.score).collect();
+        column::<FloatType, _>(&mut rg, &scores)?;
+
+        rg.close().map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    /// write the footer, the file's no good without it
+    pub fn close(self) -> io::Result<()> {
+        self.writer.close().map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+/// write the next column of a row group
+fn column<T: DataType, W: Write + Send>(
+    rg: &mut SerializedRowGroupWriter<'_, W>,
+    values: &[T::T],
+) -> io::Result<()> {
+    let mut col = rg
+        .next_column()
+        .map_err(io::Error::other)?
+        .ok_or_else(|| io::Error::other("ran out of parquet columns"))?;
+    col.typed::<T>()
+        .write_batch(values, None, None)
+        .map_err(io::Error::other)?;
+    col.close().map_err(io::Error::other)
+}
@@ -0,0 +1,294 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::lb::{Entry, Event, EventKind, Severity};
+
+/// decides which events are worth sending
+///
+/// build one in code:
+///
+/// ```
+/// use hdget::filter::EventFilter;
+/// use hdget::lb::EventKind::*;
+///
+/// let filter = EventFilter::new().min_rank(100).event_types([Pb, WorldRecord]);
+/// ```
+///
+/// or get one out of a board's config:
+///
+/// ```toml
+/// [board.filter]
+/// min_rank = 100
+/// event_types = ["pb", "world_record"]
+/// watchlist = [1234, "possm"]
+/// mute = [5678]
+/// min_gain = 0.1
+/// min_severity = "pb"
+/// ```
+///
+/// an empty filter lets everything through.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventFilter {
+    min_rank: Option<u16>,
+    event_types: Option<HashSet<EventKind>>,
+    watchlist: Option<Vec<Player>>,
+    mute: Vec<Player>,
+    min_gain: Option<MinGain>,
+    min_severity: Option<Severity>,
+}
+
+/// how much a pb has to gain to be worth sending
+///
+/// either the same everywhere, or by rank:
+///
+/// ```toml
+/// min_gain = [{ top = 10, gain = 0.0 }, { top = 1000, gain = 0.5 }]
+/// ```
+///
+/// where the smallest bracket someone's in counts, and
+/// anyone past all of them can gain as little as they like
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum MinGain {
+    All(f32),
+    Brackets(Vec<GainBracket>),
+}
+
+/// see `MinGain`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GainBracket {
+    pub top: u16,
+    pub gain: f32,
+}
+
+impl MinGain {
+    /// the least someone at `rank` has to gain
+    pub fn at(&self, rank: u16) -> f32 {
+        match self {
+            Self::All(gain) => *gain,
+            Self::Brackets(brackets) => brackets
+                .iter()
+                .filter(|b| rank <= b.top)
+                .min_by_key(|b| b.top)
+                .map_or(0.0, |b| b.gain),
+        }
+    }
+}
+
+/// someone to pick out of the board, by user id or by name
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Player {
+    Id(u32),
+    /// matched ignoring case
+    Name(String),
+}
+
+impl Player {
+    /// check if an entry is this player
+    pub fn is(&self, entry: &Entry) -> bool {
+        match self {
+            Self::Id(id) => entry.user_id == *id,
+            Self::Name(name) => entry.name.eq_ignore_ascii_case(name),
+        }
+    }
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// only let through events that end up at `rank` or better
+    pub fn min_rank(mut self, rank: u16) -> Self {
+        self.min_rank = Some(rank);
+        self
+    }
+
+    /// only let through these kinds of events
+    pub fn event_types(mut self, kinds: impl IntoIterator<Item = EventKind>) -> Self {
+        self.event_types = Some(kinds.into_iter().collect());
+        self
+    }
+
+    /// only let through events about these players
+    pub fn watchlist(mut self, players: impl IntoIterator<Item = Player>) -> Self {
+        self.watchlist = Some(players.into_iter().collect());
+        self
+    }
+
+    /// never let through events about these players, like alts or bots.
+    /// they still end up in the baseline
+    pub fn mute(mut self, players: impl IntoIterator<Item = Player>) -> Self {
+        self.mute.extend(players);
+        self
+    }
+
+    /// only let through pbs that gained at least this much.
+    /// world records, milestones and the like always go through
+    pub fn min_gain(mut self, gain: MinGain) -> Self {
+        self.min_gain = Some(gain);
+        self
+    }
+
+    /// only let through events that are at least this big of a deal
+    pub fn min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    /// check if an event makes it through the filter
+    pub fn matches(&self, pb: &Event) -> bool {
+        if let Some(rank) = self.min_rank {
+            if pb.entry().rank > rank {
+                return false;
+            }
+        }
+
+        if self.min_severity.is_some_and(|min| pb.severity() < min) {
+            return false;
+        }
+
+        if let Some(kinds) = &self.event_types {
+            if !kinds.contains(&pb.kind()) {
+                return false;
+            }
+        }
+
+        // they might've been on it under their old name
+        let listed = |players: &[Player]| {
+            let mut entries = pb.previous().into_iter().chain([pb.entry()]);
+            entries.any(|e| players.iter().any(|p| p.is(e)))
+        };
+        if self.watchlist.as_deref().is_some_and(|w| !listed(w)) || listed(&self.mute) {
+            return false;
+        }
+
+        if let (Some(min), EventKind::Pb) = (&self.min_gain, pb.kind()) {
+            let gain = pb.delta().unwrap_or_default();
+            if gain < min.at(pb.entry().rank) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(rank: u16, run_id: u32) -> Entry {
+        Entry {
+            rank,
+            name: "possm".to_string(),
+            user_id: 1,
+            run_id,
+            score: 390.0,
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let old = entry(200, 1);
+        let new = entry(150, 2);
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+
+        assert!(EventFilter::new().matches(&pb));
+        assert!(!EventFilter::new().min_rank(100).matches(&pb));
+        assert!(EventFilter::new().min_rank(150).matches(&pb));
+        assert!(!EventFilter::new()
+            .event_types([EventKind::WorldRecord])
+            .matches(&pb));
+
+        assert!(EventFilter::new().min_severity(Severity::Pb).matches(&pb));
+        assert!(!EventFilter::new()
+            .min_severity(Severity::TopPb)
+            .matches(&pb));
+
+        // config and code build the same filter
+        let config: EventFilter = toml::from_str("min_rank = 150\nevent_types = [\"pb\"]").unwrap();
+        assert!(config.matches(&pb));
+        let config: EventFilter = toml::from_str("min_severity = \"milestone\"").unwrap();
+        assert!(!config.matches(&pb));
+    }
+
+    #[test]
+    fn test_watchlist() {
+        let old = entry(200, 1);
+        let new = entry(150, 2);
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+
+        let watch = |players| EventFilter::new().watchlist(players);
+        assert!(watch(vec![Player::Id(1)]).matches(&pb));
+        assert!(watch(vec![Player::Name("POSSM".to_string())]).matches(&pb));
+        assert!(!watch(vec![Player::Id(2), Player::Name("fennekal".to_string())]).matches(&pb));
+        assert!(!watch(vec![]).matches(&pb));
+
+        let config: EventFilter = toml::from_str("watchlist = [2, \"possm\"]").unwrap();
+        assert!(config.matches(&pb));
+    }
+
+    #[test]
+    fn test_mute() {
+        let old = entry(200, 1);
+        let new = entry(150, 2);
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+
+        assert!(!EventFilter::new().mute([Player::Id(1)]).matches(&pb));
+        assert!(EventFilter::new().mute([Player::Id(2)]).matches(&pb));
+        // muting wins over watching
+        let both = EventFilter::new()
+            .watchlist([Player::Id(1)])
+            .mute([Player::Name("possm".to_string())]);
+        assert!(!both.matches(&pb));
+
+        let config: EventFilter = toml::from_str("mute = [1]").unwrap();
+        assert!(!config.matches(&pb));
+    }
+
+    #[test]
+    fn test_min_gain() {
+        let old = entry(200, 1);
+        let gained = |rank| Entry {
+            score: old.score + 0.25,
+            ..entry(rank, 2)
+        };
+        let (top, mid, bottom) = (gained(150), gained(151), gained(2000));
+        let pb = |new| Event::Improvement { old: &old, new };
+
+        assert!(EventFilter::new()
+            .min_gain(MinGain::All(0.1))
+            .matches(&pb(&top)));
+        assert!(!EventFilter::new()
+            .min_gain(MinGain::All(0.5))
+            .matches(&pb(&top)));
+
+        let config: EventFilter =
+            toml::from_str("min_gain = [{ top = 1000, gain = 0.5 }, { top = 150, gain = 0.2 }]")
+                .unwrap();
+        assert!(config.matches(&pb(&top)));
+        assert!(!config.matches(&pb(&mid)));
+        assert!(config.matches(&pb(&bottom)));
+
+        // only regular pbs get held to it
+        let wr = Event::WorldRecord {
+            old: &old,
+            new: &mid,
+        };
+        assert!(EventFilter::new().min_gain(MinGain::All(0.5)).matches(&wr));
+    }
+}
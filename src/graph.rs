@@ -0,0 +1,83 @@
+use plotters::prelude::*;
+use tokio::io;
+
+/// how big the graph is, small enough to sit in an embed
+const SIZE: (u32, u32) = (400, 150);
+
+/// score over time, as a png, for someone's pbs. `points` are
+/// unix times and scores, oldest first, and the last one gets a dot.
+/// `markers` are unix times that get a line across, like patches
+///
+/// there's no text on it, the embed already says what it is
+pub fn progression(points: &[(u64, f32)], markers: &[u64]) -> io::Result<Vec<u8>> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "a graph needs at least one point",
+        ));
+    };
+    let (low, high) = points.iter().fold((f32::MAX, f32::MIN), |(low, high), p| {
+        (low.min(p.1), high.max(p.1))
+    });
+    // a bit of room around the line
+    let pad = ((high - low) * 0.1).max(1.0);
+    let times = first.0 as f64..(last.0 as f64).max(first.0 as f64 + 1.0);
+
+    let mut rgb = vec![0; (SIZE.0 * SIZE.1 * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut rgb, SIZE).into_drawing_area();
+        root.fill(&RGBColor(0x2b, 0x2d, 0x31))
+            .map_err(io::Error::other)?;
+        let mut chart = ChartBuilder::on(&root)
+            .margin(10)
+            .build_cartesian_2d(times.clone(), (low - pad) as f64..(high + pad) as f64)
+            .map_err(io::Error::other)?;
+
+        // under the line, so they don't hide it
+        let (bottom, top) = ((low - pad) as f64, (high + pad) as f64);
+        let grey = RGBColor(0x80, 0x84, 0x8e);
+        let markers = markers
+            .iter()
+            .map(|&at| at as f64)
+            .filter(|at| times.contains(at))
+            .map(|at| PathElement::new(vec![(at, bottom), (at, top)], grey));
+        chart.draw_series(markers).map_err(io::Error::other)?;
+
+        let line = points.iter().map(|p| (p.0 as f64, p.1 as f64));
+        let blue = RGBColor(0x34, 0x98, 0xdb);
+        chart
+            .draw_series(LineSeries::new(line, blue.stroke_width(2)))
+            .map_err(io::Error::other)?;
+        let dot = Circle::new((last.0 as f64, last.1 as f64), 4, blue.filled());
+        chart.draw_series([dot]).map_err(io::Error::other)?;
+        root.present().map_err(io::Error::other)?;
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, SIZE.0, SIZE.1);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&rgb).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)?;
+
+    Ok(png)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_progression() {
+        let points = [(0, 380.0), (86400, 390.5), (3 * 86400, 401.0)];
+        let png = progression(&points, &[]).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        // a patch in between gets a line, one from after the last pb doesn't
+        let marked = progression(&points, &[2 * 86400, 10 * 86400]).unwrap();
+        assert_ne!(marked, png);
+        assert_eq!(progression(&points, &[10 * 86400]).unwrap(), png);
+        assert!(progression(&[(0, 380.0)], &[]).is_ok());
+        assert!(progression(&[], &[]).is_err());
+    }
+}
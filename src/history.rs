@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Datelike, NaiveDate};
+use tokio::io;
+
+use crate::lb::Entry;
+use crate::store::{Emitted, Store, User};
+
+/// everything a single player did, out of the store
+///
+/// the store keeps every event as it's found, so none of this
+/// needs the archive diffed again
+pub struct History {
+    /// oldest first
+    events: Vec<Emitted>,
+}
+
+impl History {
+    pub fn new(events: Vec<Emitted>) -> Self {
+        Self { events }
+    }
+
+    /// look a player up by their name or user id
+    pub async fn of(store: &Store, user: &str, board: Option<&str>) -> io::Result<Self> {
+        let who = match user.parse() {
+            Ok(id) => User::Id(id),
+            Err(_) => User::Name(user),
+        };
+        Ok(Self::new(store.events_of(who, board).await?))
+    }
+
+    pub fn events(&self) -> &[Emitted] {
+        &self.events
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// boards they've got anything on, in the order they first showed up
+    pub fn boards(&self) -> Vec<&str> {
+        let mut boards: Vec<&str> = Vec::new();
+        for e in &self.events {
+            if !boards.contains(&e.board.as_str()) {
+                boards.push(&e.board);
+            }
+        }
+        boards
+    }
+
+    /// how many events they had on a board at or after `since`
+    pub fn count_since(&self, board: &str, since: u64) -> usize {
+        self.events
+            .iter()
+            .filter(|e| e.board == board && e.timestamp >= since)
+            .count()
+    }
+
+    /// how much their pbs on a board at or after `since` added up to
+    pub fn improved_since(&self, board: &str, since: u64) -> f32 {
+        self.events
+            .iter()
+            .filter(|e| e.board == board && e.kind.is_pb() && e.timestamp >= since)
+            .filter_map(|e| e.old_score.map(|old| e.score - old))
+            .sum()
+    }
+
+    /// their score on a board over time, one point per event
+    pub fn progression(&self, board: &str) -> Vec<(u64, f32)> {
+        self.events
+            .iter()
+            .filter(|e| e.board == board)
+            .map(|e| (e.timestamp, e.score))
+            .collect()
+    }
+
+    /// how long `holder`'s world record stood, now that `wr` took it at `until`
+    ///
+    /// holder's history is what says when they got to #1 with that run,
+    /// so records from before the store was around don't have a reign
+    pub fn reign(&self, board: &str, holder: &Entry, wr: &Entry, until: u64) -> Option<Reign> {
+        let set = self
+            .events
+            .iter()
+            .find(|e| e.board == board && e.run_id == holder.run_id && e.rank == 1)?;
+        Some(Reign {
+            holder: holder.name.clone(),
+            held: until.saturating_sub(set.timestamp),
+            broke_own: holder.same_user(wr),
+        })
+    }
+
+    /// the streak a new pb at `at` puts someone on, if it's long enough
+    /// to say anything about
+    ///
+    /// days in a row win over pbs in a week, they're harder to get
+    pub fn streak(&self, board: &str, at: u64) -> Option<Streak> {
+        let pbs = self
+            .events
+            .iter()
+            .filter(|e| e.board == board && e.kind.is_pb() && e.timestamp < at);
+
+        let days: HashSet<_> = pbs.clone().map(|e| e.timestamp / 86400).collect();
+        let today = at / 86400;
+        let mut streak = 1;
+        while today
+            .checked_sub(streak as u64)
+            .is_some_and(|day| days.contains(&day))
+        {
+            streak += 1;
+        }
+        if streak >= MIN_STREAK {
+            return Some(Streak::Days(streak));
+        }
+
+        let week = pbs.filter(|e| e.timestamp + 7 * 86400 > at).count() + 1;
+        (week >= MIN_STREAK).then_some(Streak::Week(week))
+    }
+}
+
+/// anything shorter isn't much of a streak
+const MIN_STREAK: usize = 3;
+
+/// how many pbs someone got in a row, this one included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Streak {
+    /// days in a row with a pb
+    Days(usize),
+    /// pbs in the last 7 days
+    Week(usize),
+}
+
+impl Streak {
+    /// a line to go under the message
+    pub fn line(self) -> String {
+        match self {
+            Self::Days(n) => format!("That's their {} day in a row with a pb", ordinal(n)),
+            Self::Week(n) => format!("That's their {} pb this week", ordinal(n)),
+        }
+    }
+}
+
+/// 1st, 2nd, 3rd, 4th, ..., 11th, ..., 21st
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// how long a world record stood before it fell
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reign {
+    pub holder: String,
+    /// in seconds
+    pub held: u64,
+    /// the new record is by the same player
+    pub broke_own: bool,
+}
+
+impl Reign {
+    /// a line to go under the message
+    pub fn line(&self) -> String {
+        match self.broke_own {
+            true => format!("Their previous record stood for {}", span(self.held)),
+            false => format!(
+                "Previous record held by {} for {}",
+                self.holder,
+                span(self.held)
+            ),
+        }
+    }
+}
+
+/// some seconds in words, like "37 days", rounded down
+pub fn span(secs: u64) -> String {
+    let (n, unit) = match secs {
+        s if s >= 86400 => (s / 86400, "day"),
+        s if s >= 3600 => (s / 3600, "hour"),
+        s => (s / 60, "minute"),
+    };
+    let plural = if n == 1 { "" } else { "s" };
+    format!("{} {}{}", n, unit, plural)
+}
+
+/// unix time of the start of the month `timestamp` is in
+pub fn month_start(timestamp: u64) -> u64 {
+    let Some(t) = DateTime::from_timestamp(timestamp as i64, 0) else {
+        return 0;
+    };
+    NaiveDate::from_ymd_opt(t.year(), t.month(), 1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map_or(0, |d| d.and_utc().timestamp() as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::EventKind;
+
+    fn event(board: &str, timestamp: u64, score: f32) -> Emitted {
+        Emitted {
+            board: board.to_string(),
+            timestamp,
+            kind: EventKind::Pb,
+            user_id: 1,
+            name: "possm".to_string(),
+            run_id: timestamp as u32,
+            rank: 5,
+            score,
+            old_rank: Some(6),
+            old_score: Some(score - 1.0),
+        }
+    }
+
+    #[test]
+    fn test_history() {
+        // 2024-03-10, 2024-03-31, 2024-04-01 and 2024-04-02
+        let history = History::new(vec![
+            event("main", 1710028800, 380.0),
+            event("main", 1711843200, 390.0),
+            event("hard", 1711929600, 200.0),
+            event("main", 1712016000, 395.0),
+        ]);
+
+        assert_eq!(month_start(1711843200), 1709251200);
+        assert_eq!(history.boards(), ["main", "hard"]);
+        assert_eq!(history.count_since("main", month_start(1712016000)), 1);
+        assert_eq!(history.count_since("main", month_start(1711843200)), 3);
+        assert_eq!(history.improved_since("main", 1711843200), 2.0);
+        assert_eq!(
+            history.progression("main"),
+            [
+                (1710028800, 380.0),
+                (1711843200, 390.0),
+                (1712016000, 395.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reign() {
+        let mut wr = event("main", 1710028800, 410.0);
+        wr.kind = EventKind::WorldRecord;
+        wr.rank = 1;
+        let history = History::new(vec![event("main", 1709251200, 400.0), wr]);
+        let entry = |user_id, run_id| Entry {
+            rank: 1,
+            name: "possm".to_string(),
+            user_id,
+            run_id,
+            score: 410.0,
+        };
+
+        // 37 days later
+        let reign = history
+            .reign("main", &entry(1, 1710028800), &entry(2, 1), 1713225600)
+            .unwrap();
+        assert_eq!(reign.line(), "Previous record held by possm for 37 days");
+        let reign = history
+            .reign("main", &entry(1, 1710028800), &entry(1, 1), 1710032400)
+            .unwrap();
+        assert_eq!(reign.line(), "Their previous record stood for 1 hour");
+
+        // not a run the store saw at #1
+        assert_eq!(
+            history.reign("main", &entry(1, 1709251200), &entry(2, 1), 1713225600),
+            None
+        );
+    }
+
+    #[test]
+    fn test_streak() {
+        // 2024-04-01 to 2024-04-04, then 04-08
+        let day = |n: u64| 1711929600 + n * 86400;
+        let history = History::new(vec![
+            event("main", day(0), 380.0),
+            event("main", day(1), 381.0),
+            event("hard", day(2), 200.0),
+            event("main", day(3), 382.0),
+            event("main", day(7), 383.0),
+            event("main", day(7) + 60, 384.0),
+        ]);
+
+        assert_eq!(history.streak("main", day(2) + 60), Some(Streak::Days(3)));
+        assert_eq!(history.streak("main", day(4)), Some(Streak::Week(4)));
+        assert_eq!(history.streak("main", day(10)), Some(Streak::Week(3)));
+        assert_eq!(history.streak("hard", day(3)), None);
+        assert_eq!(
+            Streak::Days(3).line(),
+            "That's their 3rd day in a row with a pb"
+        );
+        assert_eq!(ordinal(12), "12th");
+        assert_eq!(ordinal(22), "22nd");
+    }
+}
@@ -1,29 +1,638 @@
-use std::io;
+use std::future::Future;
+use std::ops::Range;
+use std::sync::Mutex;
+use std::time::Duration;
 
-// replace with actual webhook url
-// this is meant to be a secret
-const URL: &str = "fuckin shit";
+use chrono::DateTime;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io;
+use tokio::time::Instant;
+use tracing::warn;
 
-/// wrapper over this weird library
+use crate::lb::now;
+
+/// longest message discord takes, in characters
+pub const MAX_LEN: usize = 2000;
+
+/// what images attached to embeds are called
+#[cfg(feature = "graphs")]
+const IMAGE: &str = "progression.png";
+
+/// how many times to try a request that keeps getting rate limited
+const MAX_TRIES: u32 = 5;
+
+/// longest to wait out a rate limit, anything longer fails the
+/// request so the outbox can try again later
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// a discord webhook
+///
+/// talks to the webhook api directly, so we get the ids
+/// of sent messages back and can edit/delete them later.
 pub struct Hook {
-    client: webhook::client::WebhookClient,
+    client: reqwest::Client,
+    url: String,
+    /// when discord said the next request can go out
+    next: Mutex<Option<Instant>>,
+    thread: Option<Thread>,
+    /// what daily threads get called, with the date after it
+    title: String,
+    /// the day, counted from the epoch, and its thread
+    today: Mutex<Option<(u64, u64)>>,
+}
+
+/// a thread in the webhook's channel to send into, to keep the
+/// channel itself quiet
+///
+/// ```toml
+/// [[board]]
+/// thread = { id = 123456789012345678 }
+/// ```
+///
+/// or `thread = "daily"` for a new post every day, in utc, with
+/// everything from that day in it. that only works in forum
+/// channels, and messages in a daily thread can't be corrected
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Thread {
+    Id(u64),
+    Daily,
+}
+
+/// body of a message we send
+#[derive(Serialize)]
+struct Message<'a> {
+    content: &'a str,
+    /// starts a thread, only works in forum channels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thread_name: Option<&'a str>,
+    allowed_mentions: Value,
+}
+
+/// who a message is allowed to ping: the users and roles hdget
+/// put in it itself, and nobody else. anything that looks like a
+/// ping in someone's name stays text
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pings {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<u64>,
+}
+
+impl Pings {
+    /// the ids in pings from rules and scripts, like `<@&1234>`
+    pub fn parse<S: AsRef<str>>(pings: &[S]) -> Self {
+        let mut parsed = Self::default();
+        for ping in pings {
+            for rest in ping.as_ref().split("<@").skip(1) {
+                let Some((id, _)) = rest.split_once('>') else {
+                    continue;
+                };
+                match id.strip_prefix('&') {
+                    Some(role) => parsed.add_role(role.parse().ok()),
+                    None => parsed.add_user(id.trim_start_matches('!').parse().ok()),
+                }
+            }
+        }
+        parsed
+    }
+
+    pub fn add_user(&mut self, id: Option<u64>) {
+        if let Some(id) = id.filter(|id| !self.users.contains(id)) {
+            self.users.push(id);
+        }
+    }
+
+    pub fn add_role(&mut self, id: Option<u64>) {
+        if let Some(id) = id.filter(|id| !self.roles.contains(id)) {
+            self.roles.push(id);
+        }
+    }
+
+    /// everyone either of them can ping
+    pub fn merge(&mut self, other: &Self) {
+        for &id in &other.users {
+            self.add_user(Some(id));
+        }
+        for &id in &other.roles {
+            self.add_role(Some(id));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.roles.is_empty()
+    }
+
+    /// discord's `allowed_mentions` for them. never everyone
+    pub fn allowed_mentions(&self) -> Value {
+        let ids = |ids: &[u64]| ids.iter().map(u64::to_string).collect::<Vec<_>>();
+        serde_json::json!({
+            "parse": [],
+            "users": ids(&self.users),
+            "roles": ids(&self.roles),
+        })
+    }
+}
+
+/// the bits of discord's response we care about
+#[derive(Deserialize)]
+struct Posted {
+    id: String,
+    /// the thread, if the message went in one
+    channel_id: String,
 }
 
 impl Hook {
-    pub fn new() -> Self {
+    /// the url is meant to be a secret, so it comes from the config
+    pub fn new(url: &str) -> Self {
         Self {
-            client: webhook::client::WebhookClient::new(URL),
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            next: Mutex::new(None),
+            thread: None,
+            title: String::new(),
+            today: Mutex::new(None),
+        }
+    }
+
+    /// send everything into a thread instead, daily ones are
+    /// called `title` and the date
+    pub fn in_thread(mut self, thread: Thread, title: &str) -> Self {
+        self.thread = Some(thread);
+        self.title = title.to_string();
+        self
+    }
+
+    /// send text to the discord webhook, as a few messages one
+    /// after the other if it's too long for one
+    ///
+    /// returns the id of the first message that was created
+    pub async fn send(&self, content: &str) -> io::Result<u64> {
+        self.send_pinging(content, &Pings::default()).await
+    }
+
+    /// send text that can ping `pings`
+    pub async fn send_pinging(&self, content: &str, pings: &Pings) -> io::Result<u64> {
+        send_parts(split(content), |content| async move {
+            let message = Message {
+                content: &content,
+                thread_name: None,
+                allowed_mentions: pings.allowed_mentions(),
+            };
+            self.post_to_thread(serde_json::to_value(message)?, None)
+                .await
+        })
+        .await
+    }
+
+    /// send a whole payload, like one with embeds from `render::DiscordEmbed`
+    ///
+    /// returns the id of the message that was created
+    pub async fn send_embed(&self, payload: &Value) -> io::Result<u64> {
+        self.post_to_thread(payload.clone(), None).await
+    }
+
+    /// send a payload with a png that shows up as the embed's image
+    ///
+    /// returns the id of the message that was created
+    #[cfg(feature = "graphs")]
+    pub async fn send_embed_with_image(&self, payload: &Value, png: &[u8]) -> io::Result<u64> {
+        let mut payload = payload.clone();
+        let url = format!("attachment://{}", IMAGE);
+        payload["embeds"][0]["image"] = serde_json::json!({ "url": url });
+        self.post_to_thread(payload, Some(png)).await
+    }
+
+    /// post to wherever `thread` says, starting the day's
+    /// thread if it's a daily one and there isn't one yet
+    async fn post_to_thread(&self, mut body: Value, image: Option<&[u8]>) -> io::Result<u64> {
+        if body.get("allowed_mentions").is_none() {
+            body["allowed_mentions"] = Pings::default().allowed_mentions();
+        }
+        let thread = match self.thread {
+            None => None,
+            Some(Thread::Id(id)) => Some(id),
+            Some(Thread::Daily) => {
+                let day = now().as_secs() / 86400;
+                let today = *self.today.lock().unwrap();
+                match today {
+                    Some((d, thread)) if d == day => Some(thread),
+                    _ => {
+                        body["thread_name"] = day_title(&self.title, day).into();
+                        let posted = self.post(&body, None, image).await?;
+                        let thread = parse_id(&posted.channel_id)?;
+                        *self.today.lock().unwrap() = Some((day, thread));
+                        return parse_id(&posted.id);
+                    }
+                }
+            }
+        };
+
+        parse_id(&self.post(&body, thread, image).await?.id)
+    }
+
+    /// start a thread with the first message, and send the
+    /// rest into it. only works in forum channels
+    ///
+    /// returns the id of the thread
+    pub async fn send_thread(&self, name: &str, messages: &[String]) -> io::Result<u64> {
+        let Some((first, rest)) = messages.split_first() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a thread needs at least one message",
+            ));
+        };
+
+        let message = Message {
+            content: first,
+            thread_name: Some(name),
+            allowed_mentions: Pings::default().allowed_mentions(),
+        };
+        let thread = parse_id(&self.post(&message, None, None).await?.channel_id)?;
+
+        for content in rest {
+            let message = Message {
+                content,
+                thread_name: None,
+                allowed_mentions: Pings::default().allowed_mentions(),
+            };
+            self.post(&message, Some(thread), None).await?;
+        }
+
+        Ok(thread)
+    }
+
+    /// post a message, with an image attached if there is one
+    async fn post(
+        &self,
+        message: &impl Serialize,
+        thread: Option<u64>,
+        image: Option<&[u8]>,
+    ) -> io::Result<Posted> {
+        // with a file, the message goes next to it in a form
+        let json = match image {
+            Some(_) => Some(serde_json::to_string(message)?),
+            None => None,
+        };
+        let res = self
+            .request(|| {
+                let mut req = self
+                    .client
+                    .post(&self.url)
+                    // makes discord respond with the message it created
+                    .query(&[("wait", "true")]);
+                if let Some(thread) = thread {
+                    req = req.query(&[("thread_id", thread)]);
+                }
+                match (image, &json) {
+                    #[cfg(feature = "graphs")]
+                    (Some(png), Some(json)) => {
+                        use reqwest::multipart::{Form, Part};
+                        let file = Part::bytes(png.to_vec())
+                            .file_name(IMAGE)
+                            .mime_str("image/png")
+                            .expect("it's a real mime type");
+                        let form = Form::new()
+                            .text("payload_json", json.clone())
+                            .part("files[0]", file);
+                        req.multipart(form)
+                    }
+                    _ => req.json(message),
+                }
+            })
+            .await?;
+
+        res.json().await.map_err(io::Error::other)
+    }
+
+    /// replace the text of a message we sent earlier
+    pub async fn edit(&self, id: u64, content: &str) -> io::Result<()> {
+        let url = format!("{}/messages/{}", self.url, id);
+        let message = Message {
+            content,
+            thread_name: None,
+            allowed_mentions: Pings::default().allowed_mentions(),
+        };
+        self.request(|| self.thread_query(self.client.patch(&url).json(&message)))
+            .await?;
+
+        Ok(())
+    }
+
+    /// delete a message we sent earlier
+    pub async fn delete(&self, id: u64) -> io::Result<()> {
+        let url = format!("{}/messages/{}", self.url, id);
+        self.request(|| self.thread_query(self.client.delete(&url)))
+            .await?;
+
+        Ok(())
+    }
+
+    /// messages in a thread can only be changed through it
+    fn thread_query(&self, req: RequestBuilder) -> RequestBuilder {
+        match self.thread {
+            Some(Thread::Id(id)) => req.query(&[("thread_id", id)]),
+            _ => req,
         }
     }
 
-    /// send text to the discord webhook
-    pub async fn send(&self, content: &str) -> io::Result<bool> {
-        // dumb ratelimit fix
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    /// send a request, waiting out discord's rate limits
+    ///
+    /// waits until the bucket has room again before sending, and
+    /// tries again after being told to with a 429
+    async fn request(&self, req: impl Fn() -> RequestBuilder) -> io::Result<Response> {
+        for _ in 0..MAX_TRIES {
+            let next = *self.next.lock().unwrap();
+            if let Some(next) = next {
+                tokio::time::sleep_until(next).await;
+            }
+
+            let res = req().send().await.map_err(io::Error::other)?;
+            if res.status() != StatusCode::TOO_MANY_REQUESTS {
+                let wait = reset_after(res.headers());
+                *self.next.lock().unwrap() = wait.map(|wait| Instant::now() + wait);
+                return res.error_for_status().map_err(|e| {
+                    // a message or webhook someone deleted
+                    let kind = match e.status() {
+                        Some(StatusCode::NOT_FOUND) => io::ErrorKind::NotFound,
+                        _ => io::ErrorKind::Other,
+                    };
+                    io::Error::new(kind, e)
+                });
+            }
+
+            let wait = retry_after(res.headers());
+            if wait > MAX_WAIT {
+                break;
+            }
+            warn!(?wait, "rate limited by discord");
+            *self.next.lock().unwrap() = Some(Instant::now() + wait);
+        }
+
+        Err(io::Error::other("still rate limited by discord"))
+    }
+}
+
+/// how long a 429 says to wait, a second if it doesn't say
+fn retry_after(headers: &HeaderMap) -> Duration {
+    secs(headers, "retry-after").unwrap_or(Duration::from_secs(1))
+}
+
+/// how long until the bucket has room again, if it's empty
+fn reset_after(headers: &HeaderMap) -> Option<Duration> {
+    let remaining = headers.get("x-ratelimit-remaining")?.to_str().ok()?;
+    match remaining.trim() == "0" {
+        true => secs(headers, "x-ratelimit-reset-after"),
+        false => None,
+    }
+}
+
+/// a header with some seconds in it, discord sends fractions too
+fn secs(headers: &HeaderMap, name: &str) -> Option<Duration> {
+    let secs: f64 = headers.get(name)?.to_str().ok()?.trim().parse().ok()?;
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// split messages into batches that each fit in one message,
+/// when joined up with a blank line in between
+///
+/// returns the range of messages in each batch. they stay in
+/// order, and one that's too long by itself gets its own batch
+pub fn batch<S: AsRef<str>>(messages: &[S]) -> Vec<Range<usize>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut len = 0;
+
+    for (i, message) in messages.iter().enumerate() {
+        let n = message.as_ref().chars().count();
+        if i > start && len + 1 + n > MAX_LEN {
+            batches.push(start..i);
+            start = i;
+            len = 0;
+        }
+        len += if i > start { 1 + n } else { n };
+    }
+    if start < messages.len() {
+        batches.push(start..messages.len());
+    }
+
+    batches
+}
+
+/// split text into parts that each fit in one message
+///
+/// it's split between entries, the blank lines between messages
+/// in a batch, where it can be. an entry that doesn't fit in one
+/// message by itself is split between lines, and a line that
+/// doesn't is cut wherever
+pub fn split(content: &str) -> Vec<String> {
+    split_to(content, MAX_LEN)
+}
+
+/// `split`, for somewhere that takes up to `max` characters
+pub fn split_to(content: &str, max: usize) -> Vec<String> {
+    let len = |s: &str| s.chars().count();
+    if len(content) <= max {
+        return vec![content.to_string()];
+    }
+
+    let mut pieces = Vec::new();
+    for entry in content.split_inclusive("\n\n") {
+        if len(entry) <= max {
+            pieces.push(entry.to_string());
+            continue;
+        }
+        for line in entry.split_inclusive('\n') {
+            let chars: Vec<_> = line.chars().collect();
+            pieces.extend(chars.chunks(max).map(|c| c.iter().collect()));
+        }
+    }
+
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    for piece in pieces {
+        if !part.is_empty() && len(&part) + len(&piece) > max {
+            parts.push(std::mem::take(&mut part));
+        }
+        part.push_str(&piece);
+    }
+    parts.push(part);
+    parts
+}
+
+/// post the parts of a split message one after the other
+///
+/// once the first part is out the message counts as sent, so
+/// trying again doesn't post what's already there twice. the
+/// rest of it is dropped if a later part fails
+///
+/// returns the id of the first part
+pub async fn send_parts<F, Fut>(parts: Vec<String>, mut post: F) -> io::Result<u64>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = io::Result<u64>>,
+{
+    let total = parts.len();
+    let mut first = None;
+    for (i, part) in parts.into_iter().enumerate() {
+        match (post(part).await, first) {
+            (Ok(id), _) => {
+                first.get_or_insert(id);
+            }
+            (Err(e), None) => return Err(e),
+            (Err(e), Some(_)) => {
+                warn!(error = %e, sent = i, total, "couldn't send the rest of a message");
+                break;
+            }
+        }
+    }
+    // split always gives back at least one part
+    Ok(first.unwrap_or_default())
+}
+
+/// the name of a daily thread
+fn day_title(title: &str, day: u64) -> String {
+    let date = DateTime::from_timestamp((day * 86400) as i64, 0).unwrap_or_default();
+    format!("{} {}", title, date.format("%Y-%m-%d"))
+}
+
+/// discord sends ids as strings
+fn parse_id(id: &str) -> io::Result<u64> {
+    id.parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_batch() {
+        let short = "a".repeat(900);
+        let long = "b".repeat(2500);
+        let messages = [&short, &short, &short, &long, &short];
+
+        assert_eq!(batch(&messages), [0..2, 2..3, 3..4, 4..5]);
+        assert_eq!(batch(&["a", "b"]), vec![0..2]);
+        assert!(batch::<&str>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split("short"), ["short"]);
+
+        let entry = |c: &str| format!("{}\n", c.repeat(900));
+        let content = [entry("a"), entry("b"), entry("c")].join("\n");
+        let parts = split(&content);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0], format!("{}\n{}\n", entry("a"), entry("b")));
+        assert_eq!(parts[1], entry("c"));
+
+        // an entry that's too long by itself goes by lines
+        let long = format!("{}{}{}", entry("a"), entry("b"), entry("c"));
+        let parts = split(&long);
+        assert_eq!(parts, [entry("a") + entry("b").as_str(), entry("c")]);
+
+        let line = "d".repeat(4500);
+        let parts = split(&line);
+        assert_eq!(
+            parts.iter().map(|p| p.len()).collect::<Vec<_>>(),
+            [2000, 2000, 500]
+        );
+        assert_eq!(parts.concat(), line);
+        assert_eq!(split_to(&line, 4096).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_parts() {
+        let parts = || vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let sent = Mutex::new(Vec::new());
+        // fakes discord taking every part but `fail`
+        let post = |fail: &'static str| {
+            let sent = &sent;
+            move |part: String| async move {
+                if part == fail {
+                    return Err(io::Error::other("discord is down"));
+                }
+                sent.lock().unwrap().push(part.clone());
+                Ok(part.as_bytes()[0] as u64)
+            }
+        };
+
+        assert_eq!(send_parts(parts(), post("")).await.unwrap(), b'a' as u64);
+        assert_eq!(*sent.lock().unwrap(), ["a", "b", "c"]);
+        sent.lock().unwrap().clear();
+
+        // the first part is out, so it counts as sent
+        assert_eq!(send_parts(parts(), post("b")).await.unwrap(), b'a' as u64);
+        assert_eq!(*sent.lock().unwrap(), ["a"]);
+        sent.lock().unwrap().clear();
+
+        // nothing went out, so it can be tried again
+        assert!(send_parts(parts(), post("a")).await.is_err());
+        assert!(sent.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pings() {
+        let mut pings = Pings::parse(&["<@&12> <@34>", "<@&56> <@&12>", "<@&nope> <@!78>"]);
+        assert_eq!(pings.users, [34, 78]);
+        assert_eq!(pings.roles, [12, 56]);
+
+        pings.merge(&Pings {
+            users: vec![34, 90],
+            roles: vec![],
+        });
+        let allowed = pings.allowed_mentions();
+        assert_eq!(allowed["parse"], serde_json::json!([]));
+        assert_eq!(allowed["users"], serde_json::json!(["34", "78", "90"]));
+        assert_eq!(allowed["roles"], serde_json::json!(["12", "56"]));
+
+        // nothing in the message itself counts, like someone's name
+        assert!(Pings::parse(&["pb!"]).is_empty());
+    }
+
+    #[test]
+    fn test_thread() {
+        #[derive(Deserialize)]
+        struct Board {
+            thread: Thread,
+        }
+        let board: Board = toml::from_str("thread = { id = 1234 }").unwrap();
+        assert_eq!(board.thread, Thread::Id(1234));
+        let board: Board = toml::from_str("thread = \"daily\"").unwrap();
+        assert_eq!(board.thread, Thread::Daily);
+
+        assert_eq!(day_title("main pbs", 20376), "main pbs 2025-10-15");
+    }
+
+    #[test]
+    fn test_rate_limits() {
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, value.parse().unwrap());
+            }
+            headers
+        };
+
+        let limited = headers(&[("retry-after", "1.5")]);
+        assert_eq!(retry_after(&limited), Duration::from_millis(1500));
+        assert_eq!(retry_after(&headers(&[])), Duration::from_secs(1));
 
-        self.client
-            .send(|mesg| mesg.content(content))
-            .await
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        let empty = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset-after", "0.25"),
+        ]);
+        assert_eq!(reset_after(&empty), Some(Duration::from_millis(250)));
+        let room = headers(&[
+            ("x-ratelimit-remaining", "3"),
+            ("x-ratelimit-reset-after", "0.25"),
+        ]);
+        assert_eq!(reset_after(&room), None);
     }
 }
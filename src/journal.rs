@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+
+use crate::lb::{Entry, Leaderboard};
+
+/// entries that changed since the cache was last written
+///
+/// writing the whole cache every cycle is slow, so only the
+/// entries that changed get appended here. the cache gets
+/// written every so often, and the journal emptied.
+///
+/// each record is a u64 timestamp followed by an entry, in
+/// the same format as the cache.
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// add some entries to the end of the journal
+    pub async fn append<'a>(
+        &self,
+        timestamp: Duration,
+        entries: impl IntoIterator<Item = &'a Entry>,
+    ) -> io::Result<()> {
+        // build the records first, so they go out in a single write
+        let mut buf = Vec::new();
+        for entry in entries {
+            buf.write_u64_le(timestamp.as_secs()).await?;
+            entry.write(&mut buf).await?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&buf).await
+    }
+
+    /// apply the journal on top of a leaderboard read from the cache
+    ///
+    /// returns how many entries were applied. a torn record at
+    /// the end, from a crash mid write, is ignored. journals
+    /// next to caches older than version 4 aren't `wide`, see
+    /// `Entry::read_narrow`
+    pub async fn replay(&self, lb: &mut Leaderboard, wide: bool) -> io::Result<usize> {
+        let file = match File::open(&self.path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let mut buf = io::BufReader::new(file);
+
+        let mut applied = 0;
+        while let Ok(timestamp) = buf.read_u64_le().await {
+            let entry = match wide {
+                true => Entry::read(&mut buf).await,
+                false => Entry::read_narrow(&mut buf).await,
+            };
+            let Ok(entry) = entry else {
+                break;
+            };
+
+            lb.timestamp = lb.timestamp.max(Duration::from_secs(timestamp));
+            match lb.entries.iter_mut().find(|e| e.same_user(&entry)) {
+                Some(old) => *old = entry,
+                None => lb.entries.push(entry),
+            }
+            applied += 1;
+        }
+
+        // everything else expects entries in rank order
+        lb.entries.sort_by_key(|e| e.rank);
+
+        Ok(applied)
+    }
+
+    /// empty the journal, once everything in it is in the cache
+    pub async fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(rank: u16, user_id: u32, run_id: u32, score: f32) -> Entry {
+        Entry {
+            rank,
+            name: user_id.to_string(),
+            user_id,
+            run_id,
+            score,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let path = std::env::temp_dir().join(format!("hdget-journal-{}", std::process::id()));
+        let journal = Journal::new(&path);
+        journal.clear().await.unwrap();
+
+        let mut lb = Leaderboard {
+            timestamp: Duration::from_secs(0),
+            entries: vec![entry(1, 1, 1, 400.0), entry(2, 2, 2, 399.0)],
+        };
+
+        journal
+            .append(Duration::from_secs(600), [&entry(1, 2, 3, 410.0)])
+            .await
+            .unwrap();
+        // half a record, like after a crash
+        tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .await
+            .unwrap()
+            .write_all(&[1, 2, 3])
+            .await
+            .unwrap();
+
+        assert_eq!(journal.replay(&mut lb, true).await.unwrap(), 1);
+        assert_eq!(lb.timestamp, Duration::from_secs(600));
+        let replayed = lb.entries.iter().find(|e| e.user_id == 2).unwrap();
+        assert_eq!(replayed.run_id, 3);
+        assert_eq!(lb.len(), 2);
+
+        journal.clear().await.unwrap();
+    }
+}
@@ -1,8 +1,14 @@
 use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 use std::time::Duration;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use scraper::{ElementRef, Node};
+use serde::{Deserialize, Serialize};
+
+use crate::source::{is_down, is_maintenance_page, FetchOutcome};
 use tokio::fs::File;
 use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
 
@@ -10,24 +16,37 @@ use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
 /// 
 /// you obtain instances of this object through a Leaderboard,
 /// specifically, it's `.from_site` or `.from_cache` methods.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
-    rank: u16,
-    name: String,
-    user_id: u32,
-    run_id: u32,
-    score: f32,
+    pub(crate) rank: u16,
+    pub(crate) name: String,
+    pub(crate) user_id: u32,
+    pub(crate) run_id: u32,
+    pub(crate) score: f32,
 }
 
 impl Entry {
     /// reads an Entry out of some async reader
-    async fn read(r: &mut (impl io::AsyncRead + Unpin)) -> io::Result<Self> {
+    pub(crate) async fn read(r: &mut (impl io::AsyncRead + Unpin)) -> io::Result<Self> {
+        Self::read_as(r, true).await
+    }
+
+    /// reads an Entry the way caches before version 4 had them,
+    /// with the length of the name in a single byte
+    pub(crate) async fn read_narrow(r: &mut (impl io::AsyncRead + Unpin)) -> io::Result<Self> {
+        Self::read_as(r, false).await
+    }
+
+    async fn read_as(r: &mut (impl io::AsyncRead + Unpin), wide: bool) -> io::Result<Self> {
         let rank = r.read_u16_le().await?;
         let name = {
-            let len = r.read_u8().await?;
+            let len = match wide {
+                true => r.read_u16_le().await?,
+                false => r.read_u8().await? as u16,
+            };
             let mut t = vec![0; len as usize];
             r.read_exact(&mut t).await?;
-            String::from_utf8(t).unwrap()
+            String::from_utf8(t).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
         };
         let user_id = r.read_u32_le().await?;
         let run_id = r.read_u32_le().await?;
@@ -43,10 +62,12 @@ impl Entry {
     }
 
     /// writes an Entry into some async reader
-    async fn write(&self, w: &mut (impl io::AsyncWrite + Unpin)) -> io::Result<()> {
+    pub(crate) async fn write(&self, w: &mut (impl io::AsyncWrite + Unpin)) -> io::Result<()> {
         w.write_u16_le(self.rank).await?;
         let str = self.name.as_bytes();
-        w.write_u8(str.len() as u8).await?;
+        let len = u16::try_from(str.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "name too long"))?;
+        w.write_u16_le(len).await?;
         w.write_all(str).await?;
         w.write_u32_le(self.user_id).await?;
         w.write_u32_le(self.run_id).await?;
@@ -60,33 +81,235 @@ impl Entry {
     pub fn same_user(&self, other: &Self) -> bool {
         self.user_id == other.user_id
     }
+
+    pub fn rank(&self) -> u16 {
+        self.rank
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn user_id(&self) -> u32 {
+        self.user_id
+    }
+
+    pub fn run_id(&self) -> u32 {
+        self.run_id
+    }
+
+    pub fn score(&self) -> f32 {
+        self.score
+    }
+
+    /// link that opens the run in the game
+    pub fn run_link(&self) -> String {
+        format!("hyperdemon://run/{}", self.run_id)
+    }
+}
+
+/// what every cache starts with
+const CACHE_MAGIC: &[u8; 4] = b"HDGC";
+
+/// bumped whenever the layout of the cache changes
+///
+/// the cache starts with the magic and this, then the timestamp,
+/// then the number of entries, then the entries themselves, then
+/// a crc32 of everything before it
+const CACHE_VERSION: u8 = 4;
+
+/// what a zstd frame starts with
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// the layouts the cache has been written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheFormat {
+    /// just the timestamp and exactly 1000 entries
+    Legacy,
+    /// a version byte of 1, then the timestamp,
+    /// the number of entries and the entries
+    V1,
+    /// the magic, a version byte of 2, then the same as `V1`
+    V2,
+    /// the same as `Current`, but the length of each name
+    /// is a single byte
+    V3,
+    /// see `CACHE_VERSION`
+    Current,
+    /// written with `Encoding::Json`
+    Json,
 }
 
+/// the cache's checksum didn't match its contents
+///
+/// comes wrapped in an `io::Error` from `from_cache`
 #[derive(Debug)]
+pub struct CacheCorrupt {
+    pub expected: u32,
+    pub found: u32,
+}
+
+impl std::fmt::Display for CacheCorrupt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the cache is corrupt, its checksum is {:08x} but it should be {:08x}",
+            self.found, self.expected
+        )
+    }
+}
+
+impl std::error::Error for CacheCorrupt {}
+
+/// something else had the cache locked for too long
+///
+/// comes wrapped in an `io::Error` from reading or writing a cache
+#[derive(Debug)]
+pub struct CacheBusy {
+    pub path: PathBuf,
+}
+
+impl std::fmt::Display for CacheBusy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is busy, is another hdget using it?",
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for CacheBusy {}
+
+/// how long to wait on whoever else is using a cache
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// an advisory lock on a cache, let go of once dropped
+///
+/// the cache itself gets replaced on every write, so the
+/// lock is on `<cache>.lock` next to it. readers share it,
+/// writers get it to themselves
+#[derive(Debug)]
+struct CacheLock {
+    _file: std::fs::File,
+}
+
+impl CacheLock {
+    async fn acquire(path: &Path, exclusive: bool, timeout: Duration) -> io::Result<Self> {
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(path))
+            .await?
+            .into_std()
+            .await;
+
+        let start = Instant::now();
+        loop {
+            let res = match exclusive {
+                true => file.try_lock(),
+                false => file.try_lock_shared(),
+            };
+            match res {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(std::fs::TryLockError::Error(e)) => return Err(e),
+                Err(std::fs::TryLockError::WouldBlock) if start.elapsed() < timeout => {
+                    tokio::time::sleep(Duration::from_millis(50)).await
+                }
+                Err(std::fs::TryLockError::WouldBlock) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::ResourceBusy,
+                        CacheBusy {
+                            path: path.to_path_buf(),
+                        },
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// where the lock for a cache is
+fn lock_path(path: &Path) -> PathBuf {
+    let mut lock = path.as_os_str().to_owned();
+    lock.push(".lock");
+    PathBuf::from(lock)
+}
+
+/// how the cache gets written
+///
+/// ```toml
+/// cache_format = "json"
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// small and quick, see `CACHE_VERSION`
+    #[default]
+    Binary,
+    /// easy to read and edit by hand
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// represets a whole leaderboard
 /// 
 /// contains methods to read from/write to a cache
 /// or read out from the website.
 pub struct Leaderboard {
-    timestamp: Duration,
-    entries: Vec<Entry>,
+    #[serde(with = "secs")]
+    pub(crate) timestamp: Duration,
+    pub(crate) entries: Vec<Entry>,
+}
+
+/// (de)serializes a Duration as whole seconds
+mod secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_secs)
+    }
+}
+
+/// time since the unix epoch
+pub(crate) fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
 }
 
 impl Leaderboard {
-    /// Scrape the leaderboard off the site
-    pub async fn from_site() -> reqwest::Result<Option<Self>> {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards");
+    /// Scrape the leaderboard off the site at `url`
+    pub async fn from_site(url: &str) -> reqwest::Result<FetchOutcome> {
+        let timestamp = now();
 
         // GET the leaderboard
-        let html = reqwest::get("https://hyprd.mn/leaderboards")
-            .await?
-            .text()
-            .await?;
+        let res = crate::net::get(url).await?;
+        if is_down(res.status()) {
+            return Ok(FetchOutcome::Maintenance);
+        }
+        let html = res.text().await?;
+
+        Ok(match Self::from_html(&html, timestamp) {
+            Some(lb) => FetchOutcome::Board(lb),
+            None if is_maintenance_page(&html) => FetchOutcome::Maintenance,
+            None => FetchOutcome::Unparseable { body: html },
+        })
+    }
 
+    /// parse a Leaderboard out of the site's html
+    ///
+    /// returns None if any row fails to parse, or there aren't any
+    pub fn from_html(html: &str, timestamp: Duration) -> Option<Self> {
         // use a dom lib to help scrape the doc
-        let doc = scraper::Html::parse_document(&html);
+        let doc = scraper::Html::parse_document(html);
         // create a new selector
         let sel = scraper::Selector::parse(".leaderboard>tbody>tr").unwrap();
 
@@ -117,15 +340,15 @@ impl Leaderboard {
             let entry = Entry {
                 rank: rank.parse().ok()?,
                 name: name.to_string(),
-                user_id: user_url.split('/').last()?.parse().ok()?,
-                run_id: run_url.split('/').last()?.parse().ok()?,
+                user_id: user_url.split('/').next_back()?.parse().ok()?,
+                run_id: run_url.split('/').next_back()?.parse().ok()?,
                 score: score.parse().ok()?,
             };
 
             Some(entry)
         }
 
-        let Some(entries) = doc
+        let entries = doc
             // use selector
             .select(&sel)
             // every 2nd row (feature of the site :p)
@@ -133,130 +356,899 @@ impl Leaderboard {
             // map using the helper function
             .map(parse_row)
             // collect into Option<Vec<Entries>>
-            // if Option = None, return Ok(None)
+            // if Option = None, return None
             // else entries = Vec<Entries>
-            .collect::<Option<Vec<_>>>() else { return Ok(None) };
+            .collect::<Option<Vec<_>>>()?;
+
+        // a page without any rows isn't a leaderboard
+        if entries.is_empty() {
+            return None;
+        }
+
+        Some(Self { timestamp, entries })
+    }
+
+    /// get a Leaderboard from the cache at `path`
+    ///
+    /// caches in an older format are an error, they
+    /// need to go through `migrate` first
+    pub async fn from_cache(path: impl AsRef<Path>) -> io::Result<Self> {
+        let (lb, format) = Self::read_cache(path).await?;
+        if !matches!(format, CacheFormat::Current | CacheFormat::Json) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the cache is in an old format ({:?}), run `hdget migrate`", format),
+            ));
+        }
+
+        Ok(lb)
+    }
 
-        Ok(Some(Self { timestamp, entries }))
+    /// get a Leaderboard from a cache in any format it's been in
+    pub async fn read_cache(path: impl AsRef<Path>) -> io::Result<(Self, CacheFormat)> {
+        let path = path.as_ref();
+        let _lock = CacheLock::acquire(path, false, LOCK_TIMEOUT).await?;
+        Self::read_unlocked(path).await
     }
 
-    /// get a Leaderboard from cache
-    pub async fn from_cache() -> io::Result<Self> {
-        let mut cache = File::open("cache").await?;
-        let mut buf = io::BufReader::new(&mut cache);
+    /// a cache file as it is, without reading anything out of it
+    pub(crate) async fn read_raw(path: &Path) -> io::Result<Vec<u8>> {
+        let _lock = CacheLock::acquire(path, false, LOCK_TIMEOUT).await?;
+        tokio::fs::read(path).await
+    }
 
-        let raw_timestamp = buf.read_u64_le().await?;
-        let timestamp = Duration::from_secs(raw_timestamp);
+    /// `read_cache`, without the lock. for files that only
+    /// ever get written once, like the archive
+    pub(crate) async fn read_unlocked(path: &Path) -> io::Result<(Self, CacheFormat)> {
+        let bytes = tokio::fs::read(path).await?;
+        Self::parse_cache(&bytes).await
+    }
+
+    /// read a Leaderboard out of the bytes of a cache
+    pub(crate) async fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Ok(Self::parse_cache(bytes).await?.0)
+    }
 
+    /// whatever can still be read out of a cache `read_cache` gave up on
+    ///
+    /// entries are kept up to the first one that's cut off or out of
+    /// order, anything after that can't be trusted. only works for
+    /// caches with a header, and is None if nothing was worth keeping
+    pub async fn salvage(path: impl AsRef<Path>) -> io::Result<Option<(Self, CacheFormat)>> {
+        let path = path.as_ref();
+        let _lock = CacheLock::acquire(path, false, LOCK_TIMEOUT).await?;
+        let mut bytes = tokio::fs::read(path).await?;
+
+        if bytes.starts_with(&ZSTD_MAGIC) {
+            // a cut off frame still gives back what it got through
+            let mut decoded = Vec::new();
+            let _ = std::io::Read::read_to_end(&mut zstd::Decoder::new(&bytes[..])?, &mut decoded);
+            bytes = decoded;
+        }
+
+        let Some(mut rest) = bytes.strip_prefix(CACHE_MAGIC) else {
+            return Ok(None);
+        };
+        let (wide, format) = match rest.read_u8().await {
+            Ok(2) => (false, CacheFormat::V2),
+            Ok(3) => (false, CacheFormat::V3),
+            Ok(CACHE_VERSION) => (true, CacheFormat::Current),
+            _ => return Ok(None),
+        };
+        let (Ok(timestamp), Ok(count)) = (rest.read_u64_le().await, rest.read_u32_le().await)
+        else {
+            return Ok(None);
+        };
+
+        let mut entries: Vec<Entry> = Vec::new();
+        for _ in 0..count {
+            let Ok(entry) = Entry::read_as(&mut rest, wide).await else {
+                break;
+            };
+            let sane = entry.rank > 0
+                && entry.score.is_finite()
+                && entries
+                    .last()
+                    .is_none_or(|last| entry.rank >= last.rank && entry.score <= last.score);
+            if !sane {
+                break;
+            }
+            entries.push(entry);
+        }
+
+        if entries.is_empty() {
+            return Ok(None);
+        }
+        let timestamp = Duration::from_secs(timestamp);
+        Ok(Some((Self { timestamp, entries }, format)))
+    }
+
+    /// rewrite the cache at `path` in the current format
+    ///
+    /// returns the format it was in before
+    pub async fn migrate(path: impl AsRef<Path>) -> io::Result<CacheFormat> {
+        let path = path.as_ref();
+        // nothing gets to write in between
+        let _lock = CacheLock::acquire(path, true, LOCK_TIMEOUT).await?;
+        let (lb, format) = Self::read_unlocked(path).await?;
+        if !matches!(format, CacheFormat::Current | CacheFormat::Json) {
+            lb.write_unlocked(path, Encoding::Binary, false).await?;
+        }
+
+        Ok(format)
+    }
+
+    async fn parse_cache(bytes: &[u8]) -> io::Result<(Self, CacheFormat)> {
+        // compressed caches are any of the others, inside a zstd frame
+        if bytes.starts_with(&ZSTD_MAGIC) {
+            let bytes = zstd::decode_all(bytes)?;
+            return Box::pin(Self::parse_cache(&bytes)).await;
+        }
+
+        // a legacy cache starts with its timestamp, which can be a {
+        // too, so one that isn't json still gets read as the others
+        let mut json = None;
+        if bytes.trim_ascii_start().starts_with(b"{") {
+            match serde_json::from_slice(bytes) {
+                Ok(lb) => return Ok((lb, CacheFormat::Json)),
+                Err(e) => json = Some(e),
+            }
+        }
+
+        if let Some(mut rest) = bytes.strip_prefix(CACHE_MAGIC) {
+            let version = rest.read_u8().await?;
+            return match version {
+                2 => Ok((
+                    Self::read_entries(&mut rest, None, false).await?,
+                    CacheFormat::V2,
+                )),
+                3 | CACHE_VERSION => {
+                    // a cache that made it to disk wrong is better off
+                    // thrown away than diffed against
+                    let (data, sum) = bytes.split_at(bytes.len().saturating_sub(4).max(5));
+                    let expected = crc32fast::hash(data);
+                    let found = sum.try_into().map(u32::from_le_bytes).unwrap_or(!expected);
+                    if expected != found {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            CacheCorrupt { expected, found },
+                        ));
+                    }
+
+                    let mut rest = &data[5..];
+                    let wide = version == CACHE_VERSION;
+                    let lb = Self::read_entries(&mut rest, None, wide).await?;
+                    Ok((lb, if wide { CacheFormat::Current } else { CacheFormat::V3 }))
+                }
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown cache format version {}", version),
+                )),
+            };
+        }
+
+        // neither of the old formats had a magic, and a legacy cache can
+        // start with a 1 too. go with whichever one reads to the end exactly
+        if let Some(mut rest) = bytes.strip_prefix(&[1]) {
+            if let Ok(lb) = Self::read_entries(&mut rest, None, false).await {
+                if rest.is_empty() {
+                    return Ok((lb, CacheFormat::V1));
+                }
+            }
+        }
+
+        let mut rest = bytes;
+        match (Self::read_entries(&mut rest, Some(1000), false).await, json) {
+            (Ok(lb), _) if rest.is_empty() => Ok((lb, CacheFormat::Legacy)),
+            // then it was most likely meant to be json
+            (_, Some(e)) => Err(e.into()),
+            (Err(e), None) => Err(e),
+            (Ok(_), None) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a cache, or a corrupted one",
+            )),
+        }
+    }
+
+    /// read the timestamp and entries that follow the header
+    ///
+    /// the number of entries is read first unless it's given.
+    /// names have a u16 length if `wide`, a u8 one otherwise
+    async fn read_entries(
+        r: &mut (impl io::AsyncRead + Unpin),
+        count: Option<u32>,
+        wide: bool,
+    ) -> io::Result<Self> {
+        let timestamp = Duration::from_secs(r.read_u64_le().await?);
+
+        let count = match count {
+            Some(count) => count,
+            None => r.read_u32_le().await?,
+        };
         let mut entries = Vec::new();
-        for _ in 0..1000 {
-            entries.push(Entry::read(&mut buf).await?);
+        for _ in 0..count {
+            entries.push(Entry::read_as(r, wide).await?);
         }
 
         Ok(Self { timestamp, entries })
     }
 
-    /// write the Leaderboard to cache
-    pub async fn cache(&self) -> io::Result<()> {
-        let mut cache = File::create("cache").await?;
-        let mut buf = io::BufWriter::new(&mut cache);
+    /// write the Leaderboard to the cache at `path`
+    ///
+    /// the new cache is written next to the old one and moved
+    /// over it, so a crash never leaves a half written cache
+    pub async fn cache(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.cache_as(path, Encoding::Binary, false).await
+    }
+
+    /// same as `cache`, in any encoding and maybe compressed
+    ///
+    /// `from_cache` reads them all
+    pub async fn cache_as(
+        &self,
+        path: impl AsRef<Path>,
+        encoding: Encoding,
+        compress: bool,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let _lock = CacheLock::acquire(path, true, LOCK_TIMEOUT).await?;
+        self.write_unlocked(path, encoding, compress).await
+    }
+
+    /// `cache_as`, without the lock
+    pub(crate) async fn write_unlocked(
+        &self,
+        path: &Path,
+        encoding: Encoding,
+        compress: bool,
+    ) -> io::Result<()> {
+        let bytes = self.to_bytes(encoding, compress).await?;
+        let tmp = path.with_extension("tmp");
 
+        let mut cache = File::create(&tmp).await?;
+        cache.write_all(&bytes).await?;
+        cache.sync_all().await?;
+        tokio::fs::rename(&tmp, path).await?;
+
+        Ok(())
+    }
+
+    /// the bytes `cache_as` would write
+    pub(crate) async fn to_bytes(&self, encoding: Encoding, compress: bool) -> io::Result<Vec<u8>> {
+        let mut bytes = match encoding {
+            Encoding::Binary => self.to_binary().await?,
+            Encoding::Json => serde_json::to_vec_pretty(self)?,
+        };
+        if compress {
+            bytes = zstd::encode_all(&bytes[..], 0)?;
+        }
+        Ok(bytes)
+    }
+
+    pub(crate) async fn to_binary(&self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+
+        buf.write_all(CACHE_MAGIC).await?;
+        buf.write_u8(CACHE_VERSION).await?;
         buf.write_u64_le(self.timestamp.as_secs()).await?;
 
-        for entry in 0..1000 {
-            self.entries[entry].write(&mut buf).await?;
+        let count = u32::try_from(self.entries.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many entries"))?;
+        buf.write_u32_le(count).await?;
+        for entry in &self.entries {
+            entry.write(&mut buf).await?;
         }
 
-        buf.flush().await?;
+        let sum = crc32fast::hash(&buf);
+        buf.write_u32_le(sum).await?;
 
-        Ok(())
+        Ok(buf)
+    }
+
+    /// when the board was fetched, since the unix epoch
+    pub fn timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    /// number of entries on the board
+    pub fn len(&self) -> usize {
+        self.entries.len()
     }
 
-    /// Get all Pb objects from two different leaderboards
-    pub fn pbs<'a>(&'a self, new: &'a Self) -> Vec<Pb<'a>> {
-        Pb::diff(&self.entries, &new.entries)
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// every entry, best first
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// the worst rank on the board
+    pub fn max_rank(&self) -> u16 {
+        self.entries.iter().map(|e| e.rank).max().unwrap_or(0)
+    }
+
+    /// Get every Event between two different leaderboards
+    ///
+    /// only ranks that both boards cover are compared, otherwise
+    /// everyone past the end of a short board would look like
+    /// they just joined when the board is back to full size.
+    pub fn events<'a>(&'a self, new: &'a Self) -> Vec<Event<'a>> {
+        self.events_with(new, DiffOptions::standard())
+    }
+
+    /// same as `events`, with a custom idea of what a pb is
+    pub fn events_with<'a>(&'a self, new: &'a Self, options: &'a DiffOptions) -> Vec<Event<'a>> {
+        self.events_indexed(&Index::new(self), new, options)
+    }
+
+    /// same as `events_with`, with an `Index` of this board
+    /// that's kept around between polls
+    pub fn events_indexed<'a>(
+        &'a self,
+        index: &Index,
+        new: &'a Self,
+        options: &'a DiffOptions,
+    ) -> Vec<Event<'a>> {
+        let ranks = self.max_rank().min(new.max_rank());
+        // entries are in rank order
+        let end = new.entries.partition_point(|e| e.rank <= ranks);
+
+        Event::diff_indexed(&self.entries, index, &new.entries[..end], options)
     }
 }
 
-/// an object representing a Personal Best
-/// 
-/// it is derived from two leaderboards, so it
-/// contains data from those fellas
-#[derive(Debug)]
-pub struct Pb<'a> {
-    old: Option<&'a Entry>,
-    new: &'a Entry,
+/// where everyone is on a board, by user id
+///
+/// a diff has to find everyone on the old board, and hashing
+/// every entry again each poll adds up on big boards. so the
+/// poller keeps one of these around, and only updates it for
+/// the people who moved
+#[derive(Debug, Default, Clone)]
+pub struct Index {
+    positions: HashMap<u32, usize, BuildHasherDefault<IdHasher>>,
+}
+
+/// user ids are already all over the place, so they don't need
+/// the default hasher's care, and it's most of what an index costs
+#[derive(Default)]
+struct IdHasher(u64);
+
+impl Hasher for IdHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u64(b as u64);
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+}
+
+impl Index {
+    pub fn new(lb: &Leaderboard) -> Self {
+        Self::of(&lb.entries)
+    }
+
+    fn of(entries: &[Entry]) -> Self {
+        Self {
+            positions: entries
+                .iter()
+                .enumerate()
+                .map(|(i, e)| (e.user_id, i))
+                .collect(),
+        }
+    }
+
+    /// where someone is on the indexed board, trying `hint` first,
+    /// since most people are right after whoever was before them
+    fn find(&self, entries: &[Entry], user_id: u32, hint: usize) -> Option<usize> {
+        let theirs = |&i: &usize| entries.get(i).is_some_and(|e| e.user_id == user_id);
+        Some(hint)
+            .filter(theirs)
+            .or_else(|| self.positions.get(&user_id).copied().filter(theirs))
+    }
+
+    /// move the index on from the `old` board to the `new` one
+    pub fn update(&mut self, old: &Leaderboard, new: &Leaderboard) {
+        self.update_entries(&old.entries, &new.entries)
+    }
+
+    fn update_entries(&mut self, old: &[Entry], new: &[Entry]) {
+        let user = |entries: &[Entry], i| entries.get(i).map(|e: &Entry| e.user_id);
+        for (i, e) in new.iter().enumerate() {
+            if user(old, i) != Some(e.user_id) {
+                self.positions.insert(e.user_id, i);
+            }
+        }
+        // whoever's gone, and didn't just move
+        for (i, e) in old.iter().enumerate() {
+            if user(new, i) != Some(e.user_id) && self.positions.get(&e.user_id) == Some(&i) {
+                self.positions.remove(&e.user_id);
+            }
+        }
+    }
+}
+
+/// something that happened between two leaderboards
+///
+/// every kind has the entry as it is now, and most have
+/// the entry they had before, see `Event::previous`
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// someone showed up on the board for the first time
+    NewPlayer {
+        new: &'a Entry,
+        /// whoever's right above and below them now
+        above: Option<&'a Entry>,
+        below: Option<&'a Entry>,
+        /// how many entries were on the board with them
+        out_of: usize,
+    },
+    /// someone took rank 1
+    WorldRecord { old: &'a Entry, new: &'a Entry },
+    /// someone got past one of `DiffOptions::milestones`,
+    /// the highest one if it was more than one
+    Milestone {
+        old: &'a Entry,
+        new: &'a Entry,
+        milestone: &'a Milestone,
+    },
+    /// a regular old pb
+    Improvement { old: &'a Entry, new: &'a Entry },
+    /// someone moved without a new run, because they got
+    /// passed or someone above them left, see `RankChanges`
+    RankChange { old: &'a Entry, new: &'a Entry },
+    /// someone's on the board under a different name
+    NameChanged { old: &'a Entry, new: &'a Entry },
+    /// someone's best score went down, because moderators
+    /// removed a run or it got rescored
+    ScoreRemoved { old: &'a Entry, new: &'a Entry },
+    /// someone got into the `top` ranks, see `DiffOptions::brackets`
+    EnteredTop {
+        old: Option<&'a Entry>,
+        new: &'a Entry,
+        top: u16,
+    },
+    /// someone's pb got them close to whoever's a rank
+    /// up, see `CloseRaces`
+    CloseRace { new: &'a Entry, ahead: &'a Entry },
+}
+
+/// knobs for deciding what counts as a pb, see `Event::diff_with`
+///
+/// ```toml
+/// [board.diff]
+/// epsilon = 0.001
+/// rank_changes = { top = 10 }
+/// close_races = { top = 10, margin = 0.5 }
+/// milestones = [{ score = 400 }, { score = 450, banner = "{name} BROKE 450" }]
+/// brackets = [10, 100]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiffOptions {
+    /// score changes up to this much on the same run are ignored
+    pub epsilon: f32,
+    /// scores worth a banner when someone gets past them, just 400
+    /// unless it's set
+    pub milestones: Vec<Milestone>,
+    /// ranks worth an event when someone gets into them, like
+    /// the top 10. none unless it's set
+    pub brackets: Vec<u16>,
+    /// also look for people who moved without a new run,
+    /// off unless it's set
+    pub rank_changes: Option<RankChanges>,
+    /// also look for pbs that got close to the next rank up,
+    /// off unless it's set
+    pub close_races: Option<CloseRaces>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            epsilon: 0.0005,
+            milestones: vec![Milestone::new(400.0)],
+            brackets: Vec::new(),
+            rank_changes: None,
+            close_races: None,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// the defaults, for as long as the events found with them
+    fn standard() -> &'static Self {
+        static STANDARD: LazyLock<DiffOptions> = LazyLock::new(DiffOptions::default);
+        &STANDARD
+    }
+}
+
+/// a score worth making a fuss about, see `DiffOptions::milestones`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Milestone {
+    pub score: f32,
+    /// shown above the message, with `{name}`, `{score}` and
+    /// `{threshold}` filled in. "NEW {threshold}" by default
+    #[serde(default)]
+    pub banner: Option<String>,
+}
+
+impl Milestone {
+    pub fn new(score: f32) -> Self {
+        Self {
+            score,
+            banner: None,
+        }
+    }
+
+    /// use this instead of "NEW {threshold}"
+    pub fn with_banner(mut self, banner: &str) -> Self {
+        self.banner = Some(banner.to_string());
+        self
+    }
+
+    /// the banner for someone who just got past it
+    pub fn banner_for(&self, entry: &Entry) -> String {
+        self.banner
+            .as_deref()
+            .unwrap_or("NEW {threshold}")
+            .replace("{threshold}", &self.score.to_string())
+            .replace("{score}", &entry.score.to_string())
+            .replace("{name}", &entry.name)
+    }
+
+    /// whether a score went from below it to above it
+    fn passed(&self, old: &Entry, new: &Entry) -> bool {
+        new.score > self.score && self.score > old.score
+    }
+}
+
+/// which moves are worth an `Event::RankChange`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RankChanges {
+    /// only moves into, out of or within the top this many ranks
+    pub top: u16,
+    /// only moves of at least this many ranks
+    pub min_ranks: u16,
+}
+
+impl Default for RankChanges {
+    fn default() -> Self {
+        Self {
+            top: 10,
+            min_ranks: 1,
+        }
+    }
+}
+
+impl RankChanges {
+    fn matches(&self, old: &Entry, new: &Entry) -> bool {
+        old.rank.min(new.rank) <= self.top && old.rank.abs_diff(new.rank) >= self.min_ranks.max(1)
+    }
+}
+
+/// which gaps are worth an `Event::CloseRace`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CloseRaces {
+    /// only races for the top this many ranks
+    pub top: u16,
+    /// how close someone has to get to the next rank up
+    pub margin: f32,
+}
+
+impl Default for CloseRaces {
+    fn default() -> Self {
+        Self {
+            top: 10,
+            margin: 1.0,
+        }
+    }
+}
+
+impl CloseRaces {
+    /// whether the gap between `new` and `ahead` just got under the margin
+    fn closed(
+        &self,
+        old: Option<&Entry>,
+        new: &Entry,
+        ahead_old: Option<&Entry>,
+        ahead: &Entry,
+    ) -> bool {
+        let close = |new: &Entry, ahead: &Entry| ahead.score - new.score < self.margin;
+        let was_close = match (old, ahead_old) {
+            (Some(old), Some(ahead_old)) => close(old, ahead_old),
+            _ => false,
+        };
+        new.rank <= self.top && close(new, ahead) && !was_close
+    }
+}
+
+/// the kinds of Event, without anything in them
+///
+/// `Pb` is an `Event::Improvement`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// someone took rank 1
+    WorldRecord,
+    /// someone got past one of the board's milestones
+    Milestone,
+    /// a regular old pb
+    Pb,
+    /// someone showed up on the board for the first time
+    NewPlayer,
+    /// someone moved without a new run
+    RankChange,
+    /// someone's on the board under a different name
+    NameChanged,
+    /// someone's best score went down
+    ScoreRemoved,
+    /// someone got into the top so many ranks
+    EnteredTop,
+    /// someone got close to the next rank up
+    CloseRace,
+}
+
+impl EventKind {
+    /// whether someone actually got a better score, rather
+    /// than this being more about a pb or someone else's
+    pub fn is_pb(self) -> bool {
+        !matches!(
+            self,
+            Self::RankChange
+                | Self::NameChanged
+                | Self::ScoreRemoved
+                | Self::EnteredTop
+                | Self::CloseRace
+        )
+    }
 }
 
-impl<'a> Pb<'a> {
-    fn new(old: Option<&'a Entry>, new: &'a Entry) -> Self {
-        Self { old, new }
+/// how big of a deal an Event is, least first
+///
+/// ```toml
+/// min_severity = "top_pb"
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// rank and name changes, removed scores
+    Minor,
+    Pb,
+    /// a pb in the top `Severity::TOP`
+    TopPb,
+    Milestone,
+    WorldRecord,
+}
+
+impl Severity {
+    /// ranks that make a pb a `TopPb`
+    pub const TOP: u16 = 10;
+}
+
+impl<'a> Event<'a> {
+    /// figure out what kind of pb someone who was already on the board got
+    fn pb(old: &'a Entry, new: &'a Entry, options: &'a DiffOptions) -> Self {
+        if new.rank == 1 {
+            return Self::WorldRecord { old, new };
+        }
+
+        let passed = options.milestones.iter().filter(|m| m.passed(old, new));
+        match passed.max_by(|a, b| a.score.total_cmp(&b.score)) {
+            Some(milestone) => Self::Milestone {
+                old,
+                new,
+                milestone,
+            },
+            None => Self::Improvement { old, new },
+        }
+    }
+
+    /// the entry as it is now
+    pub fn entry(&self) -> &'a Entry {
+        match *self {
+            Self::NewPlayer { new, .. }
+            | Self::WorldRecord { new, .. }
+            | Self::Milestone { new, .. }
+            | Self::Improvement { new, .. }
+            | Self::RankChange { new, .. }
+            | Self::NameChanged { new, .. }
+            | Self::ScoreRemoved { new, .. }
+            | Self::EnteredTop { new, .. }
+            | Self::CloseRace { new, .. } => new,
+        }
+    }
+
+    /// the entry as it was before, if they were on the board
+    pub fn previous(&self) -> Option<&'a Entry> {
+        match *self {
+            Self::NewPlayer { .. } | Self::CloseRace { .. } => None,
+            Self::WorldRecord { old, .. }
+            | Self::Milestone { old, .. }
+            | Self::Improvement { old, .. }
+            | Self::RankChange { old, .. }
+            | Self::NameChanged { old, .. }
+            | Self::ScoreRemoved { old, .. } => Some(old),
+            Self::EnteredTop { old, .. } => old,
+        }
+    }
+
+    /// how much the score went up, if it's a pb and there was an old score
+    pub fn delta(&self) -> Option<f32> {
+        match self.kind().is_pb() {
+            true => self.previous().map(|old| self.entry().score - old.score),
+            false => None,
+        }
+    }
+
+    /// how many ranks were gained, if there was an old
+    /// rank and it was worse than the new one
+    pub fn ranks_gained(&self) -> Option<u16> {
+        self.previous()
+            .and_then(|old| old.rank.checked_sub(self.entry().rank))
+    }
+
+    /// how many ranks someone moved, up is positive. none if
+    /// they weren't on the board before
+    pub fn rank_delta(&self) -> Option<i32> {
+        self.previous()
+            .map(|old| old.rank as i32 - self.entry().rank as i32)
+    }
+
+    /// the smallest top percent of the board someone new is in,
+    /// none for everything else
+    pub fn top_percent(&self) -> Option<usize> {
+        match *self {
+            Self::NewPlayer { new, out_of, .. } if out_of > 0 => {
+                Some((new.rank as usize * 100).div_ceil(out_of).min(100))
+            }
+            _ => None,
+        }
+    }
+
+    /// link that opens the run in the game
+    pub fn run_link(&self) -> String {
+        self.entry().run_link()
+    }
+
+    /// how big of a deal this is, see `Severity`
+    ///
+    /// brackets and close races go with the pb they're about
+    pub fn severity(&self) -> Severity {
+        match self.kind() {
+            EventKind::WorldRecord => Severity::WorldRecord,
+            EventKind::Milestone => Severity::Milestone,
+            EventKind::RankChange | EventKind::NameChanged | EventKind::ScoreRemoved => {
+                Severity::Minor
+            }
+            _ if self.entry().rank <= Severity::TOP => Severity::TopPb,
+            _ => Severity::Pb,
+        }
+    }
+
+    /// what kind of Event this is
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::NewPlayer { .. } => EventKind::NewPlayer,
+            Self::WorldRecord { .. } => EventKind::WorldRecord,
+            Self::Milestone { .. } => EventKind::Milestone,
+            Self::Improvement { .. } => EventKind::Pb,
+            Self::RankChange { .. } => EventKind::RankChange,
+            Self::NameChanged { .. } => EventKind::NameChanged,
+            Self::ScoreRemoved { .. } => EventKind::ScoreRemoved,
+            Self::EnteredTop { .. } => EventKind::EnteredTop,
+            Self::CloseRace { .. } => EventKind::CloseRace,
+        }
     }
 
     /// check two vecs of entries to see if there were any
     /// peebs
-    pub fn diff(old: &'a Vec<Entry>, new: &'a Vec<Entry>) -> Vec<Self> {
+    pub fn diff(old: &'a [Entry], new: &'a [Entry]) -> Vec<Self> {
+        Self::diff_with(old, new, DiffOptions::standard())
+    }
+
+    /// same as `diff`, with a custom idea of what a pb is
+    ///
+    /// someone has a pb if they:
+    /// - weren't on the old board
+    /// - have a different run, even if the score is the same
+    /// - have the same run, but the score went up by more than
+    ///   `options.epsilon`. smaller changes are rounding noise
+    ///
+    /// anyone whose score went down by more than `options.epsilon`
+    /// gets a `ScoreRemoved` instead, and with `options.rank_changes`,
+    /// anyone else whose rank changed.
+    /// anyone who changed their name gets an event for that first, and
+    /// anyone who got into one of `options.brackets` one after. with
+    /// `options.close_races`, so does anyone whose pb got them close
+    /// to whoever's ahead
+    pub fn diff_with(old: &'a [Entry], new: &'a [Entry], options: &'a DiffOptions) -> Vec<Self> {
+        Self::diff_indexed(old, &Index::of(old), new, options)
+    }
+
+    /// same as `diff_with`, with an `Index` of `old`
+    pub fn diff_indexed(
+        old: &'a [Entry],
+        index: &Index,
+        new: &'a [Entry],
+        options: &'a DiffOptions,
+    ) -> Vec<Self> {
         let mut pbs = Vec::new();
-        let mut old: HashMap<_, _> = old.iter().map(|e| (e.user_id, e)).collect();
+        // whoever's one rank up, and where they were
+        let mut above: Option<(&Entry, Option<&Entry>)> = None;
+        let mut hint = 0;
+        let board = new;
 
-        for new in new {
-            let old = old.remove(&new.user_id);
+        for (at, new) in board.iter().enumerate() {
+            let found = index.find(old, new.user_id, hint);
+            if let Some(i) = found {
+                hint = i + 1;
+            }
+            let old = found.map(|i| &old[i]);
+            let mut pb = false;
 
             if let Some(old) = old {
-                if new.run_id == old.run_id {
-                    continue;
+                if old.name != new.name {
+                    pbs.push(Self::NameChanged { old, new });
+                }
+                let rescored = new.score - old.score > options.epsilon;
+                if old.score - new.score > options.epsilon {
+                    pbs.push(Self::ScoreRemoved { old, new });
+                } else if new.run_id == old.run_id && !rescored {
+                    let moved = options.rank_changes.as_ref();
+                    if moved.is_some_and(|m| m.matches(old, new)) {
+                        pbs.push(Self::RankChange { old, new });
+                    }
+                } else {
+                    pbs.push(Self::pb(old, new, options));
+                    pb = true;
                 }
-                pbs.push(Self::new(Some(old), new));
             } else {
-                pbs.push(Self::new(None, new));
+                pbs.push(Self::NewPlayer {
+                    new,
+                    above: above.map(|(above, _)| above),
+                    below: board.get(at + 1),
+                    out_of: board.len(),
+                });
+                pb = true;
             }
-        }
 
-        pbs
-    }
-}
-
-/// Implements Display for Pb
-/// 
-/// Which means that it knows how to be turned
-/// into a pretty string
-impl std::fmt::Display for Pb<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(old) = self.old {
-            if self.new.rank == 1 {
-                writeln!(f, "---  NEW WORLD RECORD  ---")?;
-            } else if self.new.score > 400.0 && 400.0 > old.score {
-                writeln!(f, "---  NEW 400  ---")?;
+            // the smallest bracket they got into, if any
+            let top = options
+                .brackets
+                .iter()
+                .copied()
+                .filter(|&top| new.rank <= top && old.is_none_or(|old| old.rank > top))
+                .min();
+            if let Some(top) = top {
+                pbs.push(Self::EnteredTop { old, new, top });
             }
 
-            writeln!(
-                f,
-                "{} just got a new high score! Score: {} (+{})",
-                self.new.name,
-                self.new.score,
-                self.new.score - old.score
-            )?;
-
-            if let Some(sub) = old.rank.checked_sub(self.new.rank) {
-                writeln!(
-                    f,
-                    "They are now rank #{}, gaining {} ranks.",
-                    self.new.rank, sub
-                )?;
-            } else {
-                writeln!(f, "They are now rank #{}.", self.new.rank)?;
+            let races = options.close_races.as_ref().filter(|_| pb);
+            if let (Some(races), Some((ahead, ahead_old))) = (races, above) {
+                if races.closed(old, new, ahead_old, ahead) {
+                    pbs.push(Self::CloseRace { new, ahead });
+                }
             }
-        } else {
-            writeln!(
-                f,
-                "{} just got a new high score! Score: {}",
-                self.new.name, self.new.score
-            )?;
-            writeln!(f, "They are now rank #{}", self.new.rank)?;
+            above = Some((new, old));
         }
-        writeln!(f, "Watch in-game: hyperdemon://run/{}", self.new.run_id)?;
 
-        Ok(())
+        pbs
     }
 }
 
@@ -307,9 +1299,548 @@ mod test {
             ],
         };
 
-        let pbs = old.pbs(&new);
+        let events = old.events(&new);
+
+        assert_eq!(events[0].previous().unwrap().name, old.entries[1].name);
+        assert_eq!(events[0].entry().name, new.entries[0].name);
+        assert_eq!(events[0].kind(), EventKind::WorldRecord);
+    }
+
+    #[test]
+    fn test_pb_grown_board() {
+        let old = Leaderboard {
+            timestamp: Duration::from_secs(0),
+            entries: vec![Entry {
+                rank: 1,
+                name: "possm".to_string(),
+                user_id: 1,
+                run_id: 1,
+                score: 400.0,
+            }],
+        };
+
+        let new = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![
+                Entry {
+                    rank: 1,
+                    name: "possm".to_string(),
+                    user_id: 1,
+                    run_id: 1,
+                    score: 400.0,
+                },
+                Entry {
+                    rank: 2,
+                    name: "fennekal".to_string(),
+                    user_id: 2,
+                    run_id: 2,
+                    score: 399.0,
+                },
+            ],
+        };
+
+        // fennekal was just past the end of the old board
+        assert!(old.events(&new).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_round_trip() {
+        let path = std::env::temp_dir().join(format!("hdget-cache-{}", std::process::id()));
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![Entry {
+                rank: 1,
+                name: "possm".to_string(),
+                user_id: 1,
+                run_id: 1,
+                score: 400.0,
+            }],
+        };
+
+        lb.cache(&path).await.unwrap();
+        let read = Leaderboard::from_cache(&path).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        assert_eq!(read.timestamp, lb.timestamp);
+        assert_eq!(read.len(), 1);
+        assert_eq!(read.entries[0].name, "possm");
+
+        lb.cache_as(&path, Encoding::Json, false).await.unwrap();
+        let (read, format) = Leaderboard::read_cache(&path).await.unwrap();
+
+        assert_eq!(format, CacheFormat::Json);
+        assert_eq!(read.timestamp, lb.timestamp);
+        assert_eq!(read.entries[0].name, "possm");
+
+        for encoding in [Encoding::Binary, Encoding::Json] {
+            lb.cache_as(&path, encoding, true).await.unwrap();
+            let read = Leaderboard::from_cache(&path).await.unwrap();
+            assert_eq!(read.entries[0].name, "possm");
+        }
+        tokio::fs::remove_file(&path).await.unwrap();
+        tokio::fs::remove_file(lock_path(&path)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_lock() {
+        let path = std::env::temp_dir().join(format!("hdget-lock-{}", std::process::id()));
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![],
+        };
+        lb.cache(&path).await.unwrap();
+
+        // readers don't get in each other's way
+        let read = CacheLock::acquire(&path, false, Duration::ZERO).await.unwrap();
+        CacheLock::acquire(&path, false, Duration::ZERO).await.unwrap();
+        let e = CacheLock::acquire(&path, true, Duration::ZERO)
+            .await
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::ResourceBusy);
+        assert!(e.get_ref().unwrap().is::<CacheBusy>());
+
+        drop(read);
+        CacheLock::acquire(&path, true, Duration::ZERO).await.unwrap();
+        Leaderboard::from_cache(&path).await.unwrap();
+
+        tokio::fs::remove_file(&path).await.unwrap();
+        tokio::fs::remove_file(lock_path(&path)).await.unwrap();
+    }
+
+    /// write an entry the way caches before version 4 did
+    async fn write_narrow(entry: &Entry, w: &mut Vec<u8>) {
+        w.write_u16_le(entry.rank).await.unwrap();
+        w.write_u8(entry.name.len() as u8).await.unwrap();
+        w.write_all(entry.name.as_bytes()).await.unwrap();
+        w.write_u32_le(entry.user_id).await.unwrap();
+        w.write_u32_le(entry.run_id).await.unwrap();
+        w.write_f32_le(entry.score).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_names() {
+        for name in ["ポッサム", "possm 🐀🐀", "피에르", &"ü".repeat(200)] {
+            let entry = Entry {
+                rank: 1,
+                name: name.to_string(),
+                user_id: 1,
+                run_id: 1,
+                score: 400.0,
+            };
+            let mut buf = Vec::new();
+            entry.write(&mut buf).await.unwrap();
+            let read = Entry::read(&mut &buf[..]).await.unwrap();
+            assert_eq!(read.name, name);
+        }
+
+        // a name cut off in the middle of a character
+        let mut buf = Vec::new();
+        buf.write_u16_le(1).await.unwrap();
+        buf.write_u16_le(2).await.unwrap();
+        buf.write_all(&"ポ".as_bytes()[..2]).await.unwrap();
+        buf.write_all(&[0; 12]).await.unwrap();
+        let e = Entry::read(&mut &buf[..]).await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        let entry = Entry {
+            rank: 1,
+            name: "a".repeat(70000),
+            user_id: 1,
+            run_id: 1,
+            score: 400.0,
+        };
+        assert!(entry.write(&mut Vec::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_old_caches() {
+        let entry = |rank| Entry {
+            rank,
+            name: "possm".to_string(),
+            user_id: rank as u32,
+            run_id: 1,
+            score: 400.0,
+        };
+
+        let mut legacy = Vec::new();
+        legacy.write_u64_le(600).await.unwrap();
+        for rank in 1..=1000 {
+            write_narrow(&entry(rank), &mut legacy).await;
+        }
+        let (lb, format) = Leaderboard::parse_cache(&legacy).await.unwrap();
+        assert_eq!(format, CacheFormat::Legacy);
+        assert_eq!(lb.len(), 1000);
+        assert_eq!(lb.timestamp, Duration::from_secs(600));
+
+        // 0x6553f17b, which starts with a { like json
+        let mut legacy = Vec::new();
+        legacy.write_u64_le(1700000123).await.unwrap();
+        for rank in 1..=1000 {
+            write_narrow(&entry(rank), &mut legacy).await;
+        }
+        assert_eq!(legacy[0], b'{');
+        let (lb, format) = Leaderboard::parse_cache(&legacy).await.unwrap();
+        assert_eq!(format, CacheFormat::Legacy);
+        assert_eq!(lb.timestamp, Duration::from_secs(1700000123));
+
+        let mut v1 = vec![1];
+        v1.write_u64_le(600).await.unwrap();
+        v1.write_u32_le(2).await.unwrap();
+        write_narrow(&entry(1), &mut v1).await;
+        write_narrow(&entry(2), &mut v1).await;
+        let (lb, format) = Leaderboard::parse_cache(&v1).await.unwrap();
+        assert_eq!(format, CacheFormat::V1);
+        assert_eq!(lb.len(), 2);
+
+        assert!(Leaderboard::parse_cache(b"garbage").await.is_err());
+
+        let mut v3 = CACHE_MAGIC.to_vec();
+        v3.write_u8(3).await.unwrap();
+        v3.write_u64_le(600).await.unwrap();
+        v3.write_u32_le(1).await.unwrap();
+        write_narrow(&entry(1), &mut v3).await;
+        let sum = crc32fast::hash(&v3);
+        v3.write_u32_le(sum).await.unwrap();
+        let (lb, format) = Leaderboard::parse_cache(&v3).await.unwrap();
+        assert_eq!(format, CacheFormat::V3);
+        assert_eq!(lb.entries[0].name, "possm");
+
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![entry(1)],
+        };
+        let mut current = lb.to_binary().await.unwrap();
+        let (_, format) = Leaderboard::parse_cache(&current).await.unwrap();
+        assert_eq!(format, CacheFormat::Current);
+
+        // flip a bit in the score
+        let last = current.len() - 5;
+        current[last] ^= 1;
+        let e = Leaderboard::parse_cache(&current).await.unwrap_err();
+        assert!(e.get_ref().unwrap().is::<CacheCorrupt>());
+    }
+
+    #[tokio::test]
+    async fn test_salvage() {
+        let path = std::env::temp_dir().join(format!("hdget-salvage-{}", std::process::id()));
+        let entry = |rank: u16| Entry {
+            rank,
+            name: format!("player {}", rank),
+            user_id: rank as u32,
+            run_id: rank as u32,
+            score: 500.0 - rank as f32,
+        };
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: (1..=10).map(entry).collect(),
+        };
+
+        // the header is 17 bytes, and the first 9 entries 24 each
+        let full = lb.to_binary().await.unwrap();
+        // cut off in the middle of the 8th entry
+        let mut cut = full[..17 + 7 * 24 + 5].to_vec();
+        tokio::fs::write(&path, &cut).await.unwrap();
+        assert!(Leaderboard::read_cache(&path).await.is_err());
+        let (salvaged, format) = Leaderboard::salvage(&path).await.unwrap().unwrap();
+        assert_eq!(format, CacheFormat::Current);
+        assert_eq!(salvaged.timestamp, lb.timestamp);
+        assert_eq!(salvaged.len(), 7);
+
+        // garbage in the middle, ranks go backwards from the 4th on
+        cut[17 + 3 * 24] = 0;
+        tokio::fs::write(&path, &cut).await.unwrap();
+        let (salvaged, _) = Leaderboard::salvage(&path).await.unwrap().unwrap();
+        assert_eq!(salvaged.len(), 3);
+
+        // and compressed
+        let compressed = zstd::encode_all(&full[..full.len() - 20], 0).unwrap();
+        tokio::fs::write(&path, &compressed).await.unwrap();
+        let (salvaged, _) = Leaderboard::salvage(&path).await.unwrap().unwrap();
+        assert_eq!(salvaged.len(), 9);
+
+        tokio::fs::write(&path, b"garbage").await.unwrap();
+        assert!(Leaderboard::salvage(&path).await.unwrap().is_none());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+        tokio::fs::remove_file(lock_path(&path)).await.unwrap();
+    }
+
+    #[test]
+    fn test_pb_rule() {
+        let entry = |run_id, score| Entry {
+            rank: 1,
+            name: "possm".to_string(),
+            user_id: 1,
+            run_id,
+            score,
+        };
+        let options = DiffOptions {
+            epsilon: 0.01,
+            ..Default::default()
+        };
+
+        // same run, the site rounded it differently
+        let old = [entry(1, 400.0)];
+        assert!(Event::diff_with(&old, &[entry(1, 400.005)], &options).is_empty());
+
+        // same run, but it actually got rescored
+        assert_eq!(
+            Event::diff_with(&old, &[entry(1, 401.0)], &options).len(),
+            1
+        );
+
+        // a new run that tied the old one is still a new run
+        let new = [entry(2, 400.0)];
+        let pbs = Event::diff_with(&old, &new, &options);
+        assert_eq!(pbs.len(), 1);
+        assert_eq!(pbs[0].delta(), Some(0.0));
+    }
+
+    #[test]
+    fn test_rank_changes() {
+        let entry = |rank, user_id, run_id, score| Entry {
+            rank,
+            name: format!("player {}", user_id),
+            user_id,
+            run_id,
+            score,
+        };
+        let old = [
+            entry(1, 1, 1, 410.0),
+            entry(2, 2, 2, 405.0),
+            entry(3, 3, 3, 400.0),
+            entry(4, 4, 4, 395.0),
+        ];
+        // 4 passes 2 and 3 with a new run
+        let new = [
+            entry(1, 1, 1, 410.0),
+            entry(2, 4, 5, 407.0),
+            entry(3, 2, 2, 405.0),
+            entry(4, 3, 3, 400.0),
+        ];
+
+        // off by default
+        let events = Event::diff(&old, &new);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind(), EventKind::Milestone);
+
+        let mut options = DiffOptions {
+            rank_changes: Some(RankChanges::default()),
+            ..Default::default()
+        };
+        let events = Event::diff_with(&old, &new, &options);
+        let kinds: Vec<_> = events.iter().map(|e| e.kind()).collect();
+        assert_eq!(
+            kinds,
+            [
+                EventKind::Milestone,
+                EventKind::RankChange,
+                EventKind::RankChange
+            ]
+        );
+        assert_eq!(events[1].entry().user_id, 2);
+        assert_eq!(events[1].previous().unwrap().rank, 2);
+        assert_eq!(events[1].delta(), None);
+        assert_eq!(events[1].rank_delta(), Some(-1));
+        assert_eq!(events[0].rank_delta(), Some(2));
+        assert_eq!(events[0].ranks_gained(), Some(2));
+        assert_eq!(events[0].severity(), Severity::Milestone);
+        assert_eq!(events[1].severity(), Severity::Minor);
+
+        // only the top 2 are watched, 3 dropping to 4 doesn't count
+        options.rank_changes = Some(RankChanges {
+            top: 2,
+            min_ranks: 1,
+        });
+        assert_eq!(Event::diff_with(&old, &new, &options).len(), 2);
+    }
+
+    #[test]
+    fn test_milestones() {
+        let entry = |run_id, score| Entry {
+            rank: 5,
+            name: "possm".to_string(),
+            user_id: 1,
+            run_id,
+            score,
+        };
+        let old = [entry(1, 340.0)];
+
+        // the standard one is 400
+        let new = [entry(2, 360.0)];
+        assert_eq!(Event::diff(&old, &new)[0].kind(), EventKind::Pb);
+        let new = [entry(2, 401.0)];
+        assert_eq!(Event::diff(&old, &new)[0].kind(), EventKind::Milestone);
+
+        let options = DiffOptions {
+            milestones: vec![
+                Milestone::new(350.0),
+                Milestone::new(400.0).with_banner("{name} BROKE {threshold} WITH {score}"),
+                Milestone::new(450.0),
+            ],
+            ..Default::default()
+        };
+        let new = [entry(2, 360.0)];
+        let events = Event::diff_with(&old, &new, &options);
+        let Event::Milestone { milestone, new, .. } = events[0] else {
+            panic!("350 is a milestone");
+        };
+        assert_eq!(milestone.banner_for(new), "NEW 350");
+
+        // only the highest one it got past counts
+        let new = [entry(2, 410.5)];
+        let events = Event::diff_with(&old, &new, &options);
+        let Event::Milestone { milestone, new, .. } = events[0] else {
+            panic!("400 is a milestone");
+        };
+        assert_eq!(milestone.banner_for(new), "possm BROKE 400 WITH 410.5");
+    }
+
+    #[test]
+    fn test_score_removed() {
+        let entry = |rank, user_id, run_id, score| Entry {
+            rank,
+            name: format!("player {}", user_id),
+            user_id,
+            run_id,
+            score,
+        };
+        let old = [entry(1, 1, 1, 420.0), entry(2, 2, 2, 410.0)];
+        // 1's run got deleted, so their older one is back
+        let new = [entry(1, 2, 2, 410.0), entry(2, 1, 3, 380.0)];
+
+        let events = Event::diff(&old, &new);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind(), EventKind::ScoreRemoved);
+        assert_eq!(events[0].previous().unwrap().run_id, 1);
+        assert_eq!(events[0].entry().run_id, 3);
+        assert_eq!(events[0].delta(), None);
+
+        // rounding noise on the same run isn't a removal
+        let new = [entry(1, 1, 1, 419.9999), entry(2, 2, 2, 410.0)];
+        assert!(Event::diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_index() {
+        let entry = |rank, user_id| Entry {
+            rank,
+            name: format!("player {}", user_id),
+            user_id,
+            run_id: user_id,
+            score: 500.0 - rank as f32,
+        };
+        let mut users: Vec<u32> = (1..=50).collect();
+        let mut old: Vec<_> = users.iter().zip(1..).map(|(&u, r)| entry(r, u)).collect();
+        let mut index = Index::of(&old);
+
+        for round in 0..20u32 {
+            // someone climbs, someone drops off, and someone new shows up
+            let climber = users.remove(40 - round as usize);
+            users.insert(round as usize, climber);
+            users.remove(10);
+            users.push(100 + round);
+            let new: Vec<_> = users.iter().zip(1..).map(|(&u, r)| entry(r, u)).collect();
+
+            index.update_entries(&old, &new);
+            assert_eq!(index.positions, Index::of(&new).positions);
+            old = new;
+        }
+    }
+
+    #[test]
+    fn test_close_races() {
+        let entry = |rank, user_id, run_id, score| Entry {
+            rank,
+            name: format!("player {}", user_id),
+            user_id,
+            run_id,
+            score,
+        };
+        let old = [
+            entry(1, 1, 1, 420.0),
+            entry(2, 2, 2, 410.0),
+            entry(3, 3, 3, 409.5),
+        ];
+        let new = [
+            entry(1, 1, 1, 420.0),
+            entry(2, 2, 4, 419.5),
+            entry(3, 3, 5, 419.0),
+        ];
+        let options = DiffOptions {
+            close_races: Some(CloseRaces::default()),
+            ..Default::default()
+        };
+
+        let races: Vec<_> = Event::diff_with(&old, &new, &options)
+            .into_iter()
+            .filter_map(|e| match e {
+                Event::CloseRace { new, ahead } => Some((new.user_id, ahead.user_id)),
+                _ => None,
+            })
+            .collect();
+        // 3 was already that close to 2
+        assert_eq!(races, [(2, 1)]);
+    }
+
+    #[test]
+    fn test_brackets() {
+        let entry = |rank, user_id, run_id| Entry {
+            rank,
+            name: format!("player {}", user_id),
+            user_id,
+            run_id,
+            score: 500.0 - rank as f32,
+        };
+        let old = [entry(8, 1, 1), entry(50, 2, 2), entry(150, 3, 3)];
+        let new = [
+            entry(3, 1, 4),
+            entry(9, 2, 5),
+            entry(99, 3, 6),
+            entry(100, 4, 7),
+        ];
+        let options = DiffOptions {
+            brackets: vec![100, 10],
+            ..Default::default()
+        };
+
+        let entered: Vec<_> = Event::diff_with(&old, &new, &options)
+            .into_iter()
+            .filter_map(|e| match e {
+                Event::EnteredTop { new, top, .. } => Some((new.user_id, top)),
+                _ => None,
+            })
+            .collect();
+        // 1 was already in the top 10, and 4 is new
+        assert_eq!(entered, [(2, 10), (3, 100), (4, 100)]);
+
+        assert!(Event::diff(&old, &new)
+            .iter()
+            .all(|e| e.kind() != EventKind::EnteredTop));
+    }
+
+    #[test]
+    fn test_name_changes() {
+        let entry = |name: &str, run_id| Entry {
+            rank: 5,
+            name: name.to_string(),
+            user_id: 1,
+            run_id,
+            score: 390.0 + run_id as f32,
+        };
+
+        let old = [entry("possm", 1)];
+        let renamed = [entry("possum", 1)];
+        let events = Event::diff(&old, &renamed);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind(), EventKind::NameChanged);
+        assert_eq!(events[0].previous().unwrap().name, "possm");
+        assert_eq!(events[0].delta(), None);
 
-        assert_eq!(pbs[0].old.unwrap().name, old.entries[1].name);
-        assert_eq!(pbs[0].new.name, new.entries[0].name);
+        // and a pb on top of it
+        let pb = [entry("possum", 2)];
+        let events = Event::diff(&old, &pb);
+        let kinds: Vec<_> = events.iter().map(|e| e.kind()).collect();
+        assert_eq!(kinds, [EventKind::NameChanged, EventKind::Pb]);
     }
 }
@@ -1,2 +1,44 @@
+pub mod annotate;
+pub mod archive;
+pub mod audit;
+pub mod backup;
+pub mod baseline;
+#[cfg(feature = "bot")]
+pub mod bot;
+pub mod bucket;
+pub mod config;
+pub mod digest;
+pub mod export;
+pub mod filter;
+#[cfg(feature = "graphs")]
+pub mod graph;
+pub mod history;
 pub mod hook;
+pub mod journal;
 pub mod lb;
+pub mod live;
+pub mod mentions;
+pub mod metrics;
+pub mod net;
+pub mod notify;
+pub mod outbox;
+pub mod package;
+pub mod pinned;
+pub mod poll;
+pub mod quiet;
+pub mod render;
+pub mod replay;
+pub mod report;
+pub mod rivals;
+pub mod rules;
+pub mod schedule;
+#[cfg(feature = "script")]
+pub mod script;
+pub mod sent;
+#[cfg(feature = "postgres")]
+pub mod shared;
+pub mod sink;
+pub mod source;
+pub mod store;
+pub mod validate;
+pub mod wrapped;
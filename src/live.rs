@@ -0,0 +1,21 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::lb::Leaderboard;
+
+/// the latest leaderboard of every board, kept up to date by the
+/// pollers for whatever wants to read them while they run, like
+/// the bot's slash commands
+#[derive(Default)]
+pub struct Live(RwLock<HashMap<String, Arc<Leaderboard>>>);
+
+impl Live {
+    pub fn set(&self, board: &str, lb: &Leaderboard) {
+        let lb = Arc::new(lb.clone());
+        self.0.write().unwrap().insert(board.to_string(), lb);
+    }
+
+    pub fn get(&self, board: &str) -> Option<Arc<Leaderboard>> {
+        self.0.read().unwrap().get(board).cloned()
+    }
+}
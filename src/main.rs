@@ -1,46 +1,1221 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use hdget::render::Render;
 use hdget::*;
+use tracing::warn;
+
+/// leaderboard notifications for hyperdemon
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// path to the config file
+    #[arg(long, short, default_value = "hdget.toml")]
+    config: PathBuf,
+    /// keep the baselines in memory, and don't write any caches
+    #[arg(long)]
+    no_cache: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// poll the leaderboards and send notifications (the default)
+    Run,
+    /// show what the bot did last time it polled each board
+    Status,
+    /// manage named snapshots of the leaderboards
+    Baseline {
+        #[command(subcommand)]
+        command: BaselineCommand,
+    },
+    /// show everything that changed since a baseline
+    Diff {
+        /// name of the baseline to compare against
+        #[arg(long, required_unless_present = "since", conflicts_with = "since")]
+        against: Option<String>,
+        /// compare against the archived board from this date,
+        /// as `2024-05-01`, rfc 3339, or how long ago like `24h`
+        #[arg(long)]
+        since: Option<String>,
+        /// compare with the archived board from this date,
+        /// instead of the live one
+        #[arg(long, requires = "since")]
+        until: Option<String>,
+        /// only diff this board
+        #[arg(long)]
+        board: Option<String>,
+    },
+    /// fill in history from before the bot was running
+    Backfill {
+        /// pull old copies of the leaderboard out of the wayback machine
+        #[arg(long, required = true)]
+        from_wayback: bool,
+        /// only backfill this board
+        #[arg(long)]
+        board: Option<String>,
+    },
+    /// note down something that happened, like a patch coming out
+    Annotate {
+        text: String,
+        /// when it happened, as `2024-05-01` or rfc 3339. defaults to now
+        #[arg(long)]
+        at: Option<String>,
+    },
+    /// list every annotation
+    Annotations,
+    /// explain what the bot made of a user's entry
+    Explain {
+        user_id: u32,
+        /// only look at this cycle
+        #[arg(long)]
+        cycle: Option<u64>,
+        /// only look at this board
+        #[arg(long)]
+        board: Option<String>,
+    },
+    /// send a made up world record, milestone and pb through each
+    /// board's messages, routes and webhooks, to see how they look
+    NotifyTest {
+        /// only test this board
+        #[arg(long)]
+        board: Option<String>,
+    },
+    /// edit or delete a message that was already sent
+    Correct {
+        /// id of the event, as recorded in the sent log
+        event_id: String,
+        /// delete the message
+        #[arg(long, conflicts_with = "content")]
+        delete: bool,
+        /// replace the message with this text
+        #[arg(long, required_unless_present = "delete")]
+        content: Option<String>,
+    },
+    /// list everything a player did, out of the store
+    History {
+        /// their name or user id
+        user: String,
+        /// only look at this board
+        #[arg(long)]
+        board: Option<String>,
+    },
+    /// put together a year in review out of the store
+    Wrapped {
+        year: i32,
+        /// only look at this board
+        #[arg(long)]
+        board: Option<String>,
+        /// where to write the markdown and the chart
+        #[arg(long, default_value = ".")]
+        out: PathBuf,
+        /// also post it as a thread to this board's webhook,
+        /// which has to be a forum channel
+        #[arg(long)]
+        post: Option<String>,
+    },
+    /// dump the cached leaderboards, or archived ones, to a file
+    Export {
+        #[arg(long, value_enum, default_value = "csv")]
+        format: export::Format,
+        /// only export this board
+        #[arg(long)]
+        board: Option<String>,
+        /// export archived boards from this date on, instead of the cache
+        #[arg(long)]
+        from: Option<String>,
+        /// export archived boards from before this date. defaults to now
+        #[arg(long, requires = "from")]
+        to: Option<String>,
+        /// where to write to, defaults to stdout
+        #[arg(long, short)]
+        out: Option<PathBuf>,
+    },
+    /// delete archived snapshots that are past the retention policy
+    Prune {
+        /// only list what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+        /// only prune this board
+        #[arg(long)]
+        board: Option<String>,
+    },
+    /// rewrite caches and baselines written by older versions
+    Migrate,
+    /// pack the caches, the archive and the store into a tar.zst
+    Backup { path: PathBuf },
+    /// unpack a backup to where the config keeps things
+    Restore {
+        path: PathBuf,
+        /// replace whatever's already there
+        #[arg(long)]
+        force: bool,
+    },
+    /// things to do with hdget itself
+    #[command(name = "self")]
+    Own {
+        #[command(subcommand)]
+        command: SelfCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum BaselineCommand {
+    /// save the current leaderboard under a name
+    Save {
+        name: String,
+        /// only save this board
+        #[arg(long)]
+        board: Option<String>,
+    },
+    /// list saved baselines
+    List {
+        /// only list this board's baselines
+        #[arg(long)]
+        board: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SelfCommand {
+    /// build static linux binaries, and optionally a container image
+    Package {
+        /// targets to build, defaults to x86_64 and aarch64 musl
+        #[arg(long = "target")]
+        targets: Vec<String>,
+        /// where to put the binaries and the Dockerfile
+        #[arg(long, default_value = "dist")]
+        out: PathBuf,
+        /// build with `cross` instead of `cargo`
+        #[arg(long)]
+        cross: bool,
+        /// also build a container image with this tag
+        #[arg(long)]
+        image: Option<String>,
+    },
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let hook = hook::Hook::new();
+    // stdout is for exports and the event sink
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+
+    // doesn't need a config
+    if let Some(Command::Own { command }) = cli.command {
+        return own(command).await;
+    }
+
+    let mut config = config::Config::load(&cli.config)
+        .await
+        .with_context(|| format!("couldn't load {}", cli.config.display()))?;
+    config.memory_only |= cli.no_cache;
+    if !config.memory_only {
+        config
+            .adopt_old_caches()
+            .await
+            .with_context(|| format!("couldn't set up {}", config.data_dir.display()))?;
+    }
+
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(config).await,
+        Command::Status => status(config).await,
+        Command::Baseline { command } => baseline(config, command).await,
+        Command::Diff {
+            against,
+            since,
+            until,
+            board,
+        } => {
+            let date =
+                |s: &str| annotate::parse_date(s).with_context(|| format!("{} isn't a date", s));
+            let against = match (against, since) {
+                (Some(name), _) => Against::Baseline(name),
+                (None, Some(since)) => Against::Archive {
+                    since: date(&since)?,
+                    until: until.as_deref().map(date).transpose()?,
+                },
+                (None, None) => unreachable!(),
+            };
+            diff(config, &against, board.as_deref()).await
+        }
+        Command::Backfill { board, .. } => backfill(config, board.as_deref()).await,
+        Command::Annotate { text, at } => annotate(config, text, at.as_deref()).await,
+        Command::Annotations => annotations(config).await,
+        Command::Explain {
+            user_id,
+            cycle,
+            board,
+        } => explain(config, user_id, cycle, board.as_deref()).await,
+        Command::NotifyTest { board } => notify_test(config, board.as_deref()).await,
+        Command::Correct {
+            event_id,
+            delete,
+            content,
+        } => correct(config, &event_id, delete, content).await,
+        Command::History { user, board } => history(config, &user, board.as_deref()).await,
+        Command::Wrapped {
+            year,
+            board,
+            out,
+            post,
+        } => wrapped(config, year, board.as_deref(), &out, post.as_deref()).await,
+        Command::Export {
+            format,
+            board,
+            from,
+            to,
+            out,
+        } => {
+            let date =
+                |s: &str| annotate::parse_date(s).with_context(|| format!("{} isn't a date", s));
+            let from = from.as_deref().map(date).transpose()?;
+            let to = to.as_deref().map(date).transpose()?;
+            export(config, format, board.as_deref(), from.map(|f| (f, to)), out).await
+        }
+        Command::Prune { dry_run, board } => prune(config, dry_run, board.as_deref()).await,
+        Command::Migrate => migrate(config).await,
+        Command::Backup { path } => {
+            let contents = backup::backup(&config, &path)
+                .await
+                .with_context(|| format!("couldn't back up to {}", path.display()))?;
+            print_contents("backed up", &contents);
+            Ok(())
+        }
+        Command::Restore { path, force } => {
+            let contents = backup::restore(&config, &path, force)
+                .await
+                .with_context(|| format!("couldn't restore {}", path.display()))?;
+            print_contents("restored", &contents);
+            Ok(())
+        }
+        Command::Own { .. } => unreachable!(),
+    }
+}
+
+/// poll every board until something goes wrong
+async fn run(config: config::Config) -> anyhow::Result<()> {
+    net::set_rate(config.requests_per_second);
+
+    let config = Arc::new(config);
+    let sent = Arc::new(sent::SentLog::new(&config.sent_log));
+    let audit = Arc::new(audit::Audit::new(&config.audit_log));
+    let status = Arc::new(report::Status::new(&config.status_file));
+    let alerts = config
+        .operator
+        .as_ref()
+        .map(|o| notify::webhook(&o.webhook, &config));
+    // the operator only gets the per cycle reports if it asked for them
+    let reports: Vec<_> = alerts
+        .clone()
+        .filter(|_| config.operator.as_ref().is_some_and(|o| o.verbose))
+        .into_iter()
+        .collect();
+
+    let store = match &config.store {
+        Some(path) => Some(
+            store::Store::open(path, config.store_backend)
+                .await
+                .with_context(|| format!("couldn't open {}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    #[cfg(feature = "postgres")]
+    let shared = match &config.postgres {
+        Some(url) => Some(Arc::new(
+            shared::Shared::connect(url)
+                .await
+                .context("couldn't connect to postgres")?,
+        )),
+        None => None,
+    };
+    #[cfg(not(feature = "postgres"))]
+    if config.postgres.is_some() {
+        anyhow::bail!(
+            "postgres is set in the config, but hdget was built without the postgres feature"
+        );
+    }
+
+    // digests get emailed on top of going to their board
+    #[cfg(feature = "email")]
+    let email: Option<Arc<dyn notify::Notifier>> = match &config.email {
+        Some(email) => Some(Arc::new(
+            notify::Email::new(email).context("couldn't set up email")?,
+        )),
+        None => None,
+    };
+    #[cfg(not(feature = "email"))]
+    let email: Option<Arc<dyn notify::Notifier>> = match &config.email {
+        Some(_) => anyhow::bail!(
+            "email is set in the config, but hdget was built without the email feature"
+        ),
+        None => None,
+    };
+
+    // flips to true when it's time to shut down
+    let (shutdown_tx, shutdown) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        let _ = shutdown_tx.send(true);
+    });
+
+    // the bot answers from what the pollers last saw
+    #[cfg(not(feature = "bot"))]
+    if config.bot.is_some() {
+        anyhow::bail!("bot is set in the config, but hdget was built without the bot feature");
+    }
+    let live = config.bot.as_ref().map(|_| Arc::new(live::Live::default()));
+    #[cfg(feature = "bot")]
+    if let (Some(bot), Some(live)) = (&config.bot, &live) {
+        let boards = config.boards.iter().map(|b| b.name.clone()).collect();
+        let bot = bot::Bot::new(bot, boards, live.clone(), store.clone());
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(e) = bot.run(shutdown).await {
+                warn!(error = %e, "the bot stopped");
+            }
+        });
+    }
+
+    // every board gets its own job and its own Leaderboard state
+    let mut scheduler = schedule::Scheduler::new(status.clone());
+    for board in config.boards.clone() {
+        if let Some(digest) = &board.digest {
+            let store = store
+                .clone()
+                .with_context(|| format!("{} has a digest, which needs the store", board.name))?;
+            let webhook = notify::webhook(&board.webhook, &config);
+            let notifiers = [webhook].into_iter().chain(email.clone()).collect();
+            scheduler.add(DigestJob {
+                board: board.name.clone(),
+                period: digest.every,
+                store,
+                annotations: annotate::Annotations::new(&config.annotations),
+                notifiers,
+            });
+        }
+
+        let poller = poll::Poller::new(
+            board,
+            config.clone(),
+            sent.clone(),
+            audit.clone(),
+            alerts.clone(),
+            store.clone(),
+            #[cfg(feature = "postgres")]
+            shared.clone(),
+        )
+        .await?;
+        let poller = match &live {
+            Some(live) => poller.publish_to(live.clone()),
+            None => poller,
+        };
+        scheduler.add(BoardJob {
+            poller,
+            status: status.clone(),
+            notifiers: reports.clone(),
+        });
+    }
+
+    scheduler.run(shutdown).await?;
+
+    Ok(())
+}
+
+/// polls a single board
+struct BoardJob {
+    poller: poll::Poller,
+    status: Arc<report::Status>,
+    /// where the per cycle reports go
+    notifiers: Vec<Arc<dyn notify::Notifier>>,
+}
+
+impl schedule::Job for BoardJob {
+    fn name(&self) -> String {
+        format!("poll {}", self.poller.board().name)
+    }
+
+    fn interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.poller.board().interval)
+    }
+
+    fn run(&mut self) -> schedule::BoxFuture<'_, std::io::Result<()>> {
+        Box::pin(async {
+            let report = self.poller.poll().await?;
+
+            report::record_metrics(&report);
+            if let Err(e) = self.status.record(report.clone()).await {
+                warn!(error = %e, "couldn't write the status file");
+            }
+            notify::send_all(&self.notifiers, &report.to_string(), "the report").await;
+
+            Ok(())
+        })
+    }
+
+    // write everything out before stopping
+    fn shutdown(&mut self) -> schedule::BoxFuture<'_, std::io::Result<()>> {
+        Box::pin(self.poller.persist())
+    }
+}
+
+/// posts a summary of a board every so often
+struct DigestJob {
+    board: String,
+    period: digest::Period,
+    store: store::Store,
+    annotations: annotate::Annotations,
+    notifiers: Vec<Arc<dyn notify::Notifier>>,
+}
+
+impl schedule::Job for DigestJob {
+    fn name(&self) -> String {
+        format!("digest {}", self.board)
+    }
+
+    fn interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.period.secs())
+    }
+
+    // right as a day or week is over, so each one gets one digest
+    fn first_run(&self) -> std::time::Duration {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        std::time::Duration::from_secs(self.period.until_next(now))
+    }
+
+    // a missed digest isn't worth stopping the bot for
+    fn run(&mut self) -> schedule::BoxFuture<'_, std::io::Result<()>> {
+        Box::pin(async {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            // it's due right as the period ends, but the timer can
+            // be a little ahead of the clock
+            let (from, to) = self.period.window(now + 60);
+            let events = match self.store.events_between(from, to, Some(&self.board)).await {
+                Ok(events) => events,
+                Err(e) => {
+                    warn!(
+                        board = %self.board,
+                        error = %e,
+                        "couldn't read the store for the digest",
+                    );
+                    return Ok(());
+                }
+            };
+
+            // the digest is still worth sending without them
+            let annotations = self.annotations.between(from, to).await;
+            let annotations = annotations
+                .inspect_err(|e| warn!(error = %e, "couldn't read the annotations"))
+                .unwrap_or_default();
+
+            let Some(message) = digest::digest(&self.board, self.period, &events, &annotations)
+            else {
+                return Ok(());
+            };
+            let what = format!("the digest for {}", self.board);
+            notify::send_all(&self.notifiers, &message, &what).await;
+
+            Ok(())
+        })
+    }
+}
+
+/// wait for ctrl-c, or a SIGTERM on unix
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut term = signal(SignalKind::terminate()).expect("couldn't listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = term.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// print the latest report of every board, and how the jobs are doing
+async fn status(config: config::Config) -> anyhow::Result<()> {
+    let status = report::Status::read(&config.status_file)
+        .await
+        .with_context(|| format!("couldn't read {}", config.status_file.display()))?;
+
+    for report in status.boards.values() {
+        println!("{}", report);
+    }
+    for job in status.jobs.values() {
+        println!("{}", job);
+    }
+
+    Ok(())
+}
+
+/// get the current leaderboard of a board, or explain why not
+async fn fetch(board: &config::BoardConfig) -> anyhow::Result<lb::Leaderboard> {
+    match board.source.fetch().await? {
+        source::FetchOutcome::Board(lb) => Ok(lb),
+        source::FetchOutcome::Maintenance => {
+            anyhow::bail!("{} is down for maintenance", board.name)
+        }
+        source::FetchOutcome::Unparseable { .. } => {
+            anyhow::bail!("couldn't parse the leaderboard of {}", board.name)
+        }
+    }
+}
+
+/// save or list named baselines
+async fn baseline(config: config::Config, command: BaselineCommand) -> anyhow::Result<()> {
+    net::set_rate(config.requests_per_second);
+    let baselines = baseline::Baselines::new(&config.baselines_dir);
+
+    match command {
+        BaselineCommand::Save { name, board } => {
+            for board in config.select(board.as_deref())? {
+                let lb = fetch(board).await?;
+                baselines.save(&board.name, &name, &lb).await?;
+                println!("saved {} for {}", name, board.name);
+            }
+        }
+        BaselineCommand::List { board } => {
+            for board in config.select(board.as_deref())? {
+                for name in baselines.list(&board.name).await? {
+                    println!("{}: {}", board.name, name);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// what `hdget diff` compares against
+enum Against {
+    /// a named baseline
+    Baseline(String),
+    /// the archived board from `since`, compared with the one
+    /// from `until`, or the live board
+    Archive { since: u64, until: Option<u64> },
+}
+
+/// print every pb between a baseline, or an archived board, and now
+/// or another archived board
+async fn diff(
+    config: config::Config,
+    against: &Against,
+    board: Option<&str>,
+) -> anyhow::Result<()> {
+    net::set_rate(config.requests_per_second);
+    let baselines = baseline::Baselines::new(&config.baselines_dir);
+    let archive = config
+        .archive
+        .as_ref()
+        .map(|dir| archive::Archive::new(dir, config.compress_cache));
+
+    let date = |lb: &lb::Leaderboard| {
+        chrono::DateTime::from_timestamp(lb.timestamp().as_secs() as i64, 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    };
+
+    for board in config.select(board)? {
+        let (old, new, since) = match against {
+            Against::Baseline(name) => {
+                let old = baselines
+                    .load(&board.name, name)
+                    .await
+                    .with_context(|| format!("couldn't load {} for {}", name, board.name))?;
+                (old, fetch(board).await?, name.clone())
+            }
+            Against::Archive { since, until } => {
+                let archive = archive
+                    .as_ref()
+                    .context("there's no archive set in the config")?;
+                let missing = || format!("nothing archived for {} that far back", board.name);
+                match until {
+                    Some(until) => {
+                        let (old, new) = archive
+                            .diff_range(&board.name, *since, *until)
+                            .await?
+                            .with_context(missing)?;
+                        let since = format!("{} until {}", date(&old), date(&new));
+                        (old, new, since)
+                    }
+                    None => {
+                        let old = archive
+                            .at(&board.name, *since)
+                            .await?
+                            .with_context(missing)?;
+                        let since = date(&old);
+                        (old, fetch(board).await?, since)
+                    }
+                }
+            }
+        };
+        let pbs = old.events_with(&new, &board.diff);
+
+        println!("# {}: {} pbs since {}\n", board.name, pbs.len(), since);
+        let ctx = render::RenderCtx::new(&board.name);
+        for pb in &pbs {
+            println!("{}", render::Text.render(pb, &ctx).into_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// save every archived copy of the leaderboard as a baseline, and
+/// in the archive and the store if there are ones, so history from
+/// before hdget shows up like the rest
+async fn backfill(config: config::Config, board: Option<&str>) -> anyhow::Result<()> {
+    net::set_rate(config.requests_per_second);
+    let baselines = baseline::Baselines::new(&config.baselines_dir);
+    let mut archive = config
+        .archive
+        .as_ref()
+        .map(|dir| archive::Archive::new(dir, config.compress_cache));
+    let store = match &config.store {
+        Some(path) => Some(
+            store::Store::open(path, config.store_backend)
+                .await
+                .with_context(|| format!("couldn't open {}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    for board in config.select(board)? {
+        let source::Source::Site(site) = &board.source else {
+            println!(
+                "{}: only boards scraped off the site can be backfilled",
+                board.name
+            );
+            continue;
+        };
+        let Some(url) = site.urls.first() else {
+            continue;
+        };
+
+        let existing = baselines.list(&board.name).await?;
+        let captures = source::wayback::captures(url).await?;
+        println!("{}: {} captures of {}", board.name, captures.len(), url);
+
+        // the capture before, to find what happened in between
+        let mut prev = None;
+        for capture in captures {
+            let name = format!("wayback-{}", capture.timestamp);
+            if existing.contains(&name) {
+                prev = baselines.load(&board.name, &name).await.ok();
+                continue;
+            }
+
+            match capture.fetch().await {
+                Ok(source::FetchOutcome::Board(lb)) => {
+                    baselines.save(&board.name, &name, &lb).await?;
+                    if let Some(archive) = &mut archive {
+                        archive.save(&board.name, &lb).await?;
+                    }
+                    if let Some(store) = &store {
+                        let events: Vec<_> = match &prev {
+                            Some(prev) => prev
+                                .events_with(&lb, &board.diff)
+                                .iter()
+                                .map(|pb| store::Emitted::new(&board.name, lb.timestamp(), pb))
+                                .collect(),
+                            None => Vec::new(),
+                        };
+                        store.save(&board.name, &lb, &events).await?;
+                    }
+                    println!("{}: saved {}", board.name, name);
+                    prev = Some(lb);
+                }
+                // the site looked different back then
+                Ok(_) => println!("{}: couldn't parse {}", board.name, name),
+                Err(e) => println!("{}: couldn't fetch {}: {}", board.name, name, e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// add an annotation
+async fn annotate(config: config::Config, text: String, at: Option<&str>) -> anyhow::Result<()> {
+    let at = match at {
+        Some(at) => annotate::parse_date(at).with_context(|| format!("{} isn't a date", at))?,
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs(),
+    };
+
+    annotate::Annotations::new(&config.annotations)
+        .add(&annotate::Annotation { text, at })
+        .await?;
+
+    Ok(())
+}
+
+/// print every annotation
+async fn annotations(config: config::Config) -> anyhow::Result<()> {
+    for a in annotate::Annotations::new(&config.annotations)
+        .all()
+        .await?
+    {
+        let at = chrono::DateTime::from_timestamp(a.at as i64, 0).unwrap_or_default();
+        println!("{}  {}", at.format("%Y-%m-%d %H:%M"), a.text);
+    }
+
+    Ok(())
+}
+
+/// walk through the audit log for a user
+async fn explain(
+    config: config::Config,
+    user_id: u32,
+    cycle: Option<u64>,
+    board: Option<&str>,
+) -> anyhow::Result<()> {
+    use audit::{Decision, Record};
+
+    let records = audit::Audit::new(&config.audit_log).read_all().await?;
+    let wanted =
+        |r: &Record| cycle.is_none_or(|c| r.cycle() == c) && board.is_none_or(|b| r.board() == b);
+
+    let mut found = false;
+    for record in records.iter().filter(|r| wanted(r)) {
+        let Record::Event {
+            board,
+            cycle,
+            user_id: id,
+            name,
+            old,
+            new,
+            kind,
+            filter,
+            targets,
+            decision,
+        } = record
+        else {
+            continue;
+        };
+        if *id != user_id {
+            continue;
+        }
+        found = true;
+
+        println!("cycle {} on {}: {} ({})", cycle, board, name, id);
+        match old {
+            Some(old) => println!("  diff: {} -> {}", old, new),
+            None => println!("  diff: wasn't on the board before, now {}", new),
+        }
+        println!("  counted as: {:?}", kind);
+        println!(
+            "  filter: {}",
+            if *filter {
+                "let it through"
+            } else {
+                "dropped it"
+            }
+        );
+        if !targets.is_empty() {
+            println!("  targets: {}", targets.join(", "));
+        }
+        match decision {
+            Decision::Filtered => println!("  not sent, filtered out"),
+            Decision::Suppressed => println!("  not sent, the same run was sent recently"),
+            Decision::Rule => println!("  not sent, a rule suppressed it"),
+            Decision::Digest => println!("  not sent on its own, only in the digest"),
+            Decision::CaughtUp => {
+                println!("  not sent on its own, the baseline was stale so it went in a summary")
+            }
+            Decision::Held => println!("  held in the outbox until quiet hours were over"),
+            Decision::Sent {
+                event_id,
+                message_id,
+            } => println!("  sent as {} (message {})", event_id, message_id),
+            Decision::Failed { error } => println!("  sending failed: {}", error),
+        }
+    }
+
+    if !found {
+        println!(
+            "the diff didn't find anything for user {}, so as far as the bot \
+             could tell their run didn't change",
+            user_id
+        );
+        // the cycle might not have gotten as far as diffing
+        for record in records.iter().filter(|r| wanted(r)) {
+            if let Record::Cycle(report) = record {
+                if cycle.is_some() {
+                    println!("{}", report);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// send made up events through the boards like real ones
+async fn notify_test(config: config::Config, board: Option<&str>) -> anyhow::Result<()> {
+    // none of it should stick around, or end up anywhere but
+    // the webhooks
+    let dir = std::env::temp_dir().join(format!("hdget-notify-test-{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await?;
+    let res = rehearse_all(config, board, &dir).await;
+    // whether or not it worked
+    let removed = tokio::fs::remove_dir_all(&dir).await;
+    if res? {
+        anyhow::bail!("some of them didn't go out, see above");
+    }
+    Ok(removed?)
+}
+
+/// everything `notify_test` does with the temp dir, returns if
+/// anything failed to send
+async fn rehearse_all(
+    mut config: config::Config,
+    board: Option<&str>,
+    dir: &std::path::Path,
+) -> anyhow::Result<bool> {
+    config.memory_only = true;
+    config.sent_log = dir.join("sent.jsonl");
+    config.audit_log = dir.join("audit.jsonl");
+    config.event_sink = None;
+    config.archive = None;
+    config.s3 = None;
+
+    let boards: Vec<_> = config
+        .boards
+        .iter()
+        .filter(|b| board.is_none_or(|name| b.name == name))
+        .cloned()
+        .collect();
+    if boards.is_empty() {
+        anyhow::bail!("there's no board called {}", board.unwrap_or_default());
+    }
+    let config = Arc::new(config);
+    let sent = Arc::new(sent::SentLog::new(&config.sent_log));
+    let audit = Arc::new(audit::Audit::new(&config.audit_log));
+
+    let mut failed = false;
+    for mut board in boards {
+        let name = board.name.clone();
+        // these would hold onto the test, or change real things
+        board.digest = None;
+        board.quiet_hours = None;
+        board.pinned_record = false;
+        board.replays = None;
+        board.expected_size = None;
+
+        let mut poller =
+            poll::Poller::for_rehearsal(board, config.clone(), sent.clone(), audit.clone())
+                .await
+                .with_context(|| format!("couldn't set up {}", name))?;
+        let report = poller.rehearse().await?;
+        println!(
+            "{}: {} of {} sent",
+            name, report.sends_succeeded, report.sends_attempted
+        );
+        failed |= report.sends_succeeded < report.sends_attempted;
+    }
+
+    Ok(failed)
+}
+
+/// fix up an already sent message
+async fn correct(
+    config: config::Config,
+    event_id: &str,
+    delete: bool,
+    content: Option<String>,
+) -> anyhow::Result<()> {
+    let sent = sent::SentLog::new(&config.sent_log)
+        .find(event_id)
+        .await?
+        .with_context(|| format!("no message was sent for event {}", event_id))?;
+    let board = config
+        .board(&sent.board)
+        .with_context(|| format!("board {} isn't in the config anymore", sent.board))?;
+    let webhook = board.webhook(sent.route.as_deref());
+    if !notify::is_discord(webhook) {
+        anyhow::bail!("only messages sent to discord can be corrected");
+    }
+    let mut hook = hook::Hook::new(webhook);
+    if let (None, Some(thread)) = (&sent.route, board.thread) {
+        hook = hook.in_thread(thread, &board.name);
+    }
+
+    if delete {
+        hook.delete(sent.message_id).await?;
+        println!("deleted message for {}", event_id);
+    } else if let Some(content) = content {
+        hook.edit(sent.message_id, &content).await?;
+        println!("edited message for {}", event_id);
+    }
+
+    Ok(())
+}
+
+/// print every event a player had
+async fn history(config: config::Config, user: &str, board: Option<&str>) -> anyhow::Result<()> {
+    let path = config
+        .store
+        .as_ref()
+        .context("there's no store set in the config")?;
+    let store = store::Store::open(path, config.store_backend)
+        .await
+        .with_context(|| format!("couldn't open {}", path.display()))?;
+
+    let history = history::History::of(&store, user, board).await?;
+    if history.is_empty() {
+        println!("nothing in the store for {}", user);
+        return Ok(());
+    }
 
-    // Get cache on startup
-    let mut old = match lb::Leaderboard::from_cache().await {
-        // we got the cache smoothly
-        Ok(old) => old,
-        // we couldn't read the cache for some reason :(
-        Err(e) => {
-            println!("error reading cache: {}", e);
-            let new = lb::Leaderboard::from_site()
-                .await?
-                .expect("something went wrong while fetching an intial leaderboard");
-            new.cache().await?;
-            new
+    for e in history.events() {
+        let at = chrono::DateTime::from_timestamp(e.timestamp as i64, 0).unwrap_or_default();
+        print!(
+            "{}  [{}] {} {:?}: #{} with {}",
+            at.format("%Y-%m-%d %H:%M"),
+            e.board,
+            e.name,
+            e.kind,
+            e.rank,
+            e.score
+        );
+        match (e.old_rank, e.old_score) {
+            (Some(rank), Some(score)) => println!(" (was #{} with {})", rank, score),
+            _ => println!(),
         }
+    }
+
+    println!();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let this_month = history::month_start(now);
+    for board in history.boards() {
+        let scores = history.progression(board);
+        let (first, last) = (scores[0].1, scores[scores.len() - 1].1);
+        println!(
+            "[{}] {} events, {} this month, {} -> {}",
+            board,
+            scores.len(),
+            history.count_since(board, this_month),
+            first,
+            last
+        );
+    }
+
+    Ok(())
+}
+
+/// write, and maybe post, the year in review
+async fn wrapped(
+    config: config::Config,
+    year: i32,
+    board: Option<&str>,
+    out: &std::path::Path,
+    post: Option<&str>,
+) -> anyhow::Result<()> {
+    let path = config
+        .store
+        .as_ref()
+        .context("there's no store set in the config")?;
+    let store = store::Store::open(path, config.store_backend)
+        .await
+        .with_context(|| format!("couldn't open {}", path.display()))?;
+    // look the board up before doing all the work
+    let post = post
+        .map(|name| {
+            config
+                .board(name)
+                .with_context(|| format!("no board named {}", name))
+        })
+        .transpose()?;
+
+    let (from, to) = wrapped::year_bounds(year).context("that year is out of range")?;
+    let events = store.events_between(from, to, board).await?;
+    let annotations = annotate::Annotations::new(&config.annotations)
+        .between(from, to)
+        .await?;
+    let wrapped = wrapped::Wrapped::new(year, events, annotations);
+
+    tokio::fs::create_dir_all(out).await?;
+    let md = out.join(format!("wrapped-{}.md", year));
+    tokio::fs::write(&md, wrapped.markdown()).await?;
+    let chart = out.join(format!("wrapped-{}.svg", year));
+    tokio::fs::write(&chart, wrapped.chart()).await?;
+    println!("wrote {} and {}", md.display(), chart.display());
+
+    if let Some(board) = post {
+        hook::Hook::new(&board.webhook)
+            .send_thread(&format!("hdget wrapped {}", year), &wrapped.sections())
+            .await
+            .context("couldn't post the thread")?;
+        println!("posted to {}", board.name);
+    }
+
+    Ok(())
+}
+
+/// write the cache, or a range of the archive, out for other tools
+async fn export(
+    config: config::Config,
+    format: export::Format,
+    board: Option<&str>,
+    range: Option<(u64, Option<u64>)>,
+    out: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let archive = config
+        .archive
+        .as_ref()
+        .map(|dir| archive::Archive::new(dir, config.compress_cache));
+
+    let w: Box<dyn std::io::Write + Send> = match &out {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .with_context(|| format!("couldn't create {}", path.display()))?,
+        )),
+        None => Box::new(std::io::BufWriter::new(std::io::stdout())),
     };
+    let mut exporter = export::Exporter::new(format, w)?;
 
-    loop {
-        // wait 10 mins
-        tokio::time::sleep(std::time::Duration::from_secs(600)).await;
+    // the archive can be huge, so boards get written one at a time
+    for board in config.select(board)? {
+        match range {
+            None => {
+                let path = board.cache_path();
+                let lb = lb::Leaderboard::from_cache(&path)
+                    .await
+                    .with_context(|| format!("couldn't read {}", path.display()))?;
+                exporter.write(&board.name, &lb)?;
+            }
+            Some((from, to)) => {
+                let archive = archive
+                    .as_ref()
+                    .context("there's no archive set in the config")?;
+                for t in archive.list(&board.name).await? {
+                    if t >= from && to.is_none_or(|to| t < to) {
+                        exporter.write(&board.name, &archive.load(&board.name, t).await?)?;
+                    }
+                }
+            }
+        }
+    }
+    exporter.finish()?;
+
+    Ok(())
+}
+
+/// enforce the retention policy on the archive
+async fn prune(config: config::Config, dry_run: bool, board: Option<&str>) -> anyhow::Result<()> {
+    let dir = config
+        .archive
+        .as_ref()
+        .context("there's no archive set in the config")?;
+    let archive = archive::Archive::new(dir, config.compress_cache);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+
+    for board in config.select(board)? {
+        let timestamps = archive.list(&board.name).await?;
+        let expired = config.retention.expired(&timestamps, now);
+
+        for t in &expired {
+            if !dry_run {
+                archive.remove(&board.name, *t).await?;
+            }
+        }
+        println!(
+            "{}: {} {} of {} snapshots",
+            board.name,
+            if dry_run { "would delete" } else { "deleted" },
+            expired.len(),
+            timestamps.len()
+        );
+    }
 
-        // create a new Leaderboard object by scraping the site
-        // if this fails, 
-        let Some(new) = lb::Leaderboard::from_site().await? else { continue };
+    Ok(())
+}
 
-        // get all pbs (difference of old to new)
-        let pbs = old.pbs(&new);
+/// bring every cache and baseline up to the current format
+async fn migrate(config: config::Config) -> anyhow::Result<()> {
+    let baselines = baseline::Baselines::new(&config.baselines_dir);
+    let report = |what: &str, format| match format {
+        lb::CacheFormat::Current | lb::CacheFormat::Json => {}
+        format => println!("migrated {} from {:?}", what, format),
+    };
 
-        if pbs.is_empty() {
-            println!("nothing to do");
-        } else {
-            // send each pb to the webhook            
-            for pb in &pbs {
-                hook.send(&pb.to_string()).await?;
+    for board in &config.boards {
+        let cache = board.cache_path();
+        match lb::Leaderboard::migrate(&cache).await {
+            Ok(format) => report(&cache.display().to_string(), format),
+            // nothing to migrate yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("couldn't migrate {}", cache.display()))
             }
+        }
 
-            // cache the new leaderboard
-            new.cache().await?;
-            old = new;
+        for name in baselines.list(&board.name).await? {
+            let format = baselines
+                .migrate(&board.name, &name)
+                .await
+                .with_context(|| format!("couldn't migrate baseline {} of {}", name, board.name))?;
+            report(&format!("baseline {} of {}", name, board.name), format);
         }
     }
+
+    Ok(())
+}
+
+fn print_contents(what: &str, contents: &backup::Contents) {
+    println!(
+        "{} {} caches, {} archived snapshots{}",
+        what,
+        contents.caches,
+        contents.snapshots,
+        if contents.store { " and the store" } else { "" }
+    );
+}
+
+/// build release artifacts
+async fn own(command: SelfCommand) -> anyhow::Result<()> {
+    match command {
+        SelfCommand::Package {
+            targets,
+            out,
+            cross,
+            image,
+        } => {
+            let package = package::Package::new(&out).targets(targets).cross(cross);
+            for bin in package.build().await? {
+                println!("built {}", bin.display());
+            }
+            if let Some(tag) = image {
+                package.image(&tag).await?;
+                println!("built image {}", tag);
+            }
+        }
+    }
+
+    Ok(())
 }
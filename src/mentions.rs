@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::lb::{Event, Severity};
+
+/// discord users to ping about their own pbs
+///
+/// ```toml
+/// [mentions]
+/// users = [[1234, 123456789012345678]]
+/// opt_out = [5678]
+/// records_only = true
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mentions {
+    /// pairs of leaderboard user ids and discord user ids
+    pub users: Vec<(u32, u64)>,
+    /// players who'd rather not get pinged, even if they're in `users`
+    pub opt_out: HashSet<u32>,
+    /// only ping for world records and milestones
+    pub records_only: bool,
+}
+
+impl Mentions {
+    /// the ping for whoever got the pb, if they get one
+    pub fn mention(&self, pb: &Event) -> Option<String> {
+        self.user(pb).map(|discord| format!("<@{}>", discord))
+    }
+
+    /// the discord user that gets pinged about the pb, if anyone
+    pub fn user(&self, pb: &Event) -> Option<u64> {
+        // dropping a rank or changing names isn't worth a ping
+        if !pb.kind().is_pb() {
+            return None;
+        }
+        if self.records_only && pb.severity() < Severity::Milestone {
+            return None;
+        }
+
+        let user_id = pb.entry().user_id;
+        if self.opt_out.contains(&user_id) {
+            return None;
+        }
+        let (_, discord) = self.users.iter().find(|(id, _)| *id == user_id)?;
+        Some(*discord)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+
+    #[test]
+    fn test_mentions() {
+        let mut mentions: Mentions = toml::from_str(
+            r#"
+            users = [[1, 123456789012345678], [2, 876543210987654321]]
+            opt_out = [2]
+            "#,
+        )
+        .unwrap();
+        let entry = |user_id, rank| Entry {
+            rank,
+            name: "possm".to_string(),
+            user_id,
+            run_id: rank as u32,
+            score: 400.0 - rank as f32,
+        };
+
+        let (old, new) = (entry(1, 20), entry(1, 12));
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+        assert_eq!(
+            mentions.mention(&pb).as_deref(),
+            Some("<@123456789012345678>")
+        );
+        let moved = Event::RankChange {
+            old: &old,
+            new: &new,
+        };
+        assert_eq!(mentions.mention(&moved), None);
+
+        let (old, new) = (entry(2, 20), entry(2, 12));
+        let opted_out = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+        assert_eq!(mentions.mention(&opted_out), None);
+
+        mentions.records_only = true;
+        assert_eq!(mentions.mention(&pb), None);
+        let (old, new) = (entry(1, 2), entry(1, 1));
+        let wr = Event::WorldRecord {
+            old: &old,
+            new: &new,
+        };
+        assert!(mentions.mention(&wr).is_some());
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// a counter that only ever goes up
+pub struct Counter {
+    name: &'static str,
+    value: AtomicU64,
+}
+
+impl Counter {
+    const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// pbs that weren't announced because they were already sent recently
+pub static DEDUP_SUPPRESSED: Counter = Counter::new("hdget_dedup_suppressed_total");
+
+/// polls where the site said it was down
+pub static MAINTENANCE_POLLS: Counter = Counter::new("hdget_maintenance_polls_total");
+
+/// polls where the page couldn't be parsed
+pub static PARSE_FAILURES: Counter = Counter::new("hdget_parse_failures_total");
+
+/// leaderboards thrown away for failing validation
+pub static REJECTED: Counter = Counter::new("hdget_rejected_total");
+
+/// polls of any board
+pub static CYCLES: Counter = Counter::new("hdget_cycles_total");
+
+/// events found by diffing
+pub static EVENTS: Counter = Counter::new("hdget_events_total");
+
+/// messages we tried to send
+pub static SENDS_ATTEMPTED: Counter = Counter::new("hdget_sends_attempted_total");
+
+/// messages that were actually sent
+pub static SENDS_SUCCEEDED: Counter = Counter::new("hdget_sends_succeeded_total");
@@ -0,0 +1,91 @@
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use reqwest::{IntoUrl, RequestBuilder, Response};
+use tokio::time::Instant;
+
+/// a token bucket
+///
+/// tokens refill at `rate` per second, up to `burst`.
+/// every request takes one, and waits if there aren't any.
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    /// allow `rate` requests per second on average
+    pub fn new(rate: f64) -> Self {
+        let burst = rate.max(1.0);
+
+        Self {
+            rate,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last: Instant::now(),
+            }),
+        }
+    }
+
+    /// wait until a request is allowed
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let refill = now.duration_since(bucket.last).as_secs_f64() * self.rate;
+                bucket.tokens = (bucket.tokens + refill).min(self.burst);
+                bucket.last = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate)
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+static LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// limit every request made through this module to `rate` per second
+///
+/// only the first call does anything, so call this once at startup.
+/// if it's never called, or `rate` isn't positive, requests aren't
+/// limited at all.
+pub fn set_rate(rate: f64) {
+    if rate > 0.0 {
+        let _ = LIMITER.set(RateLimiter::new(rate));
+    }
+}
+
+/// the http client shared by every source
+pub fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+/// send a request once the rate limiter allows it
+pub async fn send(req: RequestBuilder) -> reqwest::Result<Response> {
+    if let Some(limiter) = LIMITER.get() {
+        limiter.acquire().await;
+    }
+
+    req.send().await
+}
+
+/// GET a url, respecting the rate limit
+pub async fn get(url: impl IntoUrl) -> reqwest::Result<Response> {
+    send(client().get(url)).await
+}
@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::io;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::hook::{Hook, Pings};
+use crate::lb::Event;
+use crate::render::{DiscordEmbed, Format, RenderCtx, RenderedMessage};
+use crate::schedule::BoxFuture;
+
+#[cfg(feature = "bot")]
+pub mod bot;
+#[cfg(feature = "desktop")]
+pub mod desktop;
+#[cfg(feature = "email")]
+pub mod email;
+pub mod http;
+pub mod mastodon;
+pub mod ntfy;
+pub mod slack;
+pub mod telegram;
+
+#[cfg(feature = "bot")]
+pub use bot::BotChannel;
+#[cfg(feature = "desktop")]
+pub use desktop::Desktop;
+#[cfg(feature = "email")]
+pub use email::Email;
+pub use http::Http;
+pub use mastodon::Mastodon;
+pub use ntfy::Ntfy;
+pub use slack::Slack;
+pub use telegram::Telegram;
+
+/// somewhere messages get posted
+///
+/// discord and slack webhooks, discord channels through the bot,
+/// telegram chats, a mastodon account, ntfy topics, plain json
+/// endpoints and desktop notifications so far. other chat apps get
+/// their own implementation instead of going in `hook.rs`
+pub trait Notifier: Send + Sync {
+    /// post a message. json is whatever payload the backend takes,
+    /// like a discord embed
+    ///
+    /// returns the id of the message that was created
+    fn send<'a>(&'a self, message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>>;
+
+    /// post some plain text
+    fn send_text<'a>(&'a self, text: &'a str) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move { self.send(&RenderedMessage::Text(text.to_string())).await })
+    }
+
+    /// post a message that can ping `pings`, and nobody else.
+    /// backends without pings just send it
+    fn send_pinging<'a>(
+        &'a self,
+        message: &'a RenderedMessage,
+        _pings: &'a Pings,
+    ) -> BoxFuture<'a, io::Result<u64>> {
+        self.send(message)
+    }
+
+    /// post a message with a png to go along with it, backends
+    /// that can't do images just leave it off
+    fn send_with_image<'a>(
+        &'a self,
+        message: &'a RenderedMessage,
+        _png: &'a [u8],
+        pings: &'a Pings,
+    ) -> BoxFuture<'a, io::Result<u64>> {
+        self.send_pinging(message, pings)
+    }
+
+    /// the backend's own rich version of an event, for boards with
+    /// `embeds`. `content` goes in front, like pings, and `extra`
+    /// lines at the end. `text` is what the board rendered for it,
+    /// for backends whose payload is mostly that
+    fn payload(
+        &self,
+        event: &Event,
+        ctx: &RenderCtx,
+        content: &str,
+        extra: &str,
+        text: &str,
+    ) -> Value;
+
+    /// if it takes this kind of event at all, when it's a target
+    fn wants(&self, _event: &Event) -> bool {
+        true
+    }
+
+    /// if it always gets the `payload` of events, even on boards
+    /// without `embeds`
+    fn structured(&self) -> bool {
+        false
+    }
+
+    /// how its text messages should be formatted
+    fn format(&self) -> Format {
+        Format::Plain
+    }
+}
+
+impl Notifier for Hook {
+    fn send<'a>(&'a self, message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move { Notifier::send_pinging(self, message, &Pings::default()).await })
+    }
+
+    fn send_pinging<'a>(
+        &'a self,
+        message: &'a RenderedMessage,
+        pings: &'a Pings,
+    ) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            match message {
+                RenderedMessage::Text(text) => Hook::send_pinging(self, text, pings).await,
+                RenderedMessage::Json(payload) => {
+                    let mut payload = payload.clone();
+                    payload["allowed_mentions"] = pings.allowed_mentions();
+                    self.send_embed(&payload).await
+                }
+                RenderedMessage::Html(_) | RenderedMessage::Svg(_) => Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "discord only takes text and embeds",
+                )),
+            }
+        })
+    }
+
+    #[cfg(feature = "graphs")]
+    fn send_with_image<'a>(
+        &'a self,
+        message: &'a RenderedMessage,
+        png: &'a [u8],
+        pings: &'a Pings,
+    ) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            match message {
+                RenderedMessage::Json(payload) => {
+                    let mut payload = payload.clone();
+                    payload["allowed_mentions"] = pings.allowed_mentions();
+                    self.send_embed_with_image(&payload, png).await
+                }
+                message => Notifier::send_pinging(self, message, pings).await,
+            }
+        })
+    }
+
+    fn payload(
+        &self,
+        event: &Event,
+        ctx: &RenderCtx,
+        content: &str,
+        extra: &str,
+        _text: &str,
+    ) -> Value {
+        DiscordEmbed::payload(event, ctx, content, extra)
+    }
+
+    fn format(&self) -> Format {
+        Format::Markdown
+    }
+}
+
+/// whatever posts to a webhook from the config, going by its url
+pub fn webhook(url: &str, config: &Config) -> Arc<dyn Notifier> {
+    // `Config::load` makes sure these are set up
+    if let (Some(chat), Some(bot)) = (telegram::chat(url), &config.telegram) {
+        return Arc::new(Telegram::new(bot, chat));
+    }
+    if let (true, Some(account)) = (mastodon::is_account(url), &config.mastodon) {
+        return Arc::new(Mastodon::new(account));
+    }
+    #[cfg(feature = "bot")]
+    if let (Some(channel), Some(bot)) = (bot_channel(url), &config.bot) {
+        return Arc::new(BotChannel::new(bot, channel));
+    }
+    #[cfg(feature = "desktop")]
+    if is_desktop(url) {
+        return Arc::new(Desktop);
+    }
+    if let Some(topic) = ntfy::topic(url) {
+        return Arc::new(Ntfy::new(&config.ntfy, topic));
+    }
+    if let Some(name) = http::endpoint(url) {
+        if let Some(endpoint) = config.http.iter().find(|h| h.name == name) {
+            return Arc::new(Http::new(endpoint));
+        }
+    }
+    match slack::is_webhook(url) {
+        true => Arc::new(Slack::new(url)),
+        false => Arc::new(Hook::new(url)),
+    }
+}
+
+/// if a webhook from the config is a discord one, which is
+/// anything that isn't one of the others. channels the bot posts
+/// in aren't, since they can't do threads or edit pins
+pub fn is_discord(url: &str) -> bool {
+    !slack::is_webhook(url)
+        && bot_channel(url).is_none()
+        && telegram::chat(url).is_none()
+        && !mastodon::is_account(url)
+        && ntfy::topic(url).is_none()
+        && !is_desktop(url)
+        && http::endpoint(url).is_none()
+}
+
+/// if a webhook from the config means the os's own notifications,
+/// see `desktop::Desktop`. needs the `desktop` feature
+pub fn is_desktop(url: &str) -> bool {
+    url == "desktop"
+}
+
+/// the channel id, if a webhook from the config is one the bot
+/// posts in, see `bot::BotChannel`. needs the `bot` feature
+pub fn bot_channel(url: &str) -> Option<u64> {
+    url.strip_prefix("bot:")?.parse().ok()
+}
+
+/// send the same text to all of them
+///
+/// returns how many it got to, the rest get logged
+pub async fn send_all(notifiers: &[Arc<dyn Notifier>], text: &str, what: &str) -> usize {
+    let mut sent = 0;
+    for notifier in notifiers {
+        match notifier.send_text(text).await {
+            Ok(_) => sent += 1,
+            Err(e) => warn!(error = %e, "couldn't send {}", what),
+        }
+    }
+    sent
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unsupported() {
+        // doesn't get as far as the network
+        let hook = Hook::new("http://localhost:9/webhook");
+        let svg = RenderedMessage::Svg("<svg/>".to_string());
+        let err = Notifier::send(&hook, &svg).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
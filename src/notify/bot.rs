@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use serenity::http::Http;
+use serenity::model::id::ChannelId;
+use tokio::io;
+
+use crate::config::BotConfig;
+use crate::hook::{send_parts, split, Pings};
+use crate::lb::Event;
+use crate::notify::Notifier;
+use crate::render::{DiscordEmbed, Format, RenderCtx, RenderedMessage};
+use crate::schedule::BoxFuture;
+
+/// a discord channel the bot posts in, for servers that would
+/// rather have the bot than a webhook. messages look the same as
+/// through a webhook
+pub struct BotChannel {
+    http: Arc<Http>,
+    channel: ChannelId,
+}
+
+impl BotChannel {
+    pub fn new(config: &BotConfig, channel: u64) -> Self {
+        Self {
+            http: Arc::new(Http::new(&config.token)),
+            channel: ChannelId::new(channel),
+        }
+    }
+
+    async fn post(&self, mut body: Value, pings: &Pings) -> io::Result<u64> {
+        body["allowed_mentions"] = pings.allowed_mentions();
+        let message = self
+            .http
+            .send_message(self.channel, Vec::new(), &body)
+            .await
+            .map_err(io::Error::other)?;
+        Ok(message.id.get())
+    }
+}
+
+impl Notifier for BotChannel {
+    fn send<'a>(&'a self, message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move { self.send_pinging(message, &Pings::default()).await })
+    }
+
+    fn send_pinging<'a>(
+        &'a self,
+        message: &'a RenderedMessage,
+        pings: &'a Pings,
+    ) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            match message {
+                RenderedMessage::Text(text) => {
+                    send_parts(split(text), |content| {
+                        self.post(json!({ "content": content }), pings)
+                    })
+                    .await
+                }
+                RenderedMessage::Json(payload) => self.post(payload.clone(), pings).await,
+                RenderedMessage::Html(_) | RenderedMessage::Svg(_) => Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "discord only takes text and embeds",
+                )),
+            }
+        })
+    }
+
+    fn payload(
+        &self,
+        event: &Event,
+        ctx: &RenderCtx,
+        content: &str,
+        extra: &str,
+        _text: &str,
+    ) -> Value {
+        DiscordEmbed::payload(event, ctx, content, extra)
+    }
+
+    fn format(&self) -> Format {
+        Format::Markdown
+    }
+}
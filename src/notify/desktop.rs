@@ -0,0 +1,119 @@
+use serde_json::{json, Value};
+use tokio::io;
+
+use crate::lb::Event;
+use crate::notify::Notifier;
+use crate::render::{banner, headline, RenderCtx, RenderedMessage};
+use crate::schedule::BoxFuture;
+
+/// a toast from the os, for someone running hdget on their own
+/// machine to keep an eye on their rivals
+///
+/// it goes in as one of a board's targets, with `desktop` as the
+/// webhook
+///
+/// ```toml
+/// [[board.targets]]
+/// name = "me"
+/// webhook = "desktop"
+/// filter = { watchlist = ["possm"] }
+/// ```
+pub struct Desktop;
+
+impl Notifier for Desktop {
+    fn send<'a>(&'a self, message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            let (summary, body) = match message {
+                RenderedMessage::Text(text) => {
+                    let text = text.trim();
+                    match text.split_once('\n') {
+                        Some((summary, body)) => (summary.to_string(), body.to_string()),
+                        None => ("hdget".to_string(), text.to_string()),
+                    }
+                }
+                RenderedMessage::Json(payload) => (
+                    payload["summary"].as_str().unwrap_or("hdget").to_string(),
+                    payload["body"].as_str().unwrap_or_default().to_string(),
+                ),
+                RenderedMessage::Html(_) | RenderedMessage::Svg(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "desktop notifications only take text",
+                    ))
+                }
+            };
+
+            // talking to the notification daemon blocks
+            tokio::task::spawn_blocking(move || {
+                notify_rust::Notification::new()
+                    .appname("hdget")
+                    .summary(&summary)
+                    .body(&body)
+                    .show()
+                    .map(|_| ())
+            })
+            .await?
+            .map_err(io::Error::other)?;
+
+            // the os doesn't give back anything that can be corrected
+            Ok(0)
+        })
+    }
+
+    fn payload(
+        &self,
+        event: &Event,
+        ctx: &RenderCtx,
+        content: &str,
+        extra: &str,
+        _text: &str,
+    ) -> Value {
+        let new = event.entry();
+        let what = headline(event, str::to_string).unwrap_or_else(|| {
+            let mut s = format!("{} got {}", new.name, new.score);
+            if let Some(delta) = event.delta() {
+                s.push_str(&format!(" (+{})", delta));
+            }
+            s.push_str(&format!(", now #{}", new.rank));
+            s
+        });
+        let summary = match banner(event) {
+            Some(banner) => format!("{}: {}", ctx.board, banner),
+            None => format!("{}: new pb", ctx.board),
+        };
+        json!({
+            "summary": summary,
+            "body": format!("{}{}\n{}", content, what, extra).trim_end(),
+        })
+    }
+
+    fn structured(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+
+    #[test]
+    fn test_payload() {
+        let entry = |rank, run_id, score| Entry {
+            rank,
+            name: "possm".to_string(),
+            user_id: 1,
+            run_id,
+            score,
+        };
+        let (old, new) = (entry(5, 1, 380.0), entry(3, 2, 390.5));
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+
+        let payload = Desktop.payload(&pb, &RenderCtx::new("main"), "", "", "");
+        assert_eq!(payload["summary"], "main: new pb");
+        assert_eq!(payload["body"], "possm got 390.5 (+10.5), now #3");
+    }
+}
@@ -0,0 +1,132 @@
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde_json::{json, Value};
+use tokio::io;
+
+use crate::config::EmailConfig;
+use crate::lb::Event;
+use crate::notify::Notifier;
+use crate::render::{RenderCtx, RenderedMessage};
+use crate::schedule::BoxFuture;
+
+/// sends messages as emails, with the first line as the subject
+pub struct Email {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl Email {
+    /// fails if any of the addresses don't parse
+    pub fn new(config: &EmailConfig) -> io::Result<Self> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+        let address = |s: &str| {
+            s.parse::<Mailbox>()
+                .map_err(|e| invalid(format!("{} isn't an email address: {}", s, e)))
+        };
+
+        let mut mailer = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.server)
+            .map_err(io::Error::other)?
+            .port(config.port);
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            mailer = mailer.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            mailer: mailer.build(),
+            from: address(&config.from)?,
+            to: config
+                .to
+                .iter()
+                .map(|to| address(to))
+                .collect::<io::Result<_>>()?,
+        })
+    }
+
+    async fn mail(&self, subject: &str, body: &str) -> io::Result<()> {
+        let mut message = Message::builder().from(self.from.clone()).subject(subject);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message.body(body.to_string()).map_err(io::Error::other)?;
+
+        self.mailer.send(message).await.map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+/// the first line, without any trailing punctuation
+fn subject(text: &str) -> &str {
+    let line = text.lines().next().unwrap_or_default();
+    // a banner like `---  NEW WORLD RECORD  ---` is just the words
+    line.trim_matches(['-', ' '])
+        .trim_end_matches(['.', ':', ' '])
+}
+
+impl Notifier for Email {
+    fn send<'a>(&'a self, message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            match message {
+                RenderedMessage::Text(text) => self.mail(subject(text), text).await?,
+                RenderedMessage::Json(payload) => {
+                    let subject = payload["subject"].as_str().unwrap_or_default();
+                    let body = payload["body"].as_str().unwrap_or_default();
+                    self.mail(subject, body).await?
+                }
+                RenderedMessage::Html(_) | RenderedMessage::Svg(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "emails only take text",
+                    ))
+                }
+            }
+
+            // there's nothing to correct later
+            Ok(0)
+        })
+    }
+
+    fn payload(
+        &self,
+        _event: &Event,
+        ctx: &RenderCtx,
+        content: &str,
+        _extra: &str,
+        text: &str,
+    ) -> Value {
+        // pings go in the body, but they'd be noise in the subject
+        let unpinged = text.strip_prefix(content).unwrap_or(text).trim_start();
+        json!({
+            "subject": format!("[{}] {}", ctx.board, subject(unpinged)),
+            "body": text,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_subject() {
+        assert_eq!(
+            subject("[main] 3 pbs today, biggest gain: possm +1.5\n1 new players\n"),
+            "[main] 3 pbs today, biggest gain: possm +1.5"
+        );
+        assert_eq!(subject("something happened.\n"), "something happened");
+        assert_eq!(subject("---  世界記録  ---\npossm 410\n"), "世界記録");
+
+        let config: EmailConfig = toml::from_str(
+            r#"
+            server = "smtp.example.com"
+            from = "hdget <hdget@example.com>"
+            to = ["mods@example.com", "not an address"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.port, 587);
+        let err = Email::new(&config).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
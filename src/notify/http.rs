@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::io;
+
+use crate::bucket::hex;
+use crate::lb::{now, Event, Severity};
+use crate::notify::Notifier;
+use crate::render::{RenderCtx, RenderedMessage};
+use crate::schedule::BoxFuture;
+
+/// somewhere that takes events as plain json, for hooking hdget
+/// up to n8n, zapier or whatever else
+///
+/// boards send to it with `http:<name>` as their webhook
+///
+/// ```toml
+/// [[http]]
+/// name = "n8n"
+/// url = "https://n8n.example.com/webhook/hdget"
+/// headers = { Authorization = "env:N8N_AUTH" }
+/// secret = "env:N8N_SECRET"
+///
+/// [[board.targets]]
+/// name = "automations"
+/// webhook = "http:n8n"
+/// ```
+///
+/// every event goes out as json, batches and the like are just
+/// `{ "text": ... }`
+///
+/// with a `secret`, every request has an `X-Hdget-Timestamp` and an
+/// `X-Hdget-Signature` of `sha256=` and the hex hmac-sha256 of the
+/// timestamp, a `.` and the body, so the other end can tell it's
+/// really from hdget
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    pub name: String,
+    pub url: String,
+    /// sent with every request, like for auth. values can come
+    /// from the environment like webhooks
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// shared with the other end to sign requests with, can come
+    /// from the environment too
+    pub secret: Option<String>,
+}
+
+/// posts json to a url
+pub struct Http {
+    client: reqwest::Client,
+    config: HttpConfig,
+}
+
+/// what an event gets posted as
+#[derive(Serialize)]
+struct Body<'a> {
+    board: &'a str,
+    /// unix time the event was found at
+    timestamp: u64,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+    severity: Severity,
+    /// the message that would go to a chat
+    text: String,
+}
+
+impl Http {
+    pub fn new(config: &HttpConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config: config.clone(),
+        }
+    }
+}
+
+/// the endpoint, if a webhook from the config is one of these
+pub fn endpoint(url: &str) -> Option<&str> {
+    url.strip_prefix("http:")
+}
+
+/// the signature of a request, see `HttpConfig`
+fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes any key size");
+    mac.update(format!("{}.", timestamp).as_bytes());
+    mac.update(body);
+    format!("sha256={}", hex(&mac.finalize().into_bytes()))
+}
+
+impl Notifier for Http {
+    fn send<'a>(&'a self, message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            let body = match message {
+                RenderedMessage::Text(text) => &json!({ "text": text }),
+                RenderedMessage::Json(payload) => payload,
+                RenderedMessage::Html(_) | RenderedMessage::Svg(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "http endpoints only take json",
+                    ))
+                }
+            };
+
+            let body = serde_json::to_vec(body)?;
+            let mut req = self
+                .client
+                .post(&self.config.url)
+                .header(CONTENT_TYPE, "application/json");
+            for (name, value) in &self.config.headers {
+                req = req.header(name, value);
+            }
+            if let Some(secret) = &self.config.secret {
+                let timestamp = now().as_secs();
+                req = req
+                    .header("X-Hdget-Timestamp", timestamp)
+                    .header("X-Hdget-Signature", sign(secret, timestamp, &body));
+            }
+            let req = req.body(body);
+            req.send()
+                .await
+                .map_err(io::Error::other)?
+                .error_for_status()
+                .map_err(io::Error::other)?;
+
+            // whatever's on the other end doesn't give us anything to correct
+            Ok(0)
+        })
+    }
+
+    fn payload(
+        &self,
+        event: &Event,
+        ctx: &RenderCtx,
+        _content: &str,
+        _extra: &str,
+        text: &str,
+    ) -> Value {
+        let body = Body {
+            board: &ctx.board,
+            timestamp: ctx.timestamp,
+            event,
+            severity: event.severity(),
+            text: text.to_string(),
+        };
+        serde_json::to_value(body).expect("events always serialize")
+    }
+
+    fn structured(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+    use crate::render::{Render, Text};
+
+    #[test]
+    fn test_payload() {
+        let config: HttpConfig = toml::from_str(
+            r#"
+            name = "n8n"
+            url = "https://n8n.example.com/webhook/hdget"
+            headers = { Authorization = "Bearer hunter2" }
+            "#,
+        )
+        .unwrap();
+        let http = Http::new(&config);
+
+        let (old, new) = (
+            Entry {
+                rank: 3,
+                name: "possm".to_string(),
+                user_id: 1,
+                run_id: 1,
+                score: 390.0,
+            },
+            Entry {
+                rank: 2,
+                name: "possm".to_string(),
+                user_id: 1,
+                run_id: 2,
+                score: 395.5,
+            },
+        );
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+        let ctx = RenderCtx::new("main");
+        let text = Text.render(&pb, &ctx).into_string();
+        let body = http.payload(&pb, &ctx, "", "", &text);
+        assert_eq!(body["board"], "main");
+        assert_eq!(body["kind"], "improvement");
+        assert_eq!(body["severity"], "top_pb");
+        assert_eq!(body["new"]["score"], 395.5);
+        assert!(body["text"].as_str().unwrap().starts_with("possm just got"));
+
+        // the same as `printf '1700000000.{}' | openssl dgst -sha256 -hmac hunter2`
+        assert_eq!(
+            sign("hunter2", 1700000000, b"{}"),
+            "sha256=ff2f975170d2a2ea3d6177dad44d002ac729fec15c2e1053dc096496e07f8401"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io;
+
+use crate::lb::{Event, Severity};
+use crate::notify::Notifier;
+use crate::render::{RenderCtx, RenderedMessage};
+use crate::schedule::BoxFuture;
+
+/// longest status most instances take, in characters
+const MAX_LEN: usize = 500;
+
+/// an account that posts the biggest news as statuses
+///
+/// it goes in as one of a board's targets, with `mastodon` as
+/// the webhook
+///
+/// ```toml
+/// [mastodon]
+/// instance = "https://mastodon.social"
+/// token = "env:MASTODON_TOKEN"
+/// severities = ["world_record"]
+///
+/// [[board.targets]]
+/// name = "fedi"
+/// webhook = "mastodon"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MastodonConfig {
+    pub instance: String,
+    /// access token with `write:statuses`, can come from the
+    /// environment like webhooks
+    pub token: String,
+    /// which events get posted, world records and milestones
+    /// by default
+    #[serde(default = "default_severities")]
+    pub severities: HashSet<Severity>,
+}
+
+fn default_severities() -> HashSet<Severity> {
+    HashSet::from([Severity::WorldRecord, Severity::Milestone])
+}
+
+/// posts statuses through the mastodon api
+pub struct Mastodon {
+    client: reqwest::Client,
+    config: MastodonConfig,
+}
+
+/// the bits of the response we care about
+#[derive(Deserialize)]
+struct Status {
+    id: String,
+}
+
+impl Mastodon {
+    pub fn new(config: &MastodonConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config: config.clone(),
+        }
+    }
+}
+
+/// if a webhook from the config means the mastodon account
+pub fn is_account(url: &str) -> bool {
+    url == "mastodon"
+}
+
+/// cut a status down to what fits, on a char boundary
+fn status(text: &str) -> String {
+    let text = text.trim_end();
+    match text.chars().count() > MAX_LEN {
+        true => {
+            let mut status: String = text.chars().take(MAX_LEN - 1).collect();
+            status.push('…');
+            status
+        }
+        false => text.to_string(),
+    }
+}
+
+impl Notifier for Mastodon {
+    fn send<'a>(&'a self, message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            let body = match message {
+                RenderedMessage::Text(text) => &json!({ "status": status(text) }),
+                RenderedMessage::Json(payload) => payload,
+                RenderedMessage::Html(_) | RenderedMessage::Svg(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "mastodon only takes text",
+                    ))
+                }
+            };
+
+            let url = format!(
+                "{}/api/v1/statuses",
+                self.config.instance.trim_end_matches('/')
+            );
+            let status: Status = self
+                .client
+                .post(url)
+                .bearer_auth(&self.config.token)
+                .json(body)
+                .send()
+                .await
+                .map_err(io::Error::other)?
+                .error_for_status()
+                .map_err(io::Error::other)?
+                .json()
+                .await
+                .map_err(io::Error::other)?;
+
+            status
+                .id
+                .parse()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+    }
+
+    // pings are for chats, they'd mean nothing here
+    fn payload(
+        &self,
+        _event: &Event,
+        _ctx: &RenderCtx,
+        content: &str,
+        _extra: &str,
+        text: &str,
+    ) -> Value {
+        let text = text.strip_prefix(content).unwrap_or(text).trim_start();
+        json!({ "status": status(text) })
+    }
+
+    fn wants(&self, event: &Event) -> bool {
+        self.config.severities.contains(&event.severity())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+
+    #[test]
+    fn test_payload() {
+        let entry = Entry {
+            rank: 1,
+            name: "possm".to_string(),
+            user_id: 1,
+            run_id: 2,
+            score: 410.0,
+        };
+        let wr = Event::WorldRecord {
+            old: &entry,
+            new: &entry,
+        };
+        let config: MastodonConfig =
+            toml::from_str("instance = \"https://mastodon.social\"\ntoken = \"t\"").unwrap();
+        let mastodon = Mastodon::new(&config);
+
+        // whatever the board rendered, without the pings
+        let text = "<@&1> ---  世界記録  ---\npossm 410\n";
+        let payload = mastodon.payload(&wr, &RenderCtx::new("main"), "<@&1>", "", text);
+        assert_eq!(payload["status"], "---  世界記録  ---\npossm 410");
+    }
+
+    #[test]
+    fn test_status() {
+        assert_eq!(status("new wr!\n"), "new wr!");
+
+        let long = "a".repeat(600);
+        let cut = status(&long);
+        assert_eq!(cut.chars().count(), MAX_LEN);
+        assert!(cut.ends_with('…'));
+
+        let config: MastodonConfig =
+            toml::from_str("instance = \"https://mastodon.social\"\ntoken = \"t\"").unwrap();
+        assert_eq!(config.severities, default_severities());
+    }
+}
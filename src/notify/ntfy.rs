@@ -0,0 +1,167 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io;
+
+use crate::lb::{Event, Severity};
+use crate::notify::Notifier;
+use crate::render::{banner, RenderCtx, RenderedMessage};
+use crate::schedule::BoxFuture;
+
+/// push notifications through ntfy, for someone who wants their
+/// watchlist on their phone without discord
+///
+/// targets send to a topic with `ntfy:<topic>` as their webhook.
+/// it goes through ntfy.sh, unless there's a server of your own
+///
+/// ```toml
+/// [ntfy]
+/// server = "https://ntfy.example.com"
+/// token = "env:NTFY_TOKEN"
+///
+/// [[board.targets]]
+/// name = "my-phone"
+/// webhook = "ntfy:possm-pbs"
+/// filter = { watchlist = ["possm"] }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NtfyConfig {
+    pub server: String,
+    /// for servers that need one, can come from the environment
+    /// like webhooks
+    pub token: Option<String>,
+}
+
+impl Default for NtfyConfig {
+    fn default() -> Self {
+        Self {
+            server: "https://ntfy.sh".to_string(),
+            token: None,
+        }
+    }
+}
+
+/// one topic on an ntfy server
+pub struct Ntfy {
+    client: reqwest::Client,
+    config: NtfyConfig,
+    topic: String,
+}
+
+impl Ntfy {
+    pub fn new(config: &NtfyConfig, topic: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config: config.clone(),
+            topic: topic.to_string(),
+        }
+    }
+}
+
+/// the topic, if a webhook from the config is an ntfy one
+pub fn topic(url: &str) -> Option<&str> {
+    url.strip_prefix("ntfy:")
+}
+
+impl Notifier for Ntfy {
+    fn send<'a>(&'a self, message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            let mut body = match message {
+                RenderedMessage::Text(text) => json!({ "message": text.trim_end() }),
+                RenderedMessage::Json(payload) => payload.clone(),
+                RenderedMessage::Html(_) | RenderedMessage::Svg(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "ntfy only takes text",
+                    ))
+                }
+            };
+            body["topic"] = self.topic.clone().into();
+
+            let mut req = self.client.post(&self.config.server).json(&body);
+            if let Some(token) = &self.config.token {
+                req = req.bearer_auth(token);
+            }
+            req.send()
+                .await
+                .map_err(io::Error::other)?
+                .error_for_status()
+                .map_err(io::Error::other)?;
+
+            // a notification can't be edited, so its id is no use
+            Ok(0)
+        })
+    }
+
+    fn payload(
+        &self,
+        event: &Event,
+        ctx: &RenderCtx,
+        _content: &str,
+        _extra: &str,
+        text: &str,
+    ) -> Value {
+        // the banner is the title, so it doesn't need to be in there twice
+        let text = match text.split_once("  ---\n") {
+            Some((_, rest)) => rest,
+            None => text,
+        };
+        let (priority, tags) = match event.severity() {
+            Severity::WorldRecord => (5, vec!["trophy"]),
+            Severity::Milestone => (4, vec!["tada"]),
+            Severity::TopPb | Severity::Pb => (3, vec![]),
+            Severity::Minor => (2, vec![]),
+        };
+        json!({
+            "title": format!("{}: {}", ctx.board, banner(event).unwrap_or("New pb".to_string())),
+            "message": text.trim_end(),
+            "priority": priority,
+            "tags": tags,
+        })
+    }
+
+    fn structured(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+    use crate::render::{Render, Text};
+
+    #[test]
+    fn test_payload() {
+        let entry = |rank, run_id, score| Entry {
+            rank,
+            name: "possm".to_string(),
+            user_id: 1,
+            run_id,
+            score,
+        };
+        let (old, new) = (entry(2, 1, 399.0), entry(1, 2, 410.0));
+        let wr = Event::WorldRecord {
+            old: &old,
+            new: &new,
+        };
+        let ntfy = Ntfy::new(&NtfyConfig::default(), "possm-pbs");
+        assert_eq!(topic("ntfy:possm-pbs"), Some("possm-pbs"));
+
+        let ctx = RenderCtx::new("main");
+        let text = format!(
+            "{}Held it for 3 days\n",
+            Text.render(&wr, &ctx).into_string()
+        );
+        let payload = ntfy.payload(&wr, &ctx, "", "Held it for 3 days\n", &text);
+        assert_eq!(payload["title"], "main: NEW WORLD RECORD");
+        assert_eq!(payload["priority"], 5);
+        assert_eq!(
+            payload["message"],
+            "possm just got a new high score! Score: 410 (+11)\n\
+             They are now rank #1, gaining 1 ranks.\n\
+             Watch in-game: hyperdemon://run/2\n\
+             Held it for 3 days"
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use serde_json::{json, Value};
+use tokio::io;
+
+use crate::lb::Event;
+use crate::notify::Notifier;
+use crate::render::{RenderCtx, RenderedMessage, SlackBlocks};
+use crate::schedule::BoxFuture;
+
+/// a slack incoming webhook
+///
+/// these don't say anything about the message they post, so
+/// there's no id for it and it can't be corrected later
+pub struct Slack {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl Slack {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+/// if a webhook from the config is one of slack's
+pub fn is_webhook(url: &str) -> bool {
+    url.starts_with("https://hooks.slack.com/")
+}
+
+impl Notifier for Slack {
+    fn send<'a>(&'a self, message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            let body = match message {
+                RenderedMessage::Text(text) => &json!({ "text": text }),
+                RenderedMessage::Json(payload) => payload,
+                RenderedMessage::Html(_) | RenderedMessage::Svg(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "slack only takes text and blocks",
+                    ))
+                }
+            };
+
+            // the outbox tries again later if it's rate limited
+            self.client
+                .post(&self.url)
+                .json(body)
+                .send()
+                .await
+                .map_err(io::Error::other)?
+                .error_for_status()
+                .map_err(io::Error::other)?;
+
+            Ok(0)
+        })
+    }
+
+    fn payload(
+        &self,
+        event: &Event,
+        ctx: &RenderCtx,
+        content: &str,
+        extra: &str,
+        _text: &str,
+    ) -> Value {
+        SlackBlocks::payload(event, ctx, content, extra)
+    }
+}
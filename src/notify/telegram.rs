@@ -0,0 +1,168 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io;
+
+use crate::hook::{send_parts, split_to};
+use crate::lb::{now, Event};
+use crate::notify::Notifier;
+use crate::quiet::QuietHours;
+use crate::render::telegram::escape_markdown_v2;
+use crate::render::{RenderCtx, RenderedMessage};
+use crate::schedule::BoxFuture;
+
+/// longest message telegram takes, in characters
+const MAX_LEN: usize = 4096;
+
+/// the bot that posts to telegram chats
+///
+/// boards send to a chat with `telegram:<chat id>` as their webhook
+///
+/// ```toml
+/// [telegram]
+/// token = "env:TELEGRAM_TOKEN"
+/// quiet_hours = { from = 23, to = 7 }
+///
+/// [[board]]
+/// name = "main"
+/// webhook = "telegram:-1001234567890"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    /// from @BotFather, can come from the environment like webhooks
+    pub token: String,
+    /// messages still go out, they just don't make a sound
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// one chat, through the bot api
+pub struct Telegram {
+    client: reqwest::Client,
+    token: String,
+    chat_id: String,
+    quiet_hours: Option<QuietHours>,
+}
+
+/// the bits of telegram's response we care about
+#[derive(Deserialize)]
+struct Response {
+    result: Posted,
+}
+
+#[derive(Deserialize)]
+struct Posted {
+    message_id: u64,
+}
+
+impl Telegram {
+    pub fn new(config: &TelegramConfig, chat_id: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            token: config.token.clone(),
+            chat_id: chat_id.to_string(),
+            quiet_hours: config.quiet_hours,
+        }
+    }
+
+    async fn post(&self, mut body: Value) -> io::Result<u64> {
+        body["chat_id"] = self.chat_id.clone().into();
+        let now = now().as_secs();
+        body["disable_notification"] = self.quiet_hours.is_some_and(|q| q.at(now)).into();
+
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.token);
+        let res: Response = self
+            .client
+            .post(url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(io::Error::other)?
+            .error_for_status()
+            .map_err(io::Error::other)?
+            .json()
+            .await
+            .map_err(io::Error::other)?;
+
+        Ok(res.result.message_id)
+    }
+}
+
+/// the chat, if a webhook from the config is a telegram one
+pub fn chat(url: &str) -> Option<&str> {
+    url.strip_prefix("telegram:")
+}
+
+impl Notifier for Telegram {
+    fn send<'a>(&'a self, message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            match message {
+                // plain text goes without a parse mode, so nothing needs
+                // escaping. that's batches and digests, events go as json
+                RenderedMessage::Text(text) => {
+                    send_parts(split_to(text, MAX_LEN), |text| {
+                        self.post(json!({ "text": text }))
+                    })
+                    .await
+                }
+                RenderedMessage::Json(payload) => self.post(payload.clone()).await,
+                RenderedMessage::Html(_) | RenderedMessage::Svg(_) => Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "telegram only takes text and markdown",
+                )),
+            }
+        })
+    }
+
+    /// what the board rendered, in MarkdownV2 so nothing in it,
+    /// like a name, gets taken for markup
+    fn payload(
+        &self,
+        _event: &Event,
+        _ctx: &RenderCtx,
+        _content: &str,
+        _extra: &str,
+        text: &str,
+    ) -> Value {
+        json!({
+            "text": escape_markdown_v2(text.trim_end()),
+            "parse_mode": "MarkdownV2",
+        })
+    }
+
+    // so single events always get escaped, not only on boards with `embeds`
+    fn structured(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+
+    #[test]
+    fn test_payload() {
+        let entry = Entry {
+            rank: 1,
+            name: "fenne_kal".to_string(),
+            user_id: 1,
+            run_id: 2,
+            score: 410.5,
+        };
+        let wr = Event::WorldRecord {
+            old: &entry,
+            new: &entry,
+        };
+        let config: TelegramConfig = toml::from_str("token = \"t\"").unwrap();
+        let telegram = Telegram::new(&config, "-100");
+        assert!(telegram.structured());
+
+        let text = "fenne_kal took the world record with 410.5!\n";
+        let payload = telegram.payload(&wr, &RenderCtx::new("main"), "", "", text);
+        assert_eq!(
+            payload["text"],
+            "fenne\\_kal took the world record with 410\\.5\\!"
+        );
+        assert_eq!(payload["parse_mode"], "MarkdownV2");
+    }
+}
@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::{self, AsyncWriteExt};
+
+use crate::hook::Pings;
+use crate::lb::Event;
+use crate::sent::{event_id, Sent};
+
+/// longest to wait between tries, in seconds
+const MAX_BACKOFF: u64 = 3600;
+
+/// a message that has to go out, but hasn't yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Queued {
+    pub event_id: String,
+    pub user_id: u32,
+    pub run_id: u32,
+    pub message: String,
+    /// the `config::Route` it goes to, none for the board's webhook
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>,
+    /// a discord embed to send instead of the message, when it's
+    /// sent on its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embed: Option<serde_json::Value>,
+    /// a png to go in the embed. it's not worth much once it's
+    /// old, so it only stays in memory
+    #[serde(skip)]
+    pub image: Option<Vec<u8>>,
+    /// it came up during quiet hours, so it goes out in a batch
+    /// with everything else from them
+    #[serde(default)]
+    pub held: bool,
+    /// who it can ping, see `hook::Pings`
+    #[serde(default, skip_serializing_if = "Pings::is_empty")]
+    pub pings: Pings,
+    /// how many times sending it failed
+    pub attempts: u32,
+    /// unix time it can be tried again at
+    pub retry_at: u64,
+}
+
+impl Queued {
+    pub fn new(board: &str, pb: &Event, message: String, route: Option<&str>) -> Self {
+        Self {
+            event_id: event_id(board, pb),
+            user_id: pb.entry().user_id,
+            run_id: pb.entry().run_id,
+            message,
+            route: route.map(str::to_string),
+            embed: None,
+            image: None,
+            held: false,
+            pings: Pings::default(),
+            attempts: 0,
+            retry_at: 0,
+        }
+    }
+
+    /// the sent log record for it, once it went out
+    pub fn sent(&self, board: &str, message_id: u64, timestamp: u64) -> Sent {
+        Sent {
+            event_id: self.event_id.clone(),
+            board: board.to_string(),
+            user_id: self.user_id,
+            run_id: self.run_id,
+            message_id,
+            route: self.route.clone(),
+            timestamp,
+        }
+    }
+}
+
+/// messages for a board that still have to go out
+///
+/// everything gets written here before it's sent, and only
+/// leaves once the webhook took it. so if sending fails, or
+/// the bot dies halfway through a cycle, the baseline can
+/// still move on without anything getting lost.
+///
+/// kept next to the cache, as a json array
+pub struct Outbox {
+    /// only kept in memory if there's no path
+    path: Option<PathBuf>,
+    queue: Vec<Queued>,
+}
+
+impl Outbox {
+    /// read whatever was left over from last time
+    pub async fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let queue = match fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            path: Some(path),
+            queue,
+        })
+    }
+
+    /// an outbox that never touches the disk
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            queue: Vec::new(),
+        }
+    }
+
+    pub fn queued(&self) -> &[Queued] {
+        &self.queue
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// everything that's up for another try at `now`
+    pub fn due(&self, now: u64) -> Vec<Queued> {
+        self.queue
+            .iter()
+            .filter(|q| q.retry_at <= now)
+            .cloned()
+            .collect()
+    }
+
+    /// queue up messages, and make sure they're on disk
+    pub async fn push(&mut self, queued: impl IntoIterator<Item = Queued>) -> io::Result<()> {
+        self.queue.extend(queued);
+        self.save().await
+    }
+
+    /// it went out, forget about it
+    pub fn done(&mut self, event_id: &str) {
+        self.queue.retain(|q| q.event_id != event_id);
+    }
+
+    /// it didn't go out, back off before trying again
+    pub fn failed(&mut self, event_id: &str, now: u64) {
+        if let Some(q) = self.queue.iter_mut().find(|q| q.event_id == event_id) {
+            q.attempts += 1;
+            let backoff = 30u64
+                .saturating_mul(1 << q.attempts.min(16))
+                .min(MAX_BACKOFF);
+            q.retry_at = now + backoff;
+        }
+    }
+
+    /// write the queue out
+    pub async fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let bytes = serde_json::to_vec(&self.queue)?;
+        let tmp = path.with_extension("outbox.tmp");
+
+        let mut file = fs::File::create(&tmp).await?;
+        file.write_all(&bytes).await?;
+        file.sync_all().await?;
+        fs::rename(&tmp, path).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn queued(run_id: u32) -> Queued {
+        Queued {
+            event_id: format!("main-{}", run_id),
+            user_id: 1,
+            run_id,
+            message: "pb!".to_string(),
+            route: None,
+            embed: None,
+            image: None,
+            held: false,
+            pings: Pings::default(),
+            attempts: 0,
+            retry_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outbox() {
+        let path = std::env::temp_dir().join(format!("hdget-outbox-{}", std::process::id()));
+        let mut outbox = Outbox::load(&path).await.unwrap();
+        assert!(outbox.is_empty());
+
+        outbox.push([queued(1), queued(2)]).await.unwrap();
+        outbox.failed("main-1", 100);
+        outbox.done("main-2");
+        assert_eq!(outbox.due(100).len(), 0);
+        assert_eq!(outbox.due(160).len(), 1);
+        outbox.failed("main-1", 160);
+        assert_eq!(outbox.due(160 + 119).len(), 0);
+        assert_eq!(outbox.due(160 + 120).len(), 1);
+
+        // only what got pushed is on disk for sure
+        let reloaded = Outbox::load(&path).await.unwrap();
+        assert_eq!(reloaded.queued().len(), 2);
+        outbox.save().await.unwrap();
+        let reloaded = Outbox::load(&path).await.unwrap();
+        assert_eq!(reloaded.queued().len(), 1);
+        assert_eq!(reloaded.queued()[0].attempts, 2);
+
+        fs::remove_file(&path).await.unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+
+use tokio::fs;
+use tokio::io;
+use tokio::process::Command;
+
+/// what `hdget self package` builds when no targets are given
+pub const DEFAULT_TARGETS: &[&str] = &["x86_64-unknown-linux-musl", "aarch64-unknown-linux-musl"];
+
+/// the image the bot runs in
+///
+/// the binary is static, so all it needs from alpine is the ca certs.
+/// the config goes in `/config`, and everything the bot writes (caches,
+/// the sent log, baselines, ...) ends up in `/data`, since relative paths
+/// in the config are relative to there, and it's the data dir too
+const DOCKERFILE: &str = r#"FROM alpine:3 AS certs
+RUN apk add --no-cache ca-certificates
+
+FROM scratch
+ARG TARGETARCH
+COPY --from=certs /etc/ssl/certs/ca-certificates.crt /etc/ssl/certs/
+COPY linux-${TARGETARCH}/hdget /hdget
+VOLUME ["/config", "/data"]
+WORKDIR /data
+ENV XDG_DATA_HOME=/data
+ENTRYPOINT ["/hdget", "--config", "/config/hdget.toml"]
+"#;
+
+/// builds release binaries, and optionally a container image
+///
+/// needs to be run from a checkout of hdget, with the targets added
+/// through rustup and a musl openssl around. `cross` images have
+/// both, so `--cross` is usually the easy way
+pub struct Package {
+    out: PathBuf,
+    targets: Vec<String>,
+    /// build with `cross` instead of `cargo`, for targets
+    /// the host doesn't have a linker for
+    cross: bool,
+}
+
+impl Package {
+    pub fn new(out: impl AsRef<Path>) -> Self {
+        Self {
+            out: out.as_ref().to_path_buf(),
+            targets: DEFAULT_TARGETS.iter().map(|t| t.to_string()).collect(),
+            cross: false,
+        }
+    }
+
+    pub fn targets(mut self, targets: Vec<String>) -> Self {
+        if !targets.is_empty() {
+            self.targets = targets;
+        }
+        self
+    }
+
+    pub fn cross(mut self, cross: bool) -> Self {
+        self.cross = cross;
+        self
+    }
+
+    /// build every target, and copy the binaries to
+    /// `<out>/<os>-<arch>/hdget`
+    ///
+    /// returns where each binary ended up
+    pub async fn build(&self) -> io::Result<Vec<PathBuf>> {
+        if !fs::try_exists("Cargo.toml").await? {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no Cargo.toml here, run this from a checkout of hdget",
+            ));
+        }
+
+        let mut built = Vec::new();
+        for target in &self.targets {
+            let platform = platform(target).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("don't know how to package for {}", target),
+                )
+            })?;
+
+            let status = Command::new(if self.cross { "cross" } else { "cargo" })
+                .args(["build", "--release", "--target", target])
+                // musl links statically by default, but make sure
+                .env("RUSTFLAGS", "-C target-feature=+crt-static")
+                .status()
+                .await?;
+            if !status.success() {
+                return Err(io::Error::other(format!(
+                    "building for {} failed ({})",
+                    target, status
+                )));
+            }
+
+            let dir = self.out.join(platform.replace('/', "-"));
+            fs::create_dir_all(&dir).await?;
+            let bin = dir.join("hdget");
+            fs::copy(Path::new("target").join(target).join("release/hdget"), &bin).await?;
+            built.push(bin);
+        }
+
+        Ok(built)
+    }
+
+    /// write the Dockerfile next to the binaries, and build a
+    /// multi-arch image for every target out of it
+    pub async fn image(&self, tag: &str) -> io::Result<()> {
+        fs::write(self.out.join("Dockerfile"), DOCKERFILE).await?;
+
+        let platforms: Vec<_> = self.targets.iter().filter_map(|t| platform(t)).collect();
+        let status = Command::new("docker")
+            .args([
+                "buildx",
+                "build",
+                "--platform",
+                &platforms.join(","),
+                "-t",
+                tag,
+            ])
+            .arg(&self.out)
+            .status()
+            .await?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "building the image failed ({})",
+                status
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// the docker platform a target triple runs on
+fn platform(target: &str) -> Option<&'static str> {
+    let (arch, rest) = target.split_once('-')?;
+    if !rest.contains("linux") {
+        return None;
+    }
+
+    match arch {
+        "x86_64" => Some("linux/amd64"),
+        "aarch64" => Some("linux/arm64"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_platform() {
+        assert_eq!(platform("x86_64-unknown-linux-musl"), Some("linux/amd64"));
+        assert_eq!(platform("aarch64-unknown-linux-musl"), Some("linux/arm64"));
+        assert_eq!(platform("x86_64-pc-windows-msvc"), None);
+        assert_eq!(platform("riscv64gc-unknown-linux-musl"), None);
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+
+use tokio::fs;
+use tokio::io;
+use tracing::{info, warn};
+
+use crate::hook::Hook;
+use crate::lb::{Entry, Leaderboard};
+use crate::render::discord::escape_markdown;
+
+/// one message that always shows the current world record, edited
+/// in place whenever it changes. webhooks can't pin anything, so
+/// that part's up to whoever runs the channel
+///
+/// ```toml
+/// [[board]]
+/// pinned_record = true
+/// ```
+pub struct Pinned {
+    hook: Hook,
+    /// where the id of the message is kept, so it's the same
+    /// message after a restart. none keeps it in memory
+    path: Option<PathBuf>,
+    id: Option<u64>,
+    /// run the message shows right now
+    shown: Option<u32>,
+}
+
+impl Pinned {
+    /// pick up the message from last time, if there was one
+    pub async fn load(hook: Hook, path: Option<PathBuf>) -> io::Result<Self> {
+        let id = match &path {
+            Some(path) => match fs::read_to_string(path).await {
+                Ok(id) => Some(
+                    id.trim()
+                        .parse()
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+                ),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            },
+            None => None,
+        };
+
+        Ok(Self {
+            hook,
+            path,
+            id,
+            shown: None,
+        })
+    }
+
+    /// make the message show `lb`'s record, if it doesn't already.
+    /// always edits it the first time, in case it changed while
+    /// the bot was down
+    pub async fn update(&mut self, lb: &Leaderboard, board: &str) -> io::Result<()> {
+        let Some(wr) = lb.entries().first() else {
+            return Ok(());
+        };
+        if self.shown == Some(wr.run_id) {
+            return Ok(());
+        }
+
+        let content = text(wr, board);
+        let edited = match self.id {
+            Some(id) => match self.hook.edit(id, &content).await {
+                Ok(()) => true,
+                // someone deleted it. anything else could go away by
+                // itself, and a new one would leave the old one stale
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    warn!(board, error = %e, "the pinned record is gone, sending a new one");
+                    false
+                }
+                Err(e) => return Err(e),
+            },
+            None => false,
+        };
+        if !edited {
+            let id = self.hook.send(&content).await?;
+            info!(board, id, "sent the pinned record");
+            self.id = Some(id);
+            if let Some(path) = &self.path {
+                fs::write(path, id.to_string()).await?;
+            }
+        }
+
+        self.shown = Some(wr.run_id);
+        Ok(())
+    }
+}
+
+/// what the message says
+fn text(wr: &Entry, board: &str) -> String {
+    format!(
+        "Current world record on {}: {} with {}\nWatch in-game: {}",
+        escape_markdown(board),
+        escape_markdown(&wr.name),
+        wr.score,
+        wr.run_link()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pinned() {
+        let path = std::env::temp_dir().join(format!("hdget-pinned-{}", std::process::id()));
+        fs::write(&path, "1234\n").await.unwrap();
+        let pinned = Pinned::load(Hook::new("http://localhost:9/webhook"), Some(path.clone()))
+            .await
+            .unwrap();
+        fs::remove_file(&path).await.unwrap();
+        assert_eq!(pinned.id, Some(1234));
+
+        let wr = Entry {
+            rank: 1,
+            name: "fenne_kal".to_string(),
+            user_id: 2,
+            run_id: 3,
+            score: 410.0,
+        };
+        assert_eq!(
+            text(&wr, "main"),
+            "Current world record on main: fenne\\_kal with 410\n\
+             Watch in-game: hyperdemon://run/3"
+        );
+    }
+}
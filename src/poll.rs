@@ -0,0 +1,1593 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::fs;
+use tokio::io;
+use tracing::{info, warn};
+
+#[cfg(feature = "graphs")]
+use crate::annotate::Annotations;
+use crate::archive::Archive;
+use crate::audit::{Audit, Decision, Record};
+use crate::bucket::Bucket;
+use crate::config::{BoardConfig, Config};
+use crate::digest;
+#[cfg(feature = "graphs")]
+use crate::graph;
+use crate::history::History;
+use crate::hook::{self, Hook, Pings, Thread};
+use crate::journal::Journal;
+use crate::lb::{now, CacheFormat, DiffOptions, Entry, Event, EventKind, Index, Leaderboard};
+use crate::live::Live;
+use crate::mentions::Mentions;
+use crate::notify::{self, Notifier};
+use crate::outbox::{Outbox, Queued};
+use crate::pinned::Pinned;
+use crate::render::{RenderCtx, RenderedMessage};
+use crate::report::{CycleReport, Fetched};
+use crate::rivals;
+use crate::rules;
+#[cfg(feature = "script")]
+use crate::script::Script;
+use crate::sent::{event_id, Recent, Sent, SentLog};
+#[cfg(feature = "postgres")]
+use crate::shared::Shared;
+use crate::sink::Sink;
+use crate::source::FetchOutcome;
+use crate::store::{Emitted, Store, User};
+use crate::validate::Strictness;
+
+/// keeps track of a single board between polls
+pub struct Poller {
+    board: BoardConfig,
+    config: Arc<Config>,
+    audit: Arc<Audit>,
+    delivery: Delivery,
+    cache: PathBuf,
+    journal: Journal,
+    /// the baseline new leaderboards get diffed against
+    ///
+    /// this is the real state, the cache only gets written
+    /// every `persist_interval`, see `Journal`
+    old: Leaderboard,
+    /// where everyone is on `old`
+    index: Index,
+    /// if `old` has changes that aren't in the cache yet
+    dirty: bool,
+    last_persist: Instant,
+    /// the journal is missing changes, so the cache has to be written
+    /// before anything else can go in the journal
+    must_persist: bool,
+    store: Option<Store>,
+    #[cfg(feature = "postgres")]
+    shared: Option<Arc<Shared>>,
+    archive: Option<Archive>,
+    bucket: Option<Bucket>,
+    /// events that haven't made it into the store yet
+    history: Vec<Emitted>,
+    sink: Option<Sink>,
+    #[cfg(feature = "script")]
+    script: Option<Script>,
+    pinned: Option<Pinned>,
+    /// what the next cycle gets instead of fetching the board,
+    /// see `rehearse`
+    next: Option<Leaderboard>,
+    /// where the baseline goes for the bot to answer from
+    live: Option<Arc<Live>>,
+    /// nothing's been diffed against the baseline from startup yet,
+    /// see `stale_gap`
+    restarted: bool,
+    health: Health,
+    cycle: u64,
+}
+
+impl Poller {
+    /// load the board's cache, or fetch a fresh baseline if that fails
+    pub async fn new(
+        board: BoardConfig,
+        config: Arc<Config>,
+        sent: Arc<SentLog>,
+        audit: Arc<Audit>,
+        operator: Option<Arc<dyn Notifier>>,
+        store: Option<Store>,
+        #[cfg(feature = "postgres")] shared: Option<Arc<Shared>>,
+    ) -> io::Result<Self> {
+        Self::open(
+            board,
+            config,
+            sent,
+            audit,
+            operator,
+            store,
+            #[cfg(feature = "postgres")]
+            shared,
+            None,
+        )
+        .await
+    }
+
+    /// a poller for `hdget notify-test`, that starts off the made up
+    /// board instead of fetching one, so it works with the site down.
+    /// see `rehearse`
+    pub async fn for_rehearsal(
+        board: BoardConfig,
+        config: Arc<Config>,
+        sent: Arc<SentLog>,
+        audit: Arc<Audit>,
+    ) -> io::Result<Self> {
+        let (old, _) = rehearsal(&board.diff);
+        Self::open(
+            board,
+            config,
+            sent,
+            audit,
+            None,
+            None,
+            #[cfg(feature = "postgres")]
+            None,
+            Some(old),
+        )
+        .await
+    }
+
+    /// `new`, starting off `baseline` if there is one
+    #[allow(clippy::too_many_arguments)]
+    async fn open(
+        board: BoardConfig,
+        config: Arc<Config>,
+        sent: Arc<SentLog>,
+        audit: Arc<Audit>,
+        operator: Option<Arc<dyn Notifier>>,
+        store: Option<Store>,
+        #[cfg(feature = "postgres")] shared: Option<Arc<Shared>>,
+        baseline: Option<Leaderboard>,
+    ) -> io::Result<Self> {
+        let hook: Arc<dyn Notifier> = match board.thread {
+            Some(thread) => Arc::new(Hook::new(&board.webhook).in_thread(thread, &board.name)),
+            None => notify::webhook(&board.webhook, &config),
+        };
+        let routes = board.routes.iter().map(|r| (&r.name, &r.webhook));
+        let routes = routes
+            .chain(board.targets.iter().map(|t| (&t.name, &t.webhook)))
+            .map(|(name, webhook)| (name.clone(), notify::webhook(webhook, &config)))
+            .collect();
+        #[cfg(feature = "script")]
+        let script = board.script.as_ref().map(Script::load).transpose()?;
+        #[cfg(not(feature = "script"))]
+        if board.script.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "hdget was built without the script feature",
+            ));
+        }
+        #[cfg(not(feature = "graphs"))]
+        if board.graphs {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "hdget was built without the graphs feature",
+            ));
+        }
+        let pinned = match board.pinned_record {
+            true => {
+                let mut hook = Hook::new(&board.webhook);
+                // a daily thread would be gone from view by tomorrow
+                if let Some(thread @ Thread::Id(_)) = board.thread {
+                    hook = hook.in_thread(thread, &board.name);
+                }
+                let path = (!config.memory_only).then(|| board.pinned_path());
+                Some(Pinned::load(hook, path).await?)
+            }
+            false => None,
+        };
+        let cache = board.cache_path();
+        let journal = Journal::new(board.journal_path());
+        let outbox = match config.memory_only {
+            true => Outbox::in_memory(),
+            false => Outbox::load(board.outbox_path()).await?,
+        };
+        let bucket = config
+            .s3
+            .as_ref()
+            .map(|s3| Bucket::new(s3, config.compress_cache))
+            .transpose()?;
+
+        // Get cache on startup
+        let old = if let Some(old) = baseline {
+            old
+        } else if config.memory_only {
+            initial(&board, store.as_ref(), bucket.as_ref()).await?
+        } else {
+            let mut salvaged = false;
+            let read = match Leaderboard::read_cache(&cache).await {
+                // a few lost entries beats a fresh baseline, which
+                // would swallow every pb set while the bot was down
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    match Leaderboard::salvage(&cache).await {
+                        Ok(Some((old, format))) => {
+                            warn!(
+                                board = %board.name,
+                                error = %e,
+                                kept = old.len(),
+                                "salvaged what was left of the cache",
+                            );
+                            salvaged = true;
+                            Ok((old, format))
+                        }
+                        _ => Err(e),
+                    }
+                }
+                read => read,
+            };
+            match read {
+                // we got the cache smoothly, catch up on whatever
+                // changed after it was written
+                Ok((mut old, format)) => {
+                    // the journal was written by whatever wrote the cache
+                    let current = matches!(format, CacheFormat::Current | CacheFormat::Json);
+                    let applied = journal.replay(&mut old, current).await?;
+                    if applied > 0 {
+                        info!(board = %board.name, applied, "replayed journal");
+                    }
+                    if !current || salvaged {
+                        info!(board = %board.name, ?format, "rewriting cache");
+                        old.cache_as(&cache, config.cache_format, config.compress_cache)
+                            .await?;
+                        journal.clear().await?;
+                    }
+                    old
+                }
+                // we couldn't read the cache for some reason :(
+                Err(e) => {
+                    warn!(board = %board.name, error = %e, "couldn't read cache");
+                    let new = initial(&board, store.as_ref(), bucket.as_ref()).await?;
+                    new.cache_as(&cache, config.cache_format, config.compress_cache)
+                        .await?;
+                    // anything in here was for the old cache
+                    journal.clear().await?;
+                    new
+                }
+            }
+        };
+
+        let cycle = audit.last_cycle(&board.name).await?;
+        let archive = config.archive.as_ref().map(|dir| {
+            Archive::new(dir, config.compress_cache).full_every(config.archive_full_every)
+        });
+        let sink = config.event_sink.as_ref().map(Sink::new);
+        let health = Health {
+            board: board.name.clone(),
+            operator,
+            degraded: false,
+            failed: false,
+        };
+
+        Ok(Self {
+            board,
+            config,
+            audit,
+            delivery: Delivery {
+                hook,
+                routes,
+                sent,
+                pending: Vec::new(),
+                outbox,
+            },
+            cache,
+            journal,
+            index: Index::new(&old),
+            old,
+            dirty: false,
+            last_persist: Instant::now(),
+            must_persist: false,
+            store,
+            #[cfg(feature = "postgres")]
+            shared,
+            archive,
+            bucket,
+            history: Vec::new(),
+            sink,
+            #[cfg(feature = "script")]
+            script,
+            pinned,
+            next: None,
+            live: None,
+            restarted: true,
+            health,
+            cycle,
+        })
+    }
+
+    /// write the baseline to the cache, if it changed
+    pub async fn persist(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        if !self.config.memory_only {
+            self.old
+                .cache_as(
+                    &self.cache,
+                    self.config.cache_format,
+                    self.config.compress_cache,
+                )
+                .await?;
+            self.journal.clear().await?;
+        }
+        if let Some(bucket) = &self.bucket {
+            bucket.save_latest(&self.board.name, &self.old).await?;
+        }
+        self.dirty = false;
+        self.must_persist = false;
+        self.last_persist = Instant::now();
+
+        Ok(())
+    }
+
+    pub fn board(&self) -> &BoardConfig {
+        &self.board
+    }
+
+    /// keep the latest leaderboard in `live` after every cycle
+    pub fn publish_to(mut self, live: Arc<Live>) -> Self {
+        live.set(&self.board.name, &self.old);
+        self.live = Some(live);
+        self
+    }
+
+    /// fetch the board, diff it, and send out whatever changed
+    pub async fn poll(&mut self) -> io::Result<CycleReport> {
+        self.cycle += 1;
+        let mut report = CycleReport::new(&self.board.name, self.cycle);
+        let mut records = Vec::new();
+
+        self.health.failed = false;
+        let res = self.locked_cycle(&mut report, &mut records).await;
+        let caught_up = self.delivery.pending.is_empty()
+            && self.history.is_empty()
+            && self.delivery.outbox.is_empty();
+        if res.is_ok() && !self.health.failed && !self.must_persist && caught_up {
+            self.health.recovered().await;
+        }
+
+        // the audit log is for looking into things after the fact,
+        // so not being able to write it isn't worth stopping for
+        records.push(Record::Cycle(report.clone()));
+        if let Err(e) = self.audit.append(&records).await {
+            warn!(board = %self.board.name, error = %e, "couldn't write the audit log");
+        }
+
+        res.map(|_| report)
+    }
+
+    /// send a made up world record, milestone and pb through
+    /// everything a real cycle does, for `hdget notify-test`
+    ///
+    /// the made up boards take the place of the baseline and of
+    /// what the source has
+    pub async fn rehearse(&mut self) -> io::Result<CycleReport> {
+        let (old, new) = rehearsal(&self.board.diff);
+        self.index = Index::new(&old);
+        self.old = old;
+        self.next = Some(new);
+        self.poll().await
+    }
+
+    /// run a cycle, unless another instance has the board
+    async fn locked_cycle(
+        &mut self,
+        report: &mut CycleReport,
+        records: &mut Vec<Record>,
+    ) -> io::Result<()> {
+        #[cfg(feature = "postgres")]
+        if let Some(shared) = self.shared.clone() {
+            if !shared.lock(&self.board.name).await? {
+                report.fetched = Fetched::Standby;
+                return Ok(());
+            }
+
+            let res = async {
+                // whoever had the board last might've moved the baseline on
+                if let Some(old) = shared.baseline(&self.board.name).await? {
+                    self.index = Index::new(&old);
+                    self.old = old;
+                }
+                self.cycle(report, records).await?;
+                shared.save_baseline(&self.board.name, &self.old).await
+            }
+            .await;
+
+            shared.unlock(&self.board.name).await?;
+            return res;
+        }
+
+        self.cycle(report, records).await
+    }
+
+    async fn cycle(
+        &mut self,
+        report: &mut CycleReport,
+        records: &mut Vec<Record>,
+    ) -> io::Result<()> {
+        // catch the sent log up on anything that couldn't be written
+        while let Some(sent) = self.delivery.pending.first() {
+            let res = self.delivery.sent.append(sent).await;
+            if self.health.stored("the sent log", res).await.is_none() {
+                break;
+            }
+            self.delivery.pending.remove(0);
+        }
+
+        // nothing goes out during quiet hours, and whatever was held
+        // goes out together once they're over
+        let at = now().as_secs();
+        let quiet = self.board.quiet_hours.is_some_and(|q| q.at(at));
+        let due = match quiet {
+            true => Vec::new(),
+            false => self.delivery.outbox.due(at),
+        };
+        let (mut held, due): (Vec<_>, Vec<_>) = due.into_iter().partition(|q| q.held);
+        held.sort_by(|a, b| a.route.cmp(&b.route));
+        let held: Vec<_> = held.iter().collect();
+        for batch in batched(&held) {
+            report.sends_attempted += batch.len();
+            if let Ok(sent) = self
+                .delivery
+                .deliver(&self.board.name, &mut self.health, &held[batch])
+                .await
+            {
+                report.sends_succeeded += sent.len();
+            }
+        }
+
+        // retry whatever didn't go out before
+        for queued in &due {
+            report.sends_attempted += 1;
+            if self
+                .delivery
+                .deliver(&self.board.name, &mut self.health, &[queued])
+                .await
+                .is_ok()
+            {
+                report.sends_succeeded += 1;
+            }
+        }
+        if !due.is_empty() || !held.is_empty() {
+            let res = self.delivery.outbox.save().await;
+            self.health.stored("the outbox", res).await;
+        }
+
+        // create a new Leaderboard object from the board's source
+        let start = Instant::now();
+        let outcome = match self.next.take() {
+            Some(new) => FetchOutcome::Board(new),
+            None => self.board.source.fetch().await?,
+        };
+        report.timings.fetch = start.elapsed().as_millis() as u64;
+
+        let new = match outcome {
+            FetchOutcome::Board(new) => new,
+            // nothing to worry about, try again next time
+            FetchOutcome::Maintenance => {
+                info!(board = %self.board.name, "site is down for maintenance");
+                report.fetched = Fetched::Maintenance;
+                return Ok(());
+            }
+            // this one probably needs a fix
+            FetchOutcome::Unparseable { body } => {
+                report.fetched = Fetched::Unparseable;
+
+                let Some(dir) = &self.config.debug_dir else {
+                    warn!(board = %self.board.name, "couldn't parse the leaderboard");
+                    return Ok(());
+                };
+                match save_page(dir, &self.board.name, &body).await {
+                    Ok(page) => warn!(
+                        board = %self.board.name,
+                        page = %page.display(),
+                        "couldn't parse the leaderboard",
+                    ),
+                    Err(e) => warn!(
+                        board = %self.board.name,
+                        error = %e,
+                        "couldn't parse the leaderboard, or save the page",
+                    ),
+                }
+                return Ok(());
+            }
+        };
+        report.rows = new.len();
+
+        // don't let a half rendered page become the baseline
+        if self.board.validation != Strictness::Off {
+            let problems = new.validate();
+            for problem in &problems {
+                warn!(board = %self.board.name, %problem, "leaderboard looks wrong");
+            }
+            if !problems.is_empty() && self.board.validation == Strictness::Reject {
+                report.fetched = Fetched::Rejected;
+                return Ok(());
+            }
+        }
+
+        // a board that suddenly got a lot shorter or longer is usually
+        // the site acting up, pbs only get diffed where both boards overlap
+        let expected = self.board.expected_size.unwrap_or(self.old.len());
+        let off = new.len().abs_diff(expected) as f64;
+        if off > expected as f64 * self.board.size_tolerance {
+            warn!(
+                board = %self.board.name,
+                expected,
+                got = new.len(),
+                "leaderboard size changed",
+            );
+        }
+
+        // keep everything that made it past validation
+        if let Some(archive) = &mut self.archive {
+            let res = archive.save(&self.board.name, &new).await;
+            self.health.stored("the archive", res).await;
+        }
+        if let Some(bucket) = &self.bucket {
+            let res = bucket.save_snapshot(&self.board.name, &new).await;
+            self.health.stored("the bucket", res).await;
+        }
+        // even when nothing happened, so it's clear how fresh it is
+        if let Some(live) = &self.live {
+            live.set(&self.board.name, &new);
+        }
+
+        {
+            // get all pbs (difference of old to new)
+            let start = Instant::now();
+            let pbs = self.old.events_indexed(&self.index, &new, &self.board.diff);
+            report.timings.diff = start.elapsed().as_millis() as u64;
+
+            for pb in &pbs {
+                *report.events.entry(pb.kind()).or_default() += 1;
+            }
+            if let Some(sink) = &self.sink {
+                let res = sink
+                    .write(&self.board.name, new.timestamp.as_secs(), &pbs)
+                    .await;
+                self.health.stored("the event sink", res).await;
+            }
+
+            // after a long time away, everything that happened in between
+            // goes out as one message instead of a flood of them
+            let restarted = std::mem::take(&mut self.restarted);
+            let stale = stale_gap(restarted, &self.old, &new, self.config.stale_after);
+
+            if pbs.is_empty() {
+                info!(board = %self.board.name, "nothing to do");
+                return Ok(());
+            }
+
+            let start = Instant::now();
+            let ctx = RenderCtx::new(&self.board.name);
+            let dedup_window = Duration::from_secs(self.config.dedup_window);
+            // without the log nothing gets deduplicated, but
+            // that's better than not sending anything
+            let recent = self
+                .delivery
+                .sent
+                .recent(&self.board.name, dedup_window)
+                .await;
+            let mut recent = self
+                .health
+                .stored("the sent log", recent)
+                .await
+                .unwrap_or_default();
+            for sent in &self.delivery.pending {
+                recent.add(&sent.event_id, sent.user_id, sent.run_id);
+            }
+            for queued in self.delivery.outbox.queued() {
+                recent.add(&queued.event_id, queued.user_id, queued.run_id);
+            }
+
+            let notifier = |route: Option<&str>| self.delivery.notifier(route).clone();
+            let decider = Decider {
+                board: &self.board,
+                mentions: &self.config.mentions,
+                ctx: &ctx,
+                recent: &recent,
+                notifier: &notifier,
+                operator: self.health.operator.as_deref(),
+                stale: stale.is_some(),
+                hold_until: self.board.quiet_hours.filter(|_| quiet).map(|q| q.end(at)),
+            };
+
+            let mut caught_up = Vec::new();
+            let mut outgoing = Vec::new();
+            for pb in &pbs {
+                let event = |filter, targets, decision| {
+                    Record::event(&self.board.name, self.cycle, pb, filter, targets, decision)
+                };
+                // a broken script shouldn't stop anything from going out
+                let script = || {
+                    #[cfg(feature = "script")]
+                    if let Some(script) = &self.script {
+                        match script.run(pb, &new, &self.board.name) {
+                            Ok(outcome) => return Some(outcome),
+                            Err(e) => {
+                                warn!(board = %self.board.name, error = %e, "the script failed")
+                            }
+                        }
+                    }
+                    None
+                };
+
+                let verdict = match decider.screen(pb, script) {
+                    Ok(ruled) => {
+                        let extra = self.extra_lines(pb, &new).await;
+                        decider.decide(pb, ruled, &extra)
+                    }
+                    Err(verdict) => verdict,
+                };
+                match verdict {
+                    Verdict::Filtered => {
+                        report.filtered += 1;
+                        records.push(event(false, vec![], Decision::Filtered));
+                    }
+                    Verdict::Suppressed => {
+                        report.suppressed += 1;
+                        records.push(event(true, vec![], Decision::Suppressed));
+                    }
+                    Verdict::Ruled => {
+                        report.filtered += 1;
+                        records.push(event(true, vec![], Decision::Rule));
+                    }
+                    Verdict::CaughtUp => {
+                        caught_up.push(Emitted::new(&self.board.name, new.timestamp, pb));
+                        records.push(event(true, vec![], Decision::CaughtUp));
+                    }
+                    Verdict::Digest => records.push(event(true, vec![], Decision::Digest)),
+                    // there's no outbox for the operator channel, same
+                    // as for any other alert
+                    Verdict::Operator { message, pings } => {
+                        let Some(operator) = &self.health.operator else {
+                            continue;
+                        };
+                        report.sends_attempted += 1;
+                        let message = RenderedMessage::Text(message);
+                        let decision = match operator.send_pinging(&message, &pings).await {
+                            Ok(message_id) => {
+                                report.sends_succeeded += 1;
+                                Decision::Sent {
+                                    event_id: event_id(&self.board.name, pb),
+                                    message_id,
+                                }
+                            }
+                            Err(e) => {
+                                warn!(
+                                    board = %self.board.name,
+                                    error = %e,
+                                    "couldn't send a removed score to the operator channel",
+                                );
+                                Decision::Failed {
+                                    error: e.to_string(),
+                                }
+                            }
+                        };
+                        records.push(event(true, vec!["operator".to_string()], decision));
+                    }
+                    #[allow(unused_mut)]
+                    Verdict::Queue(mut queued) => {
+                        #[cfg(feature = "graphs")]
+                        if let (true, Some(store)) = (self.board.graphs, &self.store) {
+                            if queued.iter().any(|q| q.embed.is_some()) {
+                                let board = &self.board.name;
+                                let annotations = Annotations::new(&self.config.annotations);
+                                let at = new.timestamp.as_secs();
+                                match progression(store, &annotations, board, pb, at).await {
+                                    Ok(image) => {
+                                        for q in &mut queued {
+                                            q.image.clone_from(&image);
+                                        }
+                                    }
+                                    Err(e) => warn!(board, error = %e, "couldn't draw a graph"),
+                                }
+                            }
+                        }
+                        outgoing.extend(queued.into_iter().map(|q| (pb, q)));
+                    }
+                }
+            }
+            let summary = stale.and_then(|gap| {
+                let summary = digest::catch_up(&self.board.name, gap, &caught_up)?;
+                Some((gap, summary))
+            });
+            if let Some((gap, summary)) = summary {
+                warn!(board = %self.board.name, gap, "the baseline was stale, sending a summary");
+                report.sends_attempted += 1;
+                // there's no outbox for this, it's a summary of things
+                // that are old news anyway
+                match self.delivery.hook.send_text(&summary).await {
+                    Ok(_) => report.sends_succeeded += 1,
+                    Err(e) => {
+                        warn!(board = %self.board.name, error = %e, "couldn't send the summary")
+                    }
+                }
+            }
+            // batches lead with the biggest news
+            if self.board.batch {
+                outgoing.sort_by_key(|o| (o.1.route.clone(), o.0.kind()));
+            }
+
+            // the messages are on disk before anything is sent, so the
+            // baseline can move on even if sending fails. without the
+            // outbox, it has to stay put for the failed ones to come
+            // up again next cycle
+            let res = self
+                .delivery
+                .outbox
+                .push(outgoing.iter().map(|o| o.1.clone()))
+                .await;
+            let durable = self.health.stored("the outbox", res).await.is_some();
+
+            // held ones have to be on disk to be held, otherwise
+            // they go out now like any other time
+            let hold = quiet && durable;
+            if hold {
+                for (pb, queued) in &outgoing {
+                    let route = queued.route.as_ref().unwrap_or(&self.board.name);
+                    records.push(Record::event(
+                        &self.board.name,
+                        self.cycle,
+                        pb,
+                        true,
+                        vec![route.clone()],
+                        Decision::Held,
+                    ));
+                }
+            }
+
+            // send each pb to the webhook, or as few messages as they
+            // fit in with `batch`, and remember the message so it can
+            // be corrected later
+            let batches = match (hold, self.board.batch) {
+                (true, _) => Vec::new(),
+                (false, true) => batched(&outgoing.iter().map(|o| &o.1).collect::<Vec<_>>()),
+                (false, false) => (0..outgoing.len()).map(|i| i..i + 1).collect(),
+            };
+            let mut failed = false;
+            for batch in batches {
+                let batch = &outgoing[batch];
+                report.sends_attempted += batch.len();
+                let queued: Vec<_> = batch.iter().map(|o| &o.1).collect();
+                let decisions: Vec<_> = match self
+                    .delivery
+                    .deliver(&self.board.name, &mut self.health, &queued)
+                    .await
+                {
+                    Ok(sent) => {
+                        report.sends_succeeded += sent.len();
+                        sent.into_iter()
+                            .map(|sent| Decision::Sent {
+                                event_id: sent.event_id,
+                                message_id: sent.message_id,
+                            })
+                            .collect()
+                    }
+                    Err(e) => {
+                        failed = true;
+                        let error = e.to_string();
+                        batch
+                            .iter()
+                            .map(|_| Decision::Failed {
+                                error: error.clone(),
+                            })
+                            .collect()
+                    }
+                };
+                for ((pb, queued), decision) in batch.iter().zip(decisions) {
+                    let route = queued.route.as_ref().unwrap_or(&self.board.name);
+                    let targets = vec![route.clone()];
+                    records.push(Record::event(
+                        &self.board.name,
+                        self.cycle,
+                        pb,
+                        true,
+                        targets,
+                        decision,
+                    ));
+                }
+            }
+            if durable {
+                let res = self.delivery.outbox.save().await;
+                self.health.stored("the outbox", res).await;
+            }
+            report.timings.notify = start.elapsed().as_millis() as u64;
+
+            // keep copies of the runs, in case they get deleted
+            if let Some(replays) = &self.board.replays {
+                for pb in &pbs {
+                    match replays.download(pb.entry().run_id).await {
+                        Ok(Some(path)) => {
+                            info!(board = %self.board.name, path = %path.display(), "archived replay")
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!(
+                            board = %self.board.name,
+                            run_id = pb.entry().run_id,
+                            error = %e,
+                            "couldn't archive replay",
+                        ),
+                    }
+                }
+            }
+
+            // keep the old baseline, so the failed ones come up again
+            // next cycle. the ones that did go out are in the sent log
+            if failed && !durable {
+                return Ok(());
+            }
+
+            // only the changes go to disk now, the whole
+            // board gets written every so often
+            // if that fails, the cache gets written as soon as possible
+            // instead, since the journal is missing changes now
+            if !self.must_persist && !self.config.memory_only {
+                let changed: Vec<_> = pbs.iter().map(|pb| pb.entry()).collect();
+                let res = self.journal.append(new.timestamp, changed).await;
+                self.must_persist = self.health.stored("the journal", res).await.is_none();
+            }
+
+            // events queue up while the store is down, the
+            // boards from in between are just lost
+            if let Some(store) = &self.store {
+                let events = pbs
+                    .iter()
+                    .map(|pb| Emitted::new(&self.board.name, new.timestamp, pb));
+                self.history.extend(events);
+                let res = store.save(&self.board.name, &new, &self.history).await;
+                if self.health.stored("the store", res).await.is_some() {
+                    self.history.clear();
+                }
+            }
+        };
+
+        // `old` is what gets diffed against, so the bot keeps working
+        // off of memory if the disk goes away
+        self.index.update(&self.old, &new);
+        self.old = new;
+        self.dirty = true;
+
+        // the pinned message follows whoever's on top
+        if let Some(pinned) = &mut self.pinned {
+            if let Err(e) = pinned.update(&self.old, &self.board.name).await {
+                warn!(board = %self.board.name, error = %e, "couldn't update the pinned record");
+            }
+        }
+
+        let start = Instant::now();
+        let interval = Duration::from_secs(self.config.persist_interval);
+        if self.must_persist || self.last_persist.elapsed() >= interval {
+            // keep trying every cycle until it works
+            let res = self.persist().await;
+            self.must_persist = self.health.stored("the cache", res).await.is_none();
+        }
+        report.timings.persist = start.elapsed().as_millis() as u64;
+
+        Ok(())
+    }
+
+    /// lines that go under a pb: how it stands against their rivals,
+    /// if they're on a streak, and how long the record it broke
+    /// stood. none of it is worth holding the pb up for
+    async fn extra_lines(&self, pb: &Event<'_>, new: &Leaderboard) -> String {
+        let board = &self.board.name;
+        let at = new.timestamp.as_secs();
+        let mut lines = Vec::new();
+
+        if pb.kind().is_pb() && !self.board.rivals.is_empty() {
+            let rivals = &self.board.rivals;
+            match rivals::standings(rivals, pb, new, self.store.as_ref(), board).await {
+                Ok(standings) => lines.extend(standings.iter().map(|s| s.line())),
+                Err(e) => warn!(board, error = %e, "couldn't look up rivals"),
+            }
+        }
+
+        // whose history says something about it
+        let holder = self.old.entries.first().filter(|e| e.rank == 1);
+        let whose = match pb.kind() {
+            EventKind::Pb if self.board.streaks => Some(pb.entry()),
+            EventKind::WorldRecord => holder,
+            _ => None,
+        };
+        if let (Some(store), Some(whose)) = (&self.store, whose) {
+            let events = store.events_of(User::Id(whose.user_id), Some(board));
+            match events.await.map(History::new) {
+                Ok(history) => lines.extend(match pb.kind() {
+                    EventKind::Pb => history.streak(board, at).map(|s| s.line()),
+                    _ => history
+                        .reign(board, whose, pb.entry(), at)
+                        .map(|r| r.line()),
+                }),
+                Err(e) => warn!(board, error = %e, "couldn't look up their history"),
+            }
+        }
+
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+}
+
+/// what happens to an event, see `Decider`
+#[derive(Debug)]
+enum Verdict {
+    /// the board's filter doesn't want it
+    Filtered,
+    /// it went out not long ago
+    Suppressed,
+    /// a rule or the script said not to send it
+    Ruled,
+    /// it goes in the catch-up summary instead
+    CaughtUp,
+    /// it goes in the digest instead
+    Digest,
+    /// a removed score, for the operator channel
+    Operator { message: String, pings: Pings },
+    /// it goes in the outbox, with a copy for every target that wants it
+    Queue(Vec<Queued>),
+}
+
+/// decides what happens to each event in a cycle, without sending
+/// or looking anything up
+struct Decider<'a> {
+    board: &'a BoardConfig,
+    mentions: &'a Mentions,
+    ctx: &'a RenderCtx,
+    /// what was sent or queued not long ago
+    recent: &'a Recent,
+    /// the board's webhook, or a route's or target's
+    notifier: &'a (dyn Fn(Option<&str>) -> Arc<dyn Notifier> + Sync),
+    operator: Option<&'a dyn Notifier>,
+    /// the baseline was stale, so everything goes in a summary
+    stale: bool,
+    /// when held events can go out, if it's quiet hours
+    hold_until: Option<u64>,
+}
+
+impl Decider<'_> {
+    /// the filter, dedup and rules, and then `script`. what they
+    /// decided if it's still going out, or why it isn't
+    fn screen(
+        &self,
+        pb: &Event,
+        script: impl FnOnce() -> Option<rules::Outcome>,
+    ) -> Result<rules::Outcome, Verdict> {
+        if !self.board.filter.matches(pb) {
+            return Err(Verdict::Filtered);
+        }
+        // the run flapped off the board and back on
+        if self.recent.contains(&self.board.name, pb) {
+            return Err(Verdict::Suppressed);
+        }
+
+        let mut ruled = rules::apply(&self.board.rules, pb);
+        if let Some(outcome) = script() {
+            ruled.merge(outcome);
+        }
+        if ruled.suppress {
+            return Err(Verdict::Ruled);
+        }
+        if self.stale {
+            return Err(Verdict::CaughtUp);
+        }
+        Ok(ruled)
+    }
+
+    /// where an event that made it through `screen` goes, and what
+    /// it says there. `extra` lines go under the message
+    fn decide(&self, pb: &Event, mut ruled: rules::Outcome, extra: &str) -> Verdict {
+        let scripted = ruled.message.is_some();
+        // rendered messages end in a newline, so the rest lines up
+        let script_message = ruled.message.take().map(|m| format!("{}\n", m.trim_end()));
+
+        // only what's pinged here can ping anyone, not
+        // whatever's in someone's name
+        let mut allowed = Pings::parse(&ruled.pings);
+        // people who want to hear about records, and only those
+        if let (EventKind::WorldRecord, Some(role)) = (pb.kind(), self.board.record_role) {
+            ruled.pings.push(format!("<@&{}>", role));
+            allowed.add_role(Some(role));
+        }
+        // whoever it's about gets pinged first
+        if let Some(user) = self.mentions.user(pb) {
+            ruled.pings.insert(0, format!("<@{}>", user));
+            allowed.add_user(Some(user));
+        }
+        let pings = ruled.pings.join(" ");
+        // every backend gets the text in its own format
+        let text = |notifier: &dyn Notifier| {
+            let format = notifier.format();
+            let rendered = match &script_message {
+                Some(message) => message.clone(),
+                None => self.board.templates.render_in(pb, self.ctx, format),
+            };
+            let message = format!("{}{}", rendered, format.lines(extra));
+            match pings.is_empty() {
+                true => message,
+                false => format!("{} {}", pings, message),
+            }
+        };
+
+        // removed scores are for the moderators, so they go to
+        // the operator channel if there is one
+        if let (EventKind::ScoreRemoved, Some(operator)) = (pb.kind(), self.operator) {
+            return Verdict::Operator {
+                message: text(operator),
+                pings: allowed,
+            };
+        }
+        // it'll be in the digest instead, out of the store
+        if self.board.digest.as_ref().is_some_and(|d| d.instead) {
+            return Verdict::Digest;
+        }
+
+        let route = match &ruled.send_to {
+            Some(route) => Some(route.as_str()),
+            None => self.board.route(pb).map(|r| r.name.as_str()),
+        };
+        // the text is still what goes out in batches
+        let embed = |notifier: &dyn Notifier| {
+            let rich = self.board.embeds && !scripted;
+            (rich || notifier.structured())
+                .then(|| notifier.payload(pb, self.ctx, &pings, extra, &text(notifier)))
+        };
+        let notifier = (self.notifier)(route);
+        let mut queued = Queued::new(&self.board.name, pb, text(notifier.as_ref()), route);
+        queued.pings = allowed;
+        if let Some(until) = self.hold_until {
+            queued.held = true;
+            queued.retry_at = until;
+        }
+        queued.embed = embed(notifier.as_ref());
+
+        // copies for the other targets have their own ids, so
+        // they can be corrected on their own
+        let mut all = Vec::new();
+        for target in &self.board.targets {
+            let notifier = (self.notifier)(Some(&target.name));
+            if target.filter.matches(pb) && notifier.wants(pb) {
+                let mut copy = queued.clone();
+                copy.event_id = format!("{}@{}", queued.event_id, target.name);
+                copy.route = Some(target.name.clone());
+                copy.message = text(notifier.as_ref());
+                copy.embed = embed(notifier.as_ref());
+                all.push(copy);
+            }
+        }
+        all.push(queued);
+        Verdict::Queue(all)
+    }
+}
+
+/// what it takes to get messages out, and keep track of them
+struct Delivery {
+    hook: Arc<dyn Notifier>,
+    /// webhooks of the board's `config::Route`s and `config::Target`s,
+    /// by name
+    routes: HashMap<String, Arc<dyn Notifier>>,
+    sent: Arc<SentLog>,
+    /// sent log records that couldn't be written yet
+    pending: Vec<Sent>,
+    /// messages that still have to go out
+    outbox: Outbox,
+}
+
+impl Delivery {
+    /// where messages for a route go, the board's own webhook
+    /// for none
+    fn notifier(&self, route: Option<&str>) -> &Arc<dyn Notifier> {
+        route.and_then(|r| self.routes.get(r)).unwrap_or(&self.hook)
+    }
+
+    /// send queued messages, all together as one, and write
+    /// them to the sent log
+    ///
+    /// they leave the outbox once they're out, or get put off
+    /// for a while if they couldn't be sent
+    async fn deliver(
+        &mut self,
+        board: &str,
+        health: &mut Health,
+        queued: &[&Queued],
+    ) -> io::Result<Vec<Sent>> {
+        let messages: Vec<_> = queued.iter().map(|q| q.message.as_str()).collect();
+        let text = RenderedMessage::Text(messages.join("\n"));
+        let mut pings = Pings::default();
+        for q in queued {
+            pings.merge(&q.pings);
+        }
+        // they're all for the same webhook
+        let route = queued.first().and_then(|q| q.route.as_deref());
+        let hook = self.notifier(route);
+        let res = match queued {
+            [Queued {
+                embed: Some(embed),
+                event_id,
+                image,
+                ..
+            }] => {
+                let embed = RenderedMessage::Json(embed.clone());
+                let res = match image {
+                    Some(png) => hook.send_with_image(&embed, png, &pings).await,
+                    None => hook.send_pinging(&embed, &pings).await,
+                };
+                match res {
+                    Ok(id) => Ok(id),
+                    // text gets through where an embed might not
+                    Err(e) => {
+                        warn!(
+                            board,
+                            event = %event_id,
+                            error = %e,
+                            "couldn't send the embed, sending text",
+                        );
+                        hook.send_pinging(&text, &pings).await
+                    }
+                }
+            }
+            _ => hook.send_pinging(&text, &pings).await,
+        };
+        let message_id = match res {
+            Ok(id) => id,
+            Err(e) => {
+                for queued in queued {
+                    warn!(
+                        board,
+                        event = %queued.event_id,
+                        attempts = queued.attempts + 1,
+                        error = %e,
+                        "couldn't send",
+                    );
+                    self.outbox.failed(&queued.event_id, now().as_secs());
+                }
+                return Err(e);
+            }
+        };
+
+        let mut sent = Vec::new();
+        for queued in queued {
+            self.outbox.done(&queued.event_id);
+
+            let record = queued.sent(board, message_id, now().as_secs());
+            // hold onto it until the log can be written
+            if !self.pending.is_empty() {
+                self.pending.push(record.clone());
+            } else {
+                let res = self.sent.append(&record).await;
+                if health.stored("the sent log", res).await.is_none() {
+                    self.pending.push(record.clone());
+                }
+            }
+            info!(board, event = %record.event_id, "sent");
+            sent.push(record);
+        }
+
+        Ok(sent)
+    }
+}
+
+/// keeps track of whether writing to disk works
+struct Health {
+    board: String,
+    /// gets told when writing to disk starts or stops failing
+    operator: Option<Arc<dyn Notifier>>,
+    /// writing to disk failed, and nothing has worked since
+    degraded: bool,
+    /// something failed during the current cycle
+    failed: bool,
+}
+
+impl Health {
+    /// see if writing something to disk worked
+    ///
+    /// notifications matter more than anything on disk, so failing to
+    /// write doesn't stop the cycle. the operator gets told the first
+    /// time it happens.
+    async fn stored<T>(&mut self, what: &str, res: io::Result<T>) -> Option<T> {
+        let e = match res {
+            Ok(t) => return Some(t),
+            Err(e) => e,
+        };
+        self.failed = true;
+
+        warn!(board = %self.board, error = %e, "couldn't use {}, carrying on without it", what);
+        if !self.degraded {
+            self.degraded = true;
+            self.alert(&format!(
+                "[{}] couldn't use {}, carrying on without it: {}",
+                self.board, what, e
+            ))
+            .await;
+        }
+
+        None
+    }
+
+    /// let the operator know once everything is back on disk
+    async fn recovered(&mut self) {
+        if !self.degraded {
+            return;
+        }
+
+        self.degraded = false;
+        info!(board = %self.board, "writing to disk works again");
+        self.alert(&format!("[{}] writing to disk works again", self.board))
+            .await;
+    }
+
+    async fn alert(&self, message: &str) {
+        let Some(operator) = &self.operator else {
+            return;
+        };
+        if let Err(e) = operator.send_text(message).await {
+            warn!(board = %self.board, error = %e, "couldn't alert the operator channel");
+        }
+    }
+}
+
+/// a board from a minute ago and one from now, with a world record,
+/// the lowest milestone, and a pb under it in between
+fn rehearsal(options: &DiffOptions) -> (Leaderboard, Leaderboard) {
+    let milestone = options.milestones.iter().map(|m| m.score).reduce(f32::min);
+    let milestone = milestone.unwrap_or(400.0);
+    // ids no real player has
+    let entry = |rank, id, run, score| Entry {
+        rank,
+        name: format!("hdget test {}", id),
+        user_id: u32::MAX - id,
+        run_id: u32::MAX - run,
+        score,
+    };
+    let now = now();
+    let old = Leaderboard {
+        timestamp: now.saturating_sub(Duration::from_secs(60)),
+        entries: vec![
+            entry(1, 1, 1, milestone + 100.0),
+            entry(2, 2, 2, milestone - 5.0),
+            entry(3, 3, 3, milestone - 50.0),
+        ],
+    };
+    let new = Leaderboard {
+        timestamp: now,
+        entries: vec![
+            entry(1, 1, 4, milestone + 110.0),
+            entry(2, 2, 5, milestone + 5.0),
+            entry(3, 3, 6, milestone - 45.0),
+        ],
+    };
+    (old, new)
+}
+
+/// how long ago the baseline was, if it's old enough that what
+/// changed since should go out as one summary
+///
+/// only the first board after starting up can be stale, a board
+/// that's just been quiet for a while has its pbs sent as usual
+fn stale_gap(
+    restarted: bool,
+    old: &Leaderboard,
+    new: &Leaderboard,
+    stale_after: u64,
+) -> Option<u64> {
+    let gap = new.timestamp.saturating_sub(old.timestamp).as_secs();
+    (restarted && stale_after > 0 && gap > stale_after).then_some(gap)
+}
+
+/// which of the messages go out together, in as few messages as
+/// they fit in. a batch can only go to one webhook
+fn batched(queued: &[&Queued]) -> Vec<Range<usize>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    for group in queued.chunk_by(|a, b| a.route == b.route) {
+        let messages: Vec<_> = group.iter().map(|q| &q.message).collect();
+        let batch = hook::batch(&messages).into_iter();
+        batches.extend(batch.map(|b| b.start + start..b.end + start));
+        start += group.len();
+    }
+    batches
+}
+
+/// a graph of someone's pbs up to `pb`, if there's more than
+/// one to draw, with the annotations from in between marked
+#[cfg(feature = "graphs")]
+async fn progression(
+    store: &Store,
+    annotations: &Annotations,
+    board: &str,
+    pb: &crate::lb::Event<'_>,
+    timestamp: u64,
+) -> io::Result<Option<Vec<u8>>> {
+    if !pb.kind().is_pb() {
+        return Ok(None);
+    }
+    let events = store
+        .events_of(User::Id(pb.entry().user_id), Some(board))
+        .await?;
+    let mut points: Vec<_> = events
+        .into_iter()
+        .filter(|e| e.kind.is_pb())
+        .map(|e| (e.timestamp, e.score))
+        .collect();
+    points.push((timestamp, pb.entry().score));
+    if points.len() < 2 {
+        return Ok(None);
+    }
+    // a graph is still worth sending without them
+    let markers: Vec<_> = annotations
+        .between(points[0].0, timestamp + 1)
+        .await
+        .inspect_err(|e| warn!(board, error = %e, "couldn't read the annotations"))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|a| a.at)
+        .collect();
+    graph::progression(&points, &markers).map(Some)
+}
+
+/// a baseline to start from when there's no cache
+///
+/// the last board in the store or the bucket, or a fresh one
+async fn initial(
+    board: &BoardConfig,
+    store: Option<&Store>,
+    bucket: Option<&Bucket>,
+) -> io::Result<Leaderboard> {
+    if let Some(lb) = latest(board, store, bucket).await {
+        return Ok(lb);
+    }
+
+    match board.source.fetch().await? {
+        FetchOutcome::Board(lb) => Ok(lb),
+        _ => Err(io::Error::other(format!(
+            "couldn't fetch an initial leaderboard for {}",
+            board.name
+        ))),
+    }
+}
+
+/// the last board in the store or the bucket, if either has one
+async fn latest(
+    board: &BoardConfig,
+    store: Option<&Store>,
+    bucket: Option<&Bucket>,
+) -> Option<Leaderboard> {
+    if let Some(store) = store {
+        match store.latest(&board.name).await {
+            Ok(Some(lb)) => {
+                info!(board = %board.name, "using the last leaderboard in the store");
+                return Some(lb);
+            }
+            Ok(None) => {}
+            Err(e) => warn!(board = %board.name, error = %e, "couldn't read the store"),
+        }
+    }
+
+    match bucket?.latest(&board.name).await {
+        Ok(Some(lb)) => {
+            info!(board = %board.name, "using the last leaderboard in the bucket");
+            Some(lb)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!(board = %board.name, error = %e, "couldn't read the bucket");
+            None
+        }
+    }
+}
+
+/// keep a copy of a page we couldn't parse
+///
+/// makes it easy to see what the site changed
+async fn save_page(dir: &Path, board: &str, body: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir).await?;
+    let path = dir.join(format!("{}-{}.html", board, now().as_secs()));
+    fs::write(&path, body).await?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::schedule::BoxFuture;
+
+    /// a webhook that says which one it is in its payloads
+    struct Stub {
+        via: String,
+        structured: bool,
+    }
+
+    impl Notifier for Stub {
+        fn send<'a>(&'a self, _message: &'a RenderedMessage) -> BoxFuture<'a, io::Result<u64>> {
+            Box::pin(async { Ok(1) })
+        }
+
+        fn payload(&self, _: &Event, _: &RenderCtx, _: &str, _: &str, text: &str) -> Value {
+            json!({ "via": self.via, "text": text })
+        }
+
+        fn structured(&self) -> bool {
+            self.structured
+        }
+    }
+
+    fn board() -> BoardConfig {
+        toml::from_str(
+            r#"
+            name = "main"
+            webhook = ""
+            record_role = 7
+            routes = [{ name = "records", min_severity = "milestone", webhook = "" }]
+            targets = [{ name = "fans", webhook = "", filter = { watchlist = ["possm"] } }]
+            rules = [{ when = { players = ["possm"] }, then = { ping = "<@&9>" } }]
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn entry(rank: u16, name: &str, user_id: u32, run_id: u32, score: f32) -> Entry {
+        Entry {
+            rank,
+            name: name.to_string(),
+            user_id,
+            run_id,
+            score,
+        }
+    }
+
+    /// decides on `pb` the way a cycle would, with no script
+    fn verdict(decider: &Decider, pb: &Event) -> Verdict {
+        match decider.screen(pb, || None) {
+            Ok(ruled) => decider.decide(pb, ruled, "Held it for 3 days\n"),
+            Err(verdict) => verdict,
+        }
+    }
+
+    #[test]
+    fn test_decide() {
+        let board = board();
+        let mentions: Mentions = toml::from_str("users = [[1, 55]]").unwrap();
+        let ctx = RenderCtx::new("main");
+        let notifier = |route: Option<&str>| -> Arc<dyn Notifier> {
+            Arc::new(Stub {
+                via: route.unwrap_or("main").to_string(),
+                structured: route == Some("fans"),
+            })
+        };
+        let recent = Recent::default();
+        let decider = Decider {
+            board: &board,
+            mentions: &mentions,
+            ctx: &ctx,
+            recent: &recent,
+            notifier: &notifier,
+            operator: None,
+            stale: false,
+            hold_until: None,
+        };
+
+        // a record goes to its route and the target, pinging the
+        // player, the role and whoever the rule says
+        let (old, new) = (
+            entry(2, "possm", 1, 1, 399.0),
+            entry(1, "possm", 1, 2, 410.0),
+        );
+        let wr = Event::WorldRecord {
+            old: &old,
+            new: &new,
+        };
+        let Verdict::Queue(queued) = verdict(&decider, &wr) else {
+            panic!("a record should be queued");
+        };
+        let [copy, queued] = &queued[..] else {
+            panic!("a record should go to the target too");
+        };
+        let id = event_id("main", &wr);
+        assert_eq!(queued.event_id, id);
+        assert_eq!(queued.route.as_deref(), Some("records"));
+        assert!(queued.message.starts_with("<@55> <@&9> <@&7> "));
+        assert!(queued.message.ends_with("Held it for 3 days\n"));
+        assert_eq!(queued.pings.users, [55]);
+        assert_eq!(queued.pings.roles, [9, 7]);
+        assert_eq!(queued.embed, None);
+        assert!(!queued.held);
+        assert_eq!(copy.event_id, format!("{}@fans", id));
+        assert_eq!(copy.route.as_deref(), Some("fans"));
+        assert_eq!(copy.message, queued.message);
+        assert_eq!(copy.pings, queued.pings);
+        assert_eq!(
+            copy.embed,
+            Some(json!({ "via": "fans", "text": queued.message }))
+        );
+
+        // somebody else's pb stays on the board's webhook, without
+        // the target or anyone to ping
+        let (old, new) = (
+            entry(5, "fenne", 2, 3, 200.0),
+            entry(4, "fenne", 2, 4, 210.0),
+        );
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+        let Verdict::Queue(queued) = verdict(&decider, &pb) else {
+            panic!("a pb should be queued");
+        };
+        let [queued] = &queued[..] else {
+            panic!("only possm goes to the target");
+        };
+        assert_eq!(queued.route, None);
+        assert!(!queued.message.starts_with('<'));
+        assert_eq!(queued.pings, Pings::default());
+
+        // held until quiet hours are over
+        let quiet = Decider {
+            hold_until: Some(1000),
+            ..decider
+        };
+        let Verdict::Queue(queued) = verdict(&quiet, &pb) else {
+            panic!("a pb should be queued");
+        };
+        assert!(queued[0].held);
+        assert_eq!(queued[0].retry_at, 1000);
+
+        // removed scores go to the operator, if there is one
+        let removed = Event::ScoreRemoved {
+            old: &new,
+            new: &old,
+        };
+        assert!(matches!(verdict(&decider, &removed), Verdict::Queue(_)));
+        let operator = Stub {
+            via: "operator".to_string(),
+            structured: false,
+        };
+        let operated = Decider {
+            operator: Some(&operator),
+            ..decider
+        };
+        assert!(matches!(
+            verdict(&operated, &removed),
+            Verdict::Operator { .. }
+        ));
+
+        // sent already, or catching up after a while away
+        let mut recent = Recent::default();
+        recent.add(&event_id("main", &pb), 2, 4);
+        let again = Decider {
+            recent: &recent,
+            ..decider
+        };
+        assert!(matches!(verdict(&again, &pb), Verdict::Suppressed));
+        let stale = Decider {
+            stale: true,
+            ..decider
+        };
+        assert!(matches!(verdict(&stale, &pb), Verdict::CaughtUp));
+    }
+
+    #[test]
+    fn test_stale_gap() {
+        let board = |timestamp| Leaderboard {
+            timestamp: Duration::from_secs(timestamp),
+            entries: Vec::new(),
+        };
+        let day = 86400;
+
+        // back after two days away
+        assert_eq!(
+            stale_gap(true, &board(0), &board(2 * day), day),
+            Some(2 * day)
+        );
+        assert_eq!(stale_gap(true, &board(0), &board(day / 2), day), None);
+        assert_eq!(stale_gap(true, &board(0), &board(2 * day), 0), None);
+        // a board nobody's set a pb on in two days isn't stale
+        assert_eq!(stale_gap(false, &board(0), &board(2 * day), day), None);
+    }
+}
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Timelike};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+/// hours of the day, from the start of `from` up to `to`. can go
+/// past midnight
+///
+/// ```toml
+/// quiet_hours = { from = 23, to = 7, timezone = "Europe/Berlin" }
+/// ```
+///
+/// the timezone is utc if there isn't one
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuietHours {
+    pub from: u32,
+    pub to: u32,
+    #[serde(default = "default_timezone")]
+    pub timezone: Tz,
+}
+
+impl QuietHours {
+    pub fn contains(&self, hour: u32) -> bool {
+        match self.from <= self.to {
+            true => self.from <= hour && hour < self.to,
+            false => self.from <= hour || hour < self.to,
+        }
+    }
+
+    /// whether it's quiet at unix time `at`
+    pub fn at(&self, at: u64) -> bool {
+        self.contains(self.hour(at))
+    }
+
+    /// unix time the quiet hours that `at` is in are over, or
+    /// `at` itself if it isn't quiet then
+    pub fn end(&self, at: u64) -> u64 {
+        if !self.at(at) {
+            return at;
+        }
+        // go an hour at a time from the start of the next one, so
+        // clocks changing and half hour timezones work out
+        let local = self.local(at);
+        let mut end = at - (local.minute() * 60 + local.second()) as u64;
+        // it can't be quiet all day without `from` and `to` being
+        // the same, and then it isn't quiet at all
+        for _ in 0..24 {
+            end += 3600;
+            if !self.at(end) {
+                break;
+            }
+        }
+        end
+    }
+
+    fn hour(&self, at: u64) -> u32 {
+        self.local(at).hour()
+    }
+
+    fn local(&self, at: u64) -> DateTime<Tz> {
+        let utc = DateTime::from_timestamp(at as i64, 0).unwrap_or_default();
+        utc.with_timezone(&self.timezone)
+    }
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quiet_hours() {
+        let night = QuietHours {
+            from: 23,
+            to: 7,
+            timezone: Tz::UTC,
+        };
+        assert!(night.contains(23));
+        assert!(night.contains(3));
+        assert!(!night.contains(7));
+        assert!(!night.contains(12));
+
+        let lunch = QuietHours {
+            from: 12,
+            to: 14,
+            timezone: Tz::UTC,
+        };
+        assert!(lunch.contains(13));
+        assert!(!lunch.contains(14));
+        assert!(!lunch.contains(3));
+
+        // 2025-10-15 02:30 utc, 04:30 in berlin
+        let at = 1760495400;
+        let berlin: QuietHours = toml::from_str(
+            r#"
+            from = 23
+            to = 7
+            timezone = "Europe/Berlin"
+            "#,
+        )
+        .unwrap();
+        assert!(berlin.at(at));
+        // 07:00 there, 05:00 utc
+        assert_eq!(berlin.end(at), 1760504400);
+        assert_eq!(lunch.end(at), at);
+
+        let india = QuietHours {
+            from: 6,
+            to: 9,
+            timezone: Tz::Asia__Kolkata,
+        };
+        // 08:00 there, so it's over an hour later
+        assert_eq!(india.end(at), at + 3600);
+    }
+}
@@ -0,0 +1,383 @@
+use chrono::DateTime;
+
+use crate::lb::{now, Event};
+use crate::render::discord::escape_markdown;
+
+pub mod card;
+pub mod discord;
+pub mod html;
+pub mod locale;
+pub mod markdown;
+pub mod slack;
+pub mod telegram;
+pub mod template;
+pub mod text;
+
+pub use card::Card;
+pub use discord::DiscordEmbed;
+pub use html::Html;
+pub use locale::Locale;
+pub use markdown::Markdown;
+pub use slack::SlackBlocks;
+pub use telegram::TelegramMarkdown;
+pub use template::Templates;
+pub use text::Text;
+
+/// turns events into something that can be posted somewhere
+///
+/// every output has its own implementation, but they all
+/// agree on what an event looks like.
+pub trait Render {
+    fn render(&self, event: &Event, ctx: &RenderCtx) -> RenderedMessage;
+}
+
+/// stuff about where an event came from
+#[derive(Debug, Clone)]
+pub struct RenderCtx {
+    /// name of the board the event is from
+    pub board: String,
+    /// unix time the event was found at
+    pub timestamp: u64,
+}
+
+impl RenderCtx {
+    pub fn new(board: &str) -> Self {
+        Self {
+            board: board.to_string(),
+            timestamp: now().as_secs(),
+        }
+    }
+
+    /// the timestamp as an rfc 3339 string
+    pub fn rfc3339(&self) -> String {
+        DateTime::from_timestamp(self.timestamp as i64, 0)
+            .unwrap_or_default()
+            .to_rfc3339()
+    }
+}
+
+/// how a backend wants its text messages, see
+/// `notify::Notifier::format`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// `Text`, for anything that shows text as is
+    #[default]
+    Plain,
+    /// `Markdown`, for discord
+    Markdown,
+    /// `Html`, for chat apps that take formatted bodies
+    Html,
+}
+
+impl Format {
+    pub fn render(self, event: &Event, ctx: &RenderCtx) -> String {
+        match self {
+            Self::Plain => Text.render(event, ctx),
+            Self::Markdown => Markdown.render(event, ctx),
+            Self::Html => Html.render(event, ctx),
+        }
+        .into_string()
+    }
+
+    /// plain text, like a name, escaped so it shows up as is
+    pub fn escape(self, s: &str) -> String {
+        match self {
+            Self::Plain => s.to_string(),
+            Self::Markdown => escape_markdown(s),
+            Self::Html => escape_html(s),
+        }
+    }
+
+    /// lines of plain text that go under a message
+    pub fn lines(self, lines: &str) -> String {
+        match self {
+            Self::Plain => lines.to_string(),
+            Self::Markdown => lines.lines().map(|l| self.escape(l) + "\n").collect(),
+            Self::Html => lines
+                .lines()
+                .map(|l| format!("<p>{}</p>\n", self.escape(l)))
+                .collect(),
+        }
+    }
+}
+
+/// the output of a Render
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderedMessage {
+    /// plain text
+    Text(String),
+    /// a json payload, like a discord embed or slack blocks
+    Json(serde_json::Value),
+    /// a fragment of html
+    Html(String),
+    /// an svg image
+    Svg(String),
+}
+
+impl RenderedMessage {
+    /// the message as a string, json gets serialized
+    pub fn into_string(self) -> String {
+        match self {
+            Self::Text(s) | Self::Html(s) | Self::Svg(s) => s,
+            Self::Json(v) => v.to_string(),
+        }
+    }
+}
+
+/// the banner shown above special events
+///
+/// milestones have their own, which can have the name in it
+pub(crate) fn banner(event: &Event) -> Option<String> {
+    let banner = match event {
+        Event::WorldRecord { .. } => "NEW WORLD RECORD",
+        Event::Milestone { new, milestone, .. } => return Some(milestone.banner_for(new)),
+        Event::RankChange { .. } => "RANK CHANGE",
+        Event::NameChanged { .. } => "NAME CHANGE",
+        Event::ScoreRemoved { .. } => "SCORE REMOVED",
+        Event::EnteredTop { top, .. } => return Some(format!("TOP {}", top)),
+        Event::CloseRace { .. } => "CLOSE RACE",
+        Event::NewPlayer { .. } => "WELCOME",
+        Event::Improvement { .. } => return None,
+    };
+    Some(banner.to_string())
+}
+
+/// what happened, for events that aren't pbs, and a welcome
+/// for new players instead of the usual pb text
+///
+/// names go through `escape`, for whatever the output needs
+pub(crate) fn headline(event: &Event, escape: impl Fn(&str) -> String) -> Option<String> {
+    match *event {
+        Event::NewPlayer {
+            new, above, below, ..
+        } => {
+            let mut s = format!(
+                "Welcome {}! Their first run puts them at #{} with {}",
+                escape(&new.name),
+                new.rank,
+                new.score
+            );
+            if let Some(top) = event.top_percent() {
+                s += &format!(", in the top {}% of the board", top);
+            }
+            match (above, below) {
+                (Some(above), Some(below)) => {
+                    s += &format!(
+                        ", between {} and {}",
+                        escape(&above.name),
+                        escape(&below.name)
+                    )
+                }
+                (Some(above), None) => s += &format!(", right behind {}", escape(&above.name)),
+                (None, Some(below)) => s += &format!(", ahead of {}", escape(&below.name)),
+                (None, None) => {}
+            }
+            Some(s)
+        }
+        Event::RankChange { old, new } => {
+            let verb = if new.rank < old.rank {
+                "climbed"
+            } else {
+                "dropped"
+            };
+            Some(format!(
+                "{} {} from #{} to #{}",
+                escape(&new.name),
+                verb,
+                old.rank,
+                new.rank
+            ))
+        }
+        Event::NameChanged { old, new } => Some(format!(
+            "{} is now going by {}",
+            escape(&old.name),
+            escape(&new.name)
+        )),
+        Event::ScoreRemoved { old, new } => Some(format!(
+            "{}'s score of {} was removed, their best is {} now",
+            escape(&new.name),
+            old.score,
+            new.score
+        )),
+        Event::EnteredTop { new, top, .. } => Some(format!(
+            "{} just broke into the top {}",
+            escape(&new.name),
+            top
+        )),
+        Event::CloseRace { new, ahead } => {
+            let behind = match ahead.rank {
+                1 => "the WR".to_string(),
+                rank => format!("{} for #{}", escape(&ahead.name), rank),
+            };
+            Some(format!(
+                "{} is now only {} behind {}",
+                escape(&new.name),
+                ahead.score - new.score,
+                behind
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// escape text for html and xml
+pub(crate) fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+
+    #[test]
+    fn test_render() {
+        let old = Entry {
+            rank: 2,
+            name: "fennekal".to_string(),
+            user_id: 2,
+            run_id: 2,
+            score: 399.0,
+        };
+        let new = Entry {
+            rank: 1,
+            name: "fennekal".to_string(),
+            user_id: 2,
+            run_id: 3,
+            score: 410.0,
+        };
+        let pb = Event::WorldRecord {
+            old: &old,
+            new: &new,
+        };
+        let ctx = RenderCtx::new("main");
+
+        assert_eq!(
+            Text.render(&pb, &ctx),
+            RenderedMessage::Text(
+                "---  NEW WORLD RECORD  ---\n\
+                 fennekal just got a new high score! Score: 410 (+11)\n\
+                 They are now rank #1, gaining 1 ranks.\n\
+                 Watch in-game: hyperdemon://run/3\n"
+                    .to_string()
+            )
+        );
+
+        let RenderedMessage::Json(embed) = DiscordEmbed.render(&pb, &ctx) else {
+            panic!("discord embeds are json");
+        };
+        assert_eq!(embed["embeds"][0]["title"], "NEW WORLD RECORD");
+        let payload = DiscordEmbed::payload(&pb, &ctx, "<@&1>", "Head to head with possm\n");
+        assert_eq!(payload["content"], "<@&1>");
+        let description = payload["embeds"][0]["description"].as_str().unwrap();
+        assert!(description.ends_with(")\nHead to head with possm"));
+
+        let blocks = SlackBlocks::payload(&pb, &ctx, "<@U1>", "Head to head with <possm>\n");
+        assert_eq!(blocks["blocks"][0]["text"]["text"], "<@U1>");
+        assert_eq!(
+            blocks["blocks"][1]["text"]["text"],
+            ":trophy: NEW WORLD RECORD"
+        );
+        assert_eq!(
+            blocks["blocks"][2]["fields"][2]["text"],
+            "*Previous best*\n399 (#2)"
+        );
+        assert_eq!(
+            blocks["blocks"][3]["text"]["text"],
+            "Head to head with &lt;possm&gt;"
+        );
+
+        let message = TelegramMarkdown::payload(&pb, &ctx, "", "");
+        assert_eq!(message["parse_mode"], "MarkdownV2");
+        assert_eq!(
+            message["text"],
+            "*NEW WORLD RECORD*\n\
+             *fennekal* just got a new high score\\! Score: *410* \\(\\+11\\)\n\
+             They are now rank \\#1\\.\n\
+             Watch in\\-game: hyperdemon://run/3\n\
+             _main_"
+        );
+
+        let RenderedMessage::Html(html) = Html.render(&pb, &ctx) else {
+            panic!("html renders html");
+        };
+        assert!(html.contains("<strong>fennekal</strong>"));
+    }
+
+    #[test]
+    fn test_formats() {
+        let entry = |rank, run_id, score| Entry {
+            rank,
+            name: "fenne_kal*".to_string(),
+            user_id: 2,
+            run_id,
+            score,
+        };
+        let (old, new) = (entry(2, 2, 399.0), entry(1, 3, 410.0));
+        let pb = Event::WorldRecord {
+            old: &old,
+            new: &new,
+        };
+        let ctx = RenderCtx::new("main");
+
+        assert_eq!(
+            Format::Markdown.render(&pb, &ctx),
+            "**NEW WORLD RECORD**\n\
+             **fenne\\_kal\\*** just got a new high score! Score: **410** (+11)\n\
+             They are now rank #1, gaining 1 ranks.\n\
+             Watch in-game: hyperdemon://run/3\n"
+        );
+        assert_eq!(
+            Format::Plain.render(&pb, &ctx),
+            Text.render(&pb, &ctx).into_string()
+        );
+        assert_eq!(Format::Markdown.lines("a_b\n"), "a\\_b\n");
+        assert_eq!(
+            Format::Html.lines("ahead of <possm>\n"),
+            "<p>ahead of &lt;possm&gt;</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_welcome() {
+        let entry = |rank: u16, name: &str| Entry {
+            rank,
+            name: name.to_string(),
+            user_id: rank as u32,
+            run_id: rank as u32,
+            score: 400.0 - rank as f32,
+        };
+        let (above, new, below) = (
+            entry(11, "fennekal"),
+            entry(12, "possm"),
+            entry(13, "hoodie"),
+        );
+        let welcome = Event::NewPlayer {
+            new: &new,
+            above: Some(&above),
+            below: Some(&below),
+            out_of: 200,
+        };
+
+        assert_eq!(welcome.top_percent(), Some(6));
+        assert_eq!(
+            Text.render(&welcome, &RenderCtx::new("main")),
+            RenderedMessage::Text(
+                "Welcome possm! Their first run puts them at #12 with 388, \
+                 in the top 6% of the board, between fennekal and hoodie.\n"
+                    .to_string()
+            )
+        );
+    }
+}
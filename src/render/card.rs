@@ -0,0 +1,49 @@
+use crate::lb::{Event, EventKind};
+use crate::render::{banner, escape_html, Render, RenderCtx, RenderedMessage};
+
+/// a small image card, as an svg
+pub struct Card;
+
+/// background color of the card
+fn background(kind: EventKind) -> &'static str {
+    match kind {
+        EventKind::WorldRecord => "#7a5c00",
+        EventKind::Milestone => "#4a235a",
+        EventKind::Pb => "#1b2631",
+        EventKind::NewPlayer => "#145a32",
+        EventKind::RankChange => "#424949",
+        EventKind::NameChanged => "#424949",
+        EventKind::ScoreRemoved => "#78281f",
+        EventKind::EnteredTop => "#784212",
+        EventKind::CloseRace => "#0e6655",
+    }
+}
+
+impl Render for Card {
+    fn render(&self, event: &Event, ctx: &RenderCtx) -> RenderedMessage {
+        let new = event.entry();
+
+        let score = match event.delta() {
+            Some(delta) => format!("{} (+{})", new.score, delta),
+            None => new.score.to_string(),
+        };
+
+        RenderedMessage::Svg(format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="600" height="200" viewBox="0 0 600 200">
+<rect width="600" height="200" rx="16" fill="{bg}"/>
+<text x="32" y="48" font-family="sans-serif" font-size="20" fill="#ffffff" opacity="0.7">{banner}</text>
+<text x="32" y="100" font-family="sans-serif" font-size="40" font-weight="bold" fill="#ffffff">{name}</text>
+<text x="32" y="150" font-family="sans-serif" font-size="28" fill="#ffffff">{score}</text>
+<text x="568" y="150" font-family="sans-serif" font-size="28" fill="#ffffff" text-anchor="end">#{rank}</text>
+<text x="568" y="180" font-family="sans-serif" font-size="14" fill="#ffffff" opacity="0.5" text-anchor="end">{board}</text>
+</svg>
+"##,
+            bg = background(event.kind()),
+            banner = escape_html(&banner(event).unwrap_or_else(|| "NEW HIGH SCORE".to_string())),
+            name = escape_html(&new.name),
+            score = score,
+            rank = new.rank,
+            board = escape_html(&ctx.board),
+        ))
+    }
+}
@@ -0,0 +1,94 @@
+use serde_json::{json, Value};
+
+use crate::lb::{Event, EventKind};
+use crate::render::{banner, headline, Render, RenderCtx, RenderedMessage};
+
+/// a discord webhook payload with an embed in it
+pub struct DiscordEmbed;
+
+/// side strip color of the embed
+fn color(kind: EventKind) -> u32 {
+    match kind {
+        EventKind::WorldRecord => 0xf1c40f,
+        EventKind::Milestone => 0x9b59b6,
+        EventKind::Pb => 0x3498db,
+        EventKind::NewPlayer => 0x2ecc71,
+        EventKind::RankChange | EventKind::NameChanged => 0x95a5a6,
+        EventKind::ScoreRemoved => 0xe74c3c,
+        EventKind::EnteredTop => 0xe67e22,
+        EventKind::CloseRace => 0x1abc9c,
+    }
+}
+
+/// escape discord markdown, names can have underscores and such in them
+pub(crate) fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '~' | '`' | '|' | '>' | '#' | '-' | '[' | ']'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+impl DiscordEmbed {
+    /// a whole webhook payload, with `content` above the embed,
+    /// like pings, and `extra` lines at the end of it
+    pub fn payload(event: &Event, ctx: &RenderCtx, content: &str, extra: &str) -> Value {
+        let RenderedMessage::Json(mut payload) = DiscordEmbed.render(event, ctx) else {
+            unreachable!("discord embeds are json");
+        };
+        if !extra.is_empty() {
+            let embed = &mut payload["embeds"][0];
+            let description = embed["description"].as_str().unwrap_or_default();
+            embed["description"] = format!("{}\n{}", description, extra.trim_end()).into();
+        }
+        if !content.is_empty() {
+            payload["content"] = content.into();
+        }
+        payload
+    }
+}
+
+impl Render for DiscordEmbed {
+    fn render(&self, event: &Event, ctx: &RenderCtx) -> RenderedMessage {
+        let new = event.entry();
+
+        let score = match event.delta() {
+            Some(delta) => format!("{} (+{})", new.score, delta),
+            None => new.score.to_string(),
+        };
+        let rank = match (event.previous(), event.ranks_gained()) {
+            (Some(old), Some(gained)) => format!("#{} (was #{}, +{})", new.rank, old.rank, gained),
+            (Some(old), None) => format!("#{} (was #{})", new.rank, old.rank),
+            (None, _) => format!("#{}", new.rank),
+        };
+
+        let description = headline(event, escape_markdown).unwrap_or_else(|| {
+            format!(
+                "{} just got a new high score!\n[Watch in-game]({})",
+                escape_markdown(&new.name),
+                event.run_link(),
+            )
+        });
+
+        RenderedMessage::Json(json!({
+            "embeds": [{
+                "author": { "name": new.name },
+                "title": banner(event).unwrap_or_else(|| "New high score!".to_string()),
+                "description": description,
+                "color": color(event.kind()),
+                "fields": [
+                    { "name": "Score", "value": score, "inline": true },
+                    { "name": "Rank", "value": rank, "inline": true },
+                ],
+                "footer": { "text": ctx.board },
+                "timestamp": ctx.rfc3339(),
+            }]
+        }))
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt::Write;
+
+use crate::lb::Event;
+use crate::render::{banner, escape_html, headline, Render, RenderCtx, RenderedMessage};
+
+/// an html fragment, for feeds and dashboards
+///
+/// classes are left for the page to style.
+pub struct Html;
+
+impl Render for Html {
+    fn render(&self, event: &Event, ctx: &RenderCtx) -> RenderedMessage {
+        let mut s = String::new();
+        let new = event.entry();
+
+        // writing to a String can't fail
+        writeln!(
+            s,
+            r#"<article class="hdget-event hdget-{:?}" data-board="{}">"#,
+            event.kind(),
+            escape_html(&ctx.board)
+        )
+        .unwrap();
+        if let Some(banner) = banner(event) {
+            writeln!(s, "<h2>{}</h2>", escape_html(&banner)).unwrap();
+        }
+        let strong = |name: &str| format!("<strong>{}</strong>", escape_html(name));
+        if let Some(headline) = headline(event, strong) {
+            writeln!(s, "<p>{}.</p>", headline).unwrap();
+        } else {
+            write!(
+                s,
+                "<p><strong>{}</strong> just got a new high score! Score: {}",
+                escape_html(&new.name),
+                new.score
+            )
+            .unwrap();
+            if let Some(delta) = event.delta() {
+                write!(s, " (+{})", delta).unwrap();
+            }
+            writeln!(s, "</p>").unwrap();
+            writeln!(s, "<p>They are now rank #{}.</p>", new.rank).unwrap();
+        }
+        writeln!(
+            s,
+            r#"<p><a href="{}">Watch in-game</a> <time datetime="{}"></time></p>"#,
+            event.run_link(),
+            ctx.rfc3339()
+        )
+        .unwrap();
+        writeln!(s, "</article>").unwrap();
+
+        RenderedMessage::Html(s)
+    }
+}
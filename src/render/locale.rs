@@ -0,0 +1,129 @@
+use serde::Deserialize;
+
+use crate::lb::EventKind;
+use crate::render::Templates;
+
+/// which language the built in text messages are in
+///
+/// ```toml
+/// [[board]]
+/// locale = "ja"
+/// ```
+///
+/// each one is a set of `Templates`, that the board's own
+/// templates go on top of. english is the usual `Text`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale {
+    #[default]
+    En,
+    Ja,
+}
+
+const JA: &[(EventKind, &str)] = &[
+    (
+        EventKind::WorldRecord,
+        "---  世界記録  ---\n\
+         {name}さんが世界記録を更新しました！スコア: {score} (+{delta})\n\
+         観戦: {run_url}",
+    ),
+    (
+        EventKind::Milestone,
+        "---  {banner}  ---\n\
+         {name}さんが自己ベストを更新しました！スコア: {score} (+{delta})\n\
+         現在{rank}位です。\n\
+         観戦: {run_url}",
+    ),
+    (
+        EventKind::Pb,
+        "{name}さんが自己ベストを更新しました！スコア: {score} (+{delta})\n\
+         現在{rank}位です。\n\
+         観戦: {run_url}",
+    ),
+    (
+        EventKind::NewPlayer,
+        "ようこそ{name}さん！初めての記録で{rank}位、スコアは{score}です。",
+    ),
+    (
+        EventKind::RankChange,
+        "{name}さんの順位が{old_rank}位から{rank}位になりました。",
+    ),
+    (
+        EventKind::NameChanged,
+        "{old_name}さんは{name}という名前になりました。",
+    ),
+    (
+        EventKind::ScoreRemoved,
+        "{name}さんのスコア{old_score}が削除されました。現在のベストは{score}です。",
+    ),
+    (
+        EventKind::EnteredTop,
+        "{name}さんがトップ{top}に入りました！",
+    ),
+    (
+        EventKind::CloseRace,
+        "{name}さんが{ahead_rank}位の{ahead}さんまであと{gap}に迫っています。",
+    ),
+];
+
+impl Locale {
+    pub fn templates(self) -> Templates {
+        match self {
+            Self::En => Templates::default(),
+            Self::Ja => JA.iter().copied().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::{Entry, Event};
+    use crate::render::{Render, RenderCtx, Text};
+
+    #[test]
+    fn test_locale() {
+        let entry = |rank, score| Entry {
+            rank,
+            name: "possm".to_string(),
+            user_id: 1,
+            run_id: rank as u32,
+            score,
+        };
+        let (old, new) = (entry(12, 390.0), entry(8, 396.5));
+        let ctx = RenderCtx::new("main");
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+
+        assert_eq!(
+            Locale::Ja.templates().render(&pb, &ctx).into_string(),
+            "possmさんが自己ベストを更新しました！スコア: 396.5 (+6.5)\n\
+             現在8位です。\n\
+             観戦: hyperdemon://run/8\n"
+        );
+        assert_eq!(
+            Locale::En.templates().render(&pb, &ctx),
+            Text.render(&pb, &ctx)
+        );
+
+        let ahead = Entry {
+            name: "fennekal".to_string(),
+            ..entry(7, 400.0)
+        };
+        let close = Event::CloseRace {
+            new: &new,
+            ahead: &ahead,
+        };
+        assert_eq!(
+            Locale::Ja.templates().render(&close, &ctx).into_string(),
+            "possmさんが7位のfennekalさんまであと3.5に迫っています。\n"
+        );
+
+        // the board's own templates still win
+        let mut templates = Locale::Ja.templates();
+        templates.extend([(EventKind::Pb, "{name} pb")].into_iter().collect());
+        assert_eq!(templates.render(&pb, &ctx).into_string(), "possm pb\n");
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt::Write;
+
+use crate::lb::Event;
+use crate::render::discord::escape_markdown;
+use crate::render::{banner, headline, Render, RenderCtx, RenderedMessage};
+
+/// discord flavored markdown, the same as `Text` with names in
+/// bold, and escaped so a name can't break the formatting
+pub struct Markdown;
+
+impl Render for Markdown {
+    fn render(&self, event: &Event, _ctx: &RenderCtx) -> RenderedMessage {
+        let mut s = String::new();
+        let new = event.entry();
+        let bold = |name: &str| format!("**{}**", escape_markdown(name));
+
+        if let Some(headline) = headline(event, bold) {
+            return RenderedMessage::Text(format!("{}.\n", headline));
+        }
+
+        // writing to a String can't fail
+        if let Some(banner) = banner(event) {
+            writeln!(s, "**{}**", escape_markdown(&banner)).unwrap();
+        }
+        write!(
+            s,
+            "{} just got a new high score! Score: **{}**",
+            bold(&new.name),
+            new.score
+        )
+        .unwrap();
+        if let Some(delta) = event.delta() {
+            write!(s, " (+{})", delta).unwrap();
+        }
+        writeln!(s).unwrap();
+        match event.ranks_gained() {
+            Some(sub) => writeln!(s, "They are now rank #{}, gaining {} ranks.", new.rank, sub),
+            None => writeln!(s, "They are now rank #{}.", new.rank),
+        }
+        .unwrap();
+        writeln!(s, "Watch in-game: {}", event.run_link()).unwrap();
+
+        RenderedMessage::Text(s)
+    }
+}
@@ -0,0 +1,108 @@
+use serde_json::{json, Value};
+
+use crate::lb::Event;
+use crate::render::{banner, headline, Render, RenderCtx, RenderedMessage};
+
+/// a slack message made of block kit blocks
+pub struct SlackBlocks;
+
+/// slack wants these three escaped in mrkdwn
+pub(crate) fn escape_mrkdwn(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl SlackBlocks {
+    /// a whole webhook payload, with `content` in front, like
+    /// pings, and `extra` lines at the end
+    pub fn payload(event: &Event, ctx: &RenderCtx, content: &str, extra: &str) -> Value {
+        let RenderedMessage::Json(mut payload) = SlackBlocks.render(event, ctx) else {
+            unreachable!("slack blocks are json");
+        };
+        let blocks = payload["blocks"].as_array_mut().expect("blocks are a list");
+        if !extra.is_empty() {
+            // above the board name
+            let at = blocks.len() - 1;
+            blocks.insert(
+                at,
+                json!({
+                    "type": "section",
+                    "text": { "type": "mrkdwn", "text": escape_mrkdwn(extra.trim_end()) },
+                }),
+            );
+        }
+        // pings only work in blocks, the text is for notifications
+        if !content.is_empty() {
+            blocks.insert(
+                0,
+                json!({
+                    "type": "section",
+                    "text": { "type": "mrkdwn", "text": content },
+                }),
+            );
+        }
+        payload
+    }
+}
+
+impl Render for SlackBlocks {
+    fn render(&self, event: &Event, ctx: &RenderCtx) -> RenderedMessage {
+        let new = event.entry();
+        let name = escape_mrkdwn(&new.name);
+
+        let bold = |name: &str| format!("*{}*", escape_mrkdwn(name));
+        let summary = match (headline(event, bold), event.delta()) {
+            (Some(headline), _) => format!("{}.", headline),
+            (None, Some(delta)) => format!(
+                "*{}* just got a new high score! Score: *{}* (+{})",
+                name, new.score, delta
+            ),
+            (None, None) => format!(
+                "*{}* just got a new high score! Score: *{}*",
+                name, new.score
+            ),
+        };
+
+        // the big ones get some decoration
+        let banner = match (event, banner(event)) {
+            (Event::WorldRecord { .. }, Some(banner)) => Some(format!(":trophy: {}", banner)),
+            (Event::Milestone { .. }, Some(banner)) => Some(format!(":tada: {}", banner)),
+            (_, banner) => banner,
+        };
+        let mut fields = vec![
+            json!({ "type": "mrkdwn", "text": format!("*Rank*\n#{}", new.rank) }),
+            json!({
+                "type": "mrkdwn",
+                "text": format!("*Run*\n<{}|watch in-game>", event.run_link()),
+            }),
+        ];
+        if let Event::WorldRecord { old, .. } | Event::Milestone { old, .. } = event {
+            let previous = format!("*Previous best*\n{} (#{})", old.score, old.rank);
+            fields.push(json!({ "type": "mrkdwn", "text": previous }));
+        }
+
+        let mut blocks = Vec::new();
+        if let Some(banner) = banner {
+            blocks.push(json!({
+                "type": "header",
+                "text": { "type": "plain_text", "text": banner, "emoji": true },
+            }));
+        }
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": summary },
+            "fields": fields,
+        }));
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{ "type": "mrkdwn", "text": escape_mrkdwn(&ctx.board) }],
+        }));
+
+        RenderedMessage::Json(json!({
+            // shown in notifications, where blocks aren't
+            "text": summary,
+            "blocks": blocks,
+        }))
+    }
+}
@@ -0,0 +1,82 @@
+use serde_json::{json, Value};
+
+use crate::lb::Event;
+use crate::render::{banner, headline, Render, RenderCtx, RenderedMessage};
+
+/// a telegram bot api message, in MarkdownV2
+pub struct TelegramMarkdown;
+
+/// stand-ins for the markup, put in before the text gets escaped
+const BOLD: char = '\u{1}';
+const ITALIC: char = '\u{2}';
+
+/// MarkdownV2 wants all of these escaped, anywhere in the text
+pub(crate) fn escape_markdown_v2(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "_*[]()~`>#+-=|{}.!\\".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// escape everything, then turn the stand-ins into real markup
+fn markdown_v2(s: &str) -> String {
+    escape_markdown_v2(s)
+        .replace(BOLD, "*")
+        .replace(ITALIC, "_")
+}
+
+impl TelegramMarkdown {
+    /// a whole request body, minus the chat, with `content` in
+    /// front, like mentions, and `extra` lines at the end
+    pub fn payload(event: &Event, ctx: &RenderCtx, content: &str, extra: &str) -> Value {
+        let RenderedMessage::Json(mut payload) = TelegramMarkdown.render(event, ctx) else {
+            unreachable!("telegram messages are json");
+        };
+        let mut text = payload["text"].as_str().unwrap_or_default().to_string();
+        if !extra.is_empty() {
+            text = format!("{}\n{}", text, escape_markdown_v2(extra.trim_end()));
+        }
+        if !content.is_empty() {
+            text = format!("{}\n{}", escape_markdown_v2(content), text);
+        }
+        payload["text"] = text.into();
+        payload
+    }
+}
+
+impl Render for TelegramMarkdown {
+    fn render(&self, event: &Event, ctx: &RenderCtx) -> RenderedMessage {
+        let new = event.entry();
+
+        let bold = |s: &str| format!("{}{}{}", BOLD, s, BOLD);
+        let mut lines = Vec::new();
+        if let Some(banner) = banner(event) {
+            lines.push(bold(&banner));
+        }
+        match (headline(event, bold), event.delta()) {
+            (Some(headline), _) => lines.push(format!("{}.", headline)),
+            (None, delta) => {
+                let delta = delta.map(|d| format!(" (+{})", d)).unwrap_or_default();
+                lines.push(format!(
+                    "{} just got a new high score! Score: {}{}",
+                    bold(&new.name),
+                    bold(&new.score.to_string()),
+                    delta
+                ));
+                lines.push(format!("They are now rank #{}.", new.rank));
+            }
+        }
+        // telegram won't link to anything but http
+        lines.push(format!("Watch in-game: {}", event.run_link()));
+        lines.push(format!("{}{}{}", ITALIC, ctx.board, ITALIC));
+
+        RenderedMessage::Json(json!({
+            "text": markdown_v2(&lines.join("\n")),
+            "parse_mode": "MarkdownV2",
+        }))
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::lb::{Event, EventKind};
+use crate::render::{banner, Format, Render, RenderCtx, RenderedMessage};
+
+/// text messages written in the config instead of the built in
+/// ones, for whichever kinds of events have one
+///
+/// ```toml
+/// [board.templates]
+/// pb = "{name} got {score} (+{delta}), now #{rank}! {run_url}"
+/// world_record = ":crown: {name} took the world record with {score}"
+/// ```
+///
+/// `{name}`, `{score}`, `{old_score}`, `{old_name}`, `{delta}`,
+/// `{rank}`, `{old_rank}`, `{ranks}` gained, `{top}` for entering
+/// the top so many, `{ahead}`, `{ahead_rank}` and `{gap}` for close
+/// races, `{run_url}`, `{banner}` and `{board}` get filled in.
+/// anything the event doesn't have is left empty, and kinds
+/// without a template get the usual text for wherever it's going,
+/// see `render::Format`, or the board's `render::Locale` has one
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Templates(HashMap<EventKind, String>);
+
+impl Templates {
+    /// use `other`'s templates over these ones, where it has them
+    pub fn extend(&mut self, other: Templates) {
+        self.0.extend(other.0);
+    }
+}
+
+impl<S: Into<String>> FromIterator<(EventKind, S)> for Templates {
+    fn from_iter<I: IntoIterator<Item = (EventKind, S)>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|(k, t)| (k, t.into())).collect())
+    }
+}
+
+impl Templates {
+    /// the event's template filled in, or `format`'s usual text
+    /// for it. templates are the same whatever the format, only
+    /// the names in them get escaped for it
+    pub fn render_in(&self, event: &Event, ctx: &RenderCtx, format: Format) -> String {
+        let Some(template) = self.0.get(&event.kind()) else {
+            return format.render(event, ctx);
+        };
+
+        let new = event.entry();
+        let old = event.previous();
+        let or_empty = |v: Option<String>| v.unwrap_or_default();
+        let top = match event {
+            Event::EnteredTop { top, .. } => Some(top.to_string()),
+            _ => None,
+        };
+        let ahead = match event {
+            Event::CloseRace { new, ahead } => Some((*ahead, ahead.score - new.score)),
+            _ => None,
+        };
+        let vars = [
+            ("name", format.escape(&new.name)),
+            ("score", new.score.to_string()),
+            ("old_score", or_empty(old.map(|o| o.score.to_string()))),
+            ("old_name", or_empty(old.map(|o| format.escape(&o.name)))),
+            ("delta", or_empty(event.delta().map(|d| d.to_string()))),
+            ("rank", new.rank.to_string()),
+            ("old_rank", or_empty(old.map(|o| o.rank.to_string()))),
+            (
+                "ranks",
+                or_empty(event.ranks_gained().map(|r| r.to_string())),
+            ),
+            ("top", or_empty(top)),
+            (
+                "ahead",
+                or_empty(ahead.map(|(a, _)| format.escape(&a.name))),
+            ),
+            (
+                "ahead_rank",
+                or_empty(ahead.map(|(a, _)| a.rank.to_string())),
+            ),
+            ("gap", or_empty(ahead.map(|(_, gap)| gap.to_string()))),
+            ("run_url", event.run_link()),
+            ("banner", banner(event).unwrap_or_default()),
+            ("board", ctx.board.clone()),
+        ];
+
+        format!("{}\n", fill(template, &vars))
+    }
+}
+
+impl Render for Templates {
+    fn render(&self, event: &Event, ctx: &RenderCtx) -> RenderedMessage {
+        RenderedMessage::Text(self.render_in(event, ctx, Format::Plain))
+    }
+}
+
+/// put the values in for their `{names}`, in one go so nothing
+/// in a value gets filled in again. unknown names are left alone
+fn fill(template: &str, vars: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let var = rest.find('}').and_then(|end| {
+            let (_, value) = vars.iter().find(|(name, _)| *name == &rest[1..end])?;
+            Some((value, end))
+        });
+        match var {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+    use crate::render::Text;
+
+    #[test]
+    fn test_templates() {
+        let templates: Templates = toml::from_str(
+            r#"
+            pb = "{name} got {score} (+{delta}), now #{rank} on {board}! {nope} {run_url}"
+            new_player = "welcome {name}{delta}"
+            "#,
+        )
+        .unwrap();
+        let entry = |rank, name: &str, score| Entry {
+            rank,
+            name: name.to_string(),
+            user_id: 1,
+            run_id: rank as u32,
+            score,
+        };
+        let (old, new) = (entry(12, "possm", 390.0), entry(8, "{score}", 396.5));
+        let ctx = RenderCtx::new("main");
+
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+        assert_eq!(
+            templates.render(&pb, &ctx).into_string(),
+            "{score} got 396.5 (+6.5), now #8 on main! {nope} hyperdemon://run/8\n"
+        );
+        let joined = Event::NewPlayer {
+            new: &old,
+            above: None,
+            below: None,
+            out_of: 20,
+        };
+        assert_eq!(
+            templates.render(&joined, &ctx).into_string(),
+            "welcome possm\n"
+        );
+        // no template, so it's the usual text
+        let moved = Event::RankChange {
+            old: &old,
+            new: &new,
+        };
+        assert_eq!(templates.render(&moved, &ctx), Text.render(&moved, &ctx));
+
+        // names get escaped for discord, the template doesn't
+        let fancy = entry(8, "*fenne_kal*", 396.5);
+        let pb = Event::Improvement {
+            old: &old,
+            new: &fancy,
+        };
+        assert!(templates
+            .render_in(&pb, &ctx, Format::Markdown)
+            .starts_with("\\*fenne\\_kal\\* got 396.5"));
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt::Write;
+
+use crate::lb::Event;
+use crate::render::{banner, headline, Render, RenderCtx, RenderedMessage};
+
+/// plain text, what the bot has always sent
+pub struct Text;
+
+impl Render for Text {
+    fn render(&self, event: &Event, _ctx: &RenderCtx) -> RenderedMessage {
+        let mut s = String::new();
+        let new = event.entry();
+
+        if let Some(headline) = headline(event, str::to_string) {
+            return RenderedMessage::Text(format!("{}.\n", headline));
+        }
+
+        // writing to a String can't fail
+        if let Some(delta) = event.delta() {
+            if let Some(banner) = banner(event) {
+                writeln!(s, "---  {}  ---", banner).unwrap();
+            }
+
+            writeln!(
+                s,
+                "{} just got a new high score! Score: {} (+{})",
+                new.name, new.score, delta
+            )
+            .unwrap();
+
+            if let Some(sub) = event.ranks_gained() {
+                writeln!(s, "They are now rank #{}, gaining {} ranks.", new.rank, sub).unwrap();
+            } else {
+                writeln!(s, "They are now rank #{}.", new.rank).unwrap();
+            }
+        } else {
+            writeln!(
+                s,
+                "{} just got a new high score! Score: {}",
+                new.name, new.score
+            )
+            .unwrap();
+            writeln!(s, "They are now rank #{}", new.rank).unwrap();
+        }
+        writeln!(s, "Watch in-game: {}", event.run_link()).unwrap();
+
+        RenderedMessage::Text(s)
+    }
+}
@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tokio::fs;
+use tokio::io;
+
+use crate::net;
+
+/// where to get replays from, and where to keep them
+///
+/// ```toml
+/// [board.replays]
+/// url = "https://hyprd.mn/runs/{run_id}/replay"
+/// dir = "replays"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Replays {
+    /// `{run_id}` gets replaced with the run's id
+    pub url: String,
+    pub dir: PathBuf,
+}
+
+impl Replays {
+    /// where a run's replay is kept
+    pub fn path(&self, run_id: u32) -> PathBuf {
+        self.dir.join(run_id.to_string())
+    }
+
+    /// download a run's replay into the archive
+    ///
+    /// returns the path it was saved to, or None if it was already
+    /// archived or the site doesn't have a replay for the run
+    pub async fn download(&self, run_id: u32) -> io::Result<Option<PathBuf>> {
+        let path = self.path(run_id);
+        if fs::try_exists(&path).await? {
+            return Ok(None);
+        }
+
+        let url = self.url.replace("{run_id}", &run_id.to_string());
+        let res = net::get(url).await.map_err(io::Error::other)?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let bytes = res
+            .error_for_status()
+            .map_err(io::Error::other)?
+            .bytes()
+            .await
+            .map_err(io::Error::other)?;
+
+        // write then rename, so a crash doesn't leave half a replay
+        // sitting there looking archived
+        fs::create_dir_all(&self.dir).await?;
+        let tmp = path.with_extension("part");
+        fs::write(&tmp, &bytes).await?;
+        fs::rename(&tmp, &path).await?;
+
+        Ok(Some(path))
+    }
+}
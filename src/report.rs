@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io;
+use tokio::sync::Mutex;
+
+use crate::lb::{now, EventKind};
+use crate::metrics;
+use crate::schedule::JobStatus;
+
+/// how a cycle's fetch went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fetched {
+    #[default]
+    Board,
+    Maintenance,
+    Unparseable,
+    /// parsed, but failed validation
+    Rejected,
+    /// another instance had the board, see `shared::Shared`
+    Standby,
+}
+
+/// how long each step of a cycle took, in milliseconds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Timings {
+    pub fetch: u64,
+    pub diff: u64,
+    pub notify: u64,
+    pub persist: u64,
+}
+
+/// everything that happened during one poll of a board
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CycleReport {
+    pub board: String,
+    /// counts up from 1 every time the board is polled
+    pub cycle: u64,
+    /// unix time the cycle started at
+    pub started: u64,
+    pub fetched: Fetched,
+    pub timings: Timings,
+    /// entries on the fetched board
+    pub rows: usize,
+    /// events found by the diff
+    pub events: BTreeMap<EventKind, usize>,
+    /// events that didn't make it through the board's filter or rules
+    pub filtered: usize,
+    /// events that weren't sent because they were sent recently
+    pub suppressed: usize,
+    pub sends_attempted: usize,
+    pub sends_succeeded: usize,
+}
+
+impl CycleReport {
+    pub fn new(board: &str, cycle: u64) -> Self {
+        Self {
+            board: board.to_string(),
+            cycle,
+            started: now().as_secs(),
+            ..Default::default()
+        }
+    }
+
+    /// total number of events found
+    pub fn event_count(&self) -> usize {
+        self.events.values().sum()
+    }
+}
+
+impl fmt::Display for CycleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] cycle {}: ", self.board, self.cycle)?;
+
+        match self.fetched {
+            Fetched::Maintenance => return write!(f, "site down for maintenance"),
+            Fetched::Unparseable => return write!(f, "couldn't parse the leaderboard"),
+            Fetched::Rejected => return write!(f, "leaderboard failed validation"),
+            Fetched::Standby => return write!(f, "another instance has the board"),
+            Fetched::Board => {}
+        }
+
+        write!(f, "{} rows, {} events", self.rows, self.event_count())?;
+        for (kind, n) in &self.events {
+            write!(f, " {:?}={}", kind, n)?;
+        }
+        write!(
+            f,
+            ", sent {}/{} ({} filtered, {} suppressed). took fetch {}ms, diff {}ms, notify {}ms, persist {}ms",
+            self.sends_succeeded,
+            self.sends_attempted,
+            self.filtered,
+            self.suppressed,
+            self.timings.fetch,
+            self.timings.diff,
+            self.timings.notify,
+            self.timings.persist,
+        )
+    }
+}
+
+/// feed a report into the global metrics
+pub fn record_metrics(report: &CycleReport) {
+    metrics::CYCLES.inc();
+    match report.fetched {
+        Fetched::Maintenance => metrics::MAINTENANCE_POLLS.inc(),
+        Fetched::Unparseable => metrics::PARSE_FAILURES.inc(),
+        Fetched::Rejected => metrics::REJECTED.inc(),
+        Fetched::Board | Fetched::Standby => {}
+    }
+    metrics::EVENTS.add(report.event_count() as u64);
+    metrics::DEDUP_SUPPRESSED.add(report.suppressed as u64);
+    metrics::SENDS_ATTEMPTED.add(report.sends_attempted as u64);
+    metrics::SENDS_SUCCEEDED.add(report.sends_succeeded as u64);
+}
+
+/// the latest report of every board, and how every job is doing
+///
+/// kept in a json file so `hdget status` can read it
+/// while the bot is running.
+pub struct Status {
+    path: PathBuf,
+    file: Mutex<StatusFile>,
+}
+
+/// what's in the status file
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StatusFile {
+    pub boards: BTreeMap<String, CycleReport>,
+    #[serde(default)]
+    pub jobs: BTreeMap<String, JobStatus>,
+}
+
+impl Status {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            file: Mutex::new(StatusFile::default()),
+        }
+    }
+
+    /// replace a board's report and write the status file
+    pub async fn record(&self, report: CycleReport) -> io::Result<()> {
+        let mut file = self.file.lock().await;
+        file.boards.insert(report.board.clone(), report);
+        self.write(&file).await
+    }
+
+    /// replace a job's status and write the status file
+    pub async fn record_job(&self, job: JobStatus) -> io::Result<()> {
+        let mut file = self.file.lock().await;
+        file.jobs.insert(job.name.clone(), job);
+        self.write(&file).await
+    }
+
+    async fn write(&self, file: &StatusFile) -> io::Result<()> {
+        // write then rename, so readers never see half a file
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(file)?).await?;
+        fs::rename(&tmp, &self.path).await
+    }
+
+    /// read a status file
+    pub async fn read(path: impl AsRef<Path>) -> io::Result<StatusFile> {
+        let text = fs::read(path).await?;
+        Ok(serde_json::from_slice(&text)?)
+    }
+}
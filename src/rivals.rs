@@ -0,0 +1,151 @@
+use serde::Deserialize;
+use tokio::io;
+
+use crate::filter::Player;
+use crate::lb::{Entry, Event, Leaderboard};
+use crate::store::{Store, User};
+
+/// two players who want to hear about each other's pbs
+///
+/// ```toml
+/// [[board]]
+/// rivals = [[1234, "possm"]]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rivalry(pub Player, pub Player);
+
+impl Rivalry {
+    /// the other side, if `entry` is in this rivalry
+    fn rival_of(&self, entry: &Entry) -> Option<&Player> {
+        if self.0.is(entry) {
+            Some(&self.1)
+        } else if self.1.is(entry) {
+            Some(&self.0)
+        } else {
+            None
+        }
+    }
+}
+
+/// how someone's doing against their rival
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub rank: u16,
+    pub score: f32,
+    pub rival: String,
+    pub rival_rank: u16,
+    pub rival_score: f32,
+}
+
+impl Standing {
+    /// a line to go under the message
+    pub fn line(&self) -> String {
+        let gap = self.score - self.rival_score;
+        let side = match gap >= 0.0 {
+            true => "ahead",
+            false => "behind",
+        };
+        format!(
+            "Head to head with {}: {} by {} (#{} vs #{})",
+            self.rival,
+            side,
+            gap.abs(),
+            self.rank,
+            self.rival_rank
+        )
+    }
+}
+
+/// where whoever's in an event stands against each of their rivals
+///
+/// rivals are looked up on the board first. anyone who isn't on it
+/// anymore is taken from the last thing the store has on them
+pub async fn standings(
+    rivals: &[Rivalry],
+    pb: &Event<'_>,
+    lb: &Leaderboard,
+    store: Option<&Store>,
+    board: &str,
+) -> io::Result<Vec<Standing>> {
+    let entry = pb.entry();
+    let mut standings = Vec::new();
+
+    for rival in rivals.iter().filter_map(|r| r.rival_of(entry)) {
+        let current = lb
+            .entries
+            .iter()
+            .find(|e| rival.is(e))
+            .map(|e| (e.name.clone(), e.rank, e.score));
+        let last = match (current, store) {
+            (Some(current), _) => Some(current),
+            (None, Some(store)) => {
+                let who = match rival {
+                    Player::Id(id) => User::Id(*id),
+                    Player::Name(name) => User::Name(name),
+                };
+                let events = store.events_of(who, Some(board)).await?;
+                events.into_iter().last().map(|e| (e.name, e.rank, e.score))
+            }
+            (None, None) => None,
+        };
+
+        if let Some((name, rank, score)) = last {
+            standings.push(Standing {
+                rank: entry.rank,
+                score: entry.score,
+                rival: name,
+                rival_rank: rank,
+                rival_score: score,
+            });
+        }
+    }
+
+    Ok(standings)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn entry(rank: u16, user_id: u32, score: f32) -> Entry {
+        Entry {
+            rank,
+            name: format!("player {}", user_id),
+            user_id,
+            run_id: user_id,
+            score,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_standings() {
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![entry(1, 1, 420.0), entry(2, 2, 410.5), entry(3, 3, 400.0)],
+        };
+        let old = entry(4, 2, 390.0);
+        let pb = Event::Improvement {
+            old: &old,
+            new: &lb.entries[1],
+        };
+        let rivals = [
+            Rivalry(Player::Id(1), Player::Name("player 2".to_string())),
+            Rivalry(Player::Id(2), Player::Id(3)),
+            Rivalry(Player::Id(1), Player::Id(3)),
+            // not on the board, and there's no store
+            Rivalry(Player::Id(2), Player::Id(4)),
+        ];
+
+        let standings = standings(&rivals, &pb, &lb, None, "main").await.unwrap();
+        let lines: Vec<_> = standings.iter().map(Standing::line).collect();
+        assert_eq!(
+            lines,
+            [
+                "Head to head with player 1: behind by 9.5 (#2 vs #1)",
+                "Head to head with player 3: ahead by 10.5 (#2 vs #3)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::filter::Player;
+use crate::lb::{Event, EventKind};
+
+/// something to do with the events that match, out of the config
+///
+/// every rule that matches an event gets applied, in order
+///
+/// ```toml
+/// [[board.rules]]
+/// when = { kind = ["pb"], rank = 10, min_delta = 1.0 }
+/// then = { send_to = "records", ping = "<@&1234>" }
+///
+/// [[board.rules]]
+/// when = { players = ["possm"] }
+/// then = { suppress = true }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    #[serde(default)]
+    pub when: When,
+    pub then: Then,
+}
+
+/// what an event needs for a rule to apply to it. anything
+/// that isn't set doesn't matter
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct When {
+    pub kind: Option<HashSet<EventKind>>,
+    /// at this rank or better
+    pub rank: Option<u16>,
+    pub min_score: Option<f32>,
+    /// how much a pb gained, anything that isn't one doesn't match
+    pub min_delta: Option<f32>,
+    pub players: Option<Vec<Player>>,
+}
+
+/// what a rule does
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Then {
+    /// name of one of the board's `config::Route`s
+    pub send_to: Option<String>,
+    /// goes in front of the message, like a role mention
+    pub ping: Option<String>,
+    /// don't send it at all
+    pub suppress: bool,
+}
+
+/// what the rules decided about an event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Outcome {
+    pub suppress: bool,
+    /// the route from the last rule that had one
+    pub send_to: Option<String>,
+    pub pings: Vec<String>,
+    /// sent instead of the rendered message, only scripts set it
+    pub message: Option<String>,
+}
+
+impl Outcome {
+    /// apply what something decided after this
+    pub fn merge(&mut self, other: Outcome) {
+        self.suppress |= other.suppress;
+        if other.send_to.is_some() {
+            self.send_to = other.send_to;
+        }
+        self.pings.extend(other.pings);
+        if other.message.is_some() {
+            self.message = other.message;
+        }
+    }
+}
+
+impl When {
+    pub fn matches(&self, pb: &Event) -> bool {
+        let new = pb.entry();
+        if self.kind.as_ref().is_some_and(|k| !k.contains(&pb.kind())) {
+            return false;
+        }
+        if self.rank.is_some_and(|rank| new.rank > rank) {
+            return false;
+        }
+        if self.min_score.is_some_and(|min| new.score < min) {
+            return false;
+        }
+        if let Some(min) = self.min_delta {
+            if pb.delta().is_none_or(|delta| delta < min) {
+                return false;
+            }
+        }
+        // they might've been on the board under their old name
+        if let Some(players) = &self.players {
+            let mut entries = pb.previous().into_iter().chain([new]);
+            if !entries.any(|e| players.iter().any(|p| p.is(e))) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// run an event through every rule
+pub fn apply(rules: &[Rule], pb: &Event) -> Outcome {
+    let mut outcome = Outcome::default();
+    for then in rules.iter().filter(|r| r.when.matches(pb)).map(|r| &r.then) {
+        outcome.suppress |= then.suppress;
+        if then.send_to.is_some() {
+            outcome.send_to.clone_from(&then.send_to);
+        }
+        outcome.pings.extend(then.ping.clone());
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+
+    #[derive(Deserialize)]
+    struct Board {
+        rules: Vec<Rule>,
+    }
+
+    #[test]
+    fn test_rules() {
+        let Board { rules } = toml::from_str(
+            r#"
+            [[rules]]
+            when = { kind = ["pb", "world_record"], rank = 10 }
+            then = { send_to = "top", ping = "<@&1>" }
+
+            [[rules]]
+            when = { min_delta = 5.0 }
+            then = { send_to = "big", ping = "<@&2>" }
+
+            [[rules]]
+            when = { players = ["fennekal"] }
+            then = { suppress = true }
+            "#,
+        )
+        .unwrap();
+        let entry = |rank, name: &str, score| Entry {
+            rank,
+            name: name.to_string(),
+            user_id: 1,
+            run_id: rank as u32,
+            score,
+        };
+
+        let (old, new) = (entry(12, "possm", 390.0), entry(8, "possm", 396.0));
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+        assert_eq!(
+            apply(&rules, &pb),
+            Outcome {
+                suppress: false,
+                send_to: Some("big".to_string()),
+                pings: vec!["<@&1>".to_string(), "<@&2>".to_string()],
+                message: None,
+            }
+        );
+
+        // under their new name
+        let renamed = entry(8, "fennekal", 396.0);
+        let moved = Event::RankChange {
+            old: &old,
+            new: &renamed,
+        };
+        let outcome = apply(&rules, &moved);
+        assert!(outcome.suppress);
+        assert_eq!(outcome.send_to, None);
+
+        let low = entry(40, "possm", 391.0);
+        let pb = Event::Improvement {
+            old: &old,
+            new: &low,
+        };
+        assert_eq!(apply(&rules, &pb), Outcome::default());
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio::time::{self, Instant, MissedTickBehavior};
+use tracing::warn;
+
+use crate::lb::now;
+use crate::report::Status;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// something the bot does every so often
+pub trait Job: Send + 'static {
+    /// shows up in logs and `hdget status`, has to be unique
+    fn name(&self) -> String;
+
+    /// time between the start of one run and the next
+    fn interval(&self) -> Duration;
+
+    /// time until the first run, one interval by default
+    fn first_run(&self) -> Duration {
+        self.interval()
+    }
+
+    fn run(&mut self) -> BoxFuture<'_, io::Result<()>>;
+
+    /// called once when the bot shuts down
+    fn shutdown(&mut self) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// how a job has been doing, as shown by `hdget status`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub name: String,
+    /// seconds between runs
+    pub interval: u64,
+    pub runs: u64,
+    /// unix time the last run started at
+    pub last_started: Option<u64>,
+    /// how long the last run took, in milliseconds
+    pub last_took: u64,
+    pub last_error: Option<String>,
+    /// runs that got skipped because the one before was still going
+    pub skipped: u64,
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] every {}s: {} runs, {} skipped, last took {}ms",
+            self.name, self.interval, self.runs, self.skipped, self.last_took
+        )?;
+        if let Some(e) = &self.last_error {
+            write!(f, ", last failed: {}", e)?;
+        }
+        Ok(())
+    }
+}
+
+/// runs every job on its own interval until shutdown
+///
+/// a job never overlaps with itself, if a run takes longer than
+/// the interval the runs it would've started in the meantime are
+/// skipped. jobs don't wait on each other, and a failed run only
+/// shows up in the logs and `hdget status`, the job carries on.
+pub struct Scheduler {
+    jobs: Vec<Box<dyn Job>>,
+    status: Arc<Status>,
+}
+
+impl Scheduler {
+    pub fn new(status: Arc<Status>) -> Self {
+        Self {
+            jobs: Vec::new(),
+            status,
+        }
+    }
+
+    pub fn add(&mut self, job: impl Job) {
+        self.jobs.push(Box::new(job));
+    }
+
+    /// run the jobs until `shutdown` flips to true
+    pub async fn run(self, shutdown: watch::Receiver<bool>) -> io::Result<()> {
+        let mut jobs = JoinSet::new();
+        for job in self.jobs {
+            jobs.spawn(drive(job, self.status.clone(), shutdown.clone()));
+        }
+
+        // a job only stops once it's shut down
+        while let Some(res) = jobs.join_next().await {
+            res.map_err(io::Error::other)??;
+        }
+
+        Ok(())
+    }
+}
+
+/// run a single job forever
+async fn drive(
+    mut job: Box<dyn Job>,
+    status: Arc<Status>,
+    mut shutdown: watch::Receiver<bool>,
+) -> io::Result<()> {
+    let interval = job.interval();
+    let mut ticks = time::interval_at(Instant::now() + job.first_run(), interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut state = JobStatus {
+        name: job.name(),
+        interval: interval.as_secs(),
+        ..Default::default()
+    };
+
+    loop {
+        // wait for the next run, or wrap up and stop
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = shutdown.changed() => return job.shutdown().await,
+        }
+
+        let start = Instant::now();
+        state.last_started = Some(now().as_secs());
+        let res = job.run().await;
+        let took = start.elapsed();
+
+        state.runs += 1;
+        state.last_took = took.as_millis() as u64;
+        state.last_error = res.as_ref().err().map(|e| e.to_string());
+        state.skipped += (took.as_millis() / interval.as_millis().max(1)) as u64;
+
+        if let Err(e) = status.record_job(state.clone()).await {
+            warn!(job = %state.name, error = %e, "couldn't write the status file");
+        }
+        // it might work next time, like a fetch while the site is down
+        if let Err(e) = res {
+            warn!(job = %state.name, error = %e, "the job failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Count(Arc<std::sync::atomic::AtomicU64>);
+
+    /// fails every run
+    struct Broken;
+
+    impl Job for Broken {
+        fn name(&self) -> String {
+            "broken".to_string()
+        }
+
+        fn interval(&self) -> Duration {
+            Duration::from_millis(10)
+        }
+
+        fn run(&mut self) -> BoxFuture<'_, io::Result<()>> {
+            Box::pin(async { Err(io::Error::other("the site is down")) })
+        }
+    }
+
+    impl Job for Count {
+        fn name(&self) -> String {
+            "count".to_string()
+        }
+
+        fn interval(&self) -> Duration {
+            Duration::from_millis(10)
+        }
+
+        fn run(&mut self) -> BoxFuture<'_, io::Result<()>> {
+            Box::pin(async {
+                self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scheduler() {
+        let path = std::env::temp_dir().join(format!("hdget-status-{}", std::process::id()));
+        let status = Arc::new(Status::new(&path));
+        let runs = Arc::new(std::sync::atomic::AtomicU64::new(0));
+
+        let mut scheduler = Scheduler::new(status);
+        scheduler.add(Count(runs.clone()));
+        scheduler.add(Broken);
+
+        let (tx, rx) = watch::channel(false);
+        let handle = tokio::spawn(scheduler.run(rx));
+        time::sleep(Duration::from_millis(100)).await;
+        tx.send(true).unwrap();
+        handle.await.unwrap().unwrap();
+
+        let file = Status::read(&path).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        let job = &file.jobs["count"];
+        assert!(job.runs > 0);
+        assert_eq!(job.runs, runs.load(std::sync::atomic::Ordering::Relaxed));
+        assert_eq!(job.last_error, None);
+
+        // the other job kept going
+        let broken = &file.jobs["broken"];
+        assert!(broken.runs > 1);
+        assert_eq!(broken.last_error.as_deref(), Some("the site is down"));
+    }
+}
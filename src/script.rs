@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use rhai::serde::{from_dynamic, to_dynamic};
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde::{Deserialize, Serialize};
+use tokio::io;
+
+use crate::lb::{Entry, Event, EventKind, Leaderboard, Severity};
+use crate::rules::Outcome;
+
+/// how many entries from the top of the board a script gets to see
+const TOP: usize = 10;
+
+/// a rhai script that decides what happens to a board's events,
+/// for whatever `rules::Rule`s can't do
+///
+/// ```toml
+/// [[board]]
+/// script = "main.rhai"
+/// ```
+///
+/// the script has an `on_event(event, board)` function, where the
+/// event has its `kind`, `severity`, `entry`, `previous` entry and
+/// `delta`, and the board has its `name`, `size`, `timestamp` and
+/// the `top` 10 entries. it returns
+/// nothing to leave the event alone, or a map with any of
+/// `suppress`, `send_to`, `ping` and `message` in it:
+///
+/// ```rhai
+/// fn on_event(event, board) {
+///     if event.kind == "pb" && event.entry.score > 400.0 {
+///         #{ ping: "<@&1234>", message: `${event.entry.name} is over 400!` }
+///     }
+/// }
+/// ```
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+}
+
+/// an event, as a script sees it. `new` is a keyword in rhai,
+/// so it can't just get the `Event`
+#[derive(Serialize)]
+struct ScriptEvent<'a> {
+    kind: EventKind,
+    severity: Severity,
+    entry: &'a Entry,
+    previous: Option<&'a Entry>,
+    delta: Option<f32>,
+}
+
+/// the board, as a script sees it
+#[derive(Serialize)]
+struct Board<'a> {
+    name: &'a str,
+    size: usize,
+    timestamp: u64,
+    top: &'a [Entry],
+}
+
+/// what a script returns, see `rules::Then`
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Returned {
+    suppress: bool,
+    send_to: Option<String>,
+    ping: Option<String>,
+    message: Option<String>,
+}
+
+impl Script {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut engine = Engine::new();
+        // a script that never finishes would hang the whole board
+        engine.set_max_operations(1_000_000);
+        let ast = engine
+            .compile_file(path.as_ref().to_path_buf())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        Ok(Self { engine, ast })
+    }
+
+    /// ask the script about an event on `lb`
+    pub fn run(&self, pb: &Event, lb: &Leaderboard, board: &str) -> io::Result<Outcome> {
+        let board = Board {
+            name: board,
+            size: lb.len(),
+            timestamp: lb.timestamp().as_secs(),
+            top: &lb.entries()[..TOP.min(lb.len())],
+        };
+        let pb = ScriptEvent {
+            kind: pb.kind(),
+            severity: pb.severity(),
+            entry: pb.entry(),
+            previous: pb.previous(),
+            delta: pb.delta(),
+        };
+        let args = (
+            to_dynamic(pb).map_err(invalid)?,
+            to_dynamic(board).map_err(invalid)?,
+        );
+
+        let returned: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, "on_event", args)
+            .map_err(invalid)?;
+        if returned.is_unit() {
+            return Ok(Outcome::default());
+        }
+        let returned: Returned = from_dynamic(&returned).map_err(invalid)?;
+
+        Ok(Outcome {
+            suppress: returned.suppress,
+            send_to: returned.send_to,
+            pings: returned.ping.into_iter().collect(),
+            message: returned.message,
+        })
+    }
+}
+
+fn invalid(e: Box<rhai::EvalAltResult>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_script() {
+        let path = std::env::temp_dir().join(format!("hdget-script-{}.rhai", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+            fn on_event(event, board) {
+                if event.kind == "world_record" {
+                    return #{ send_to: "records", ping: "<@&1>" };
+                }
+                if event.entry.score < board.top[0].score - 50.0 {
+                    #{ suppress: true }
+                } else {
+                    #{ message: `${event.entry.name} is up to #${event.entry.rank} on ${board.name}` }
+                }
+            }
+            "#,
+        )
+        .unwrap();
+        let script = Script::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let entry = |rank: u16, score| Entry {
+            rank,
+            name: "possm".to_string(),
+            user_id: rank as u32,
+            run_id: rank as u32,
+            score,
+        };
+        let lb = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![entry(1, 420.0), entry(2, 390.0), entry(3, 350.0)],
+        };
+        let old = entry(4, 300.0);
+        let run = |new| {
+            let pb = Event::Improvement { old: &old, new };
+            script.run(&pb, &lb, "main").unwrap()
+        };
+
+        assert_eq!(
+            run(&lb.entries[1]).message.as_deref(),
+            Some("possm is up to #2 on main")
+        );
+        assert!(run(&lb.entries[2]).suppress);
+
+        let wr = Event::WorldRecord {
+            old: &old,
+            new: &lb.entries[0],
+        };
+        let outcome = script.run(&wr, &lb, "main").unwrap();
+        assert_eq!(outcome.send_to.as_deref(), Some("records"));
+        assert_eq!(outcome.pings, ["<@&1>"]);
+    }
+}
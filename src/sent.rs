@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{self, AsyncWriteExt};
+
+use crate::lb::{now, Event, EventKind};
+
+/// a message that made it to a webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sent {
+    /// id used to refer to the event, ie. by `hdget correct`
+    pub event_id: String,
+    pub board: String,
+    pub user_id: u32,
+    pub run_id: u32,
+    /// discord's id for the message
+    pub message_id: u64,
+    /// the `config::Route` it went to, none for the board's webhook
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>,
+    /// unix time the message was sent at
+    pub timestamp: u64,
+}
+
+impl Sent {
+    pub fn new(board: &str, pb: &Event, message_id: u64) -> Self {
+        let timestamp = now().as_secs();
+
+        Self {
+            event_id: event_id(board, pb),
+            board: board.to_string(),
+            user_id: pb.entry().user_id,
+            run_id: pb.entry().run_id,
+            message_id,
+            route: None,
+            timestamp,
+        }
+    }
+}
+
+/// the id of the Event a run belongs to
+///
+/// run ids are unique per board, so this is too. a run can
+/// change ranks and names more than once, and get into more
+/// than one bracket, so those get the rank, a hash of the
+/// name, or the bracket on the end. a removed score is about
+/// the run that's gone, not whatever's left, and a close race
+/// is about the run that's ahead too
+pub fn event_id(board: &str, pb: &Event) -> String {
+    match pb {
+        Event::RankChange { new, .. } => format!("{}-{}-{}", board, new.run_id, new.rank),
+        Event::NameChanged { new, .. } => format!(
+            "{}-{}-{:08x}",
+            board,
+            new.run_id,
+            crc32fast::hash(new.name.as_bytes())
+        ),
+        Event::ScoreRemoved { old, .. } => format!("{}-{}-removed", board, old.run_id),
+        Event::CloseRace { new, ahead } => {
+            format!("{}-{}-behind{}", board, new.run_id, ahead.run_id)
+        }
+        Event::EnteredTop { new, top, .. } => format!("{}-{}-top{}", board, new.run_id, top),
+        _ => format!("{}-{}", board, pb.entry().run_id),
+    }
+}
+
+/// append-only log of everything we've sent
+///
+/// stored as one json object per line, so it's easy
+/// to grep through when something goes wrong.
+pub struct SentLog {
+    path: PathBuf,
+}
+
+impl SentLog {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// add a record to the end of the log
+    pub async fn append(&self, sent: &Sent) -> io::Result<()> {
+        let mut line = serde_json::to_string(sent)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        // one write per line, so concurrent boards don't interleave
+        file.write_all(line.as_bytes()).await
+    }
+
+    /// read every record in the log
+    ///
+    /// a missing log just means nothing was sent yet
+    pub async fn read_all(&self) -> io::Result<Vec<Sent>> {
+        let text = match fs::read_to_string(&self.path).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        text.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| serde_json::from_str(l).map_err(io::Error::from))
+            .collect()
+    }
+
+    /// everything sent for a board within `window` of now
+    pub async fn recent(&self, board: &str, window: Duration) -> io::Result<Recent> {
+        let since = now().saturating_sub(window).as_secs();
+
+        let mut recent = Recent::default();
+        let sent = self.read_all().await?.into_iter();
+        for s in sent.filter(|s| s.board == board && s.timestamp >= since) {
+            recent.add(&s.event_id, s.user_id, s.run_id);
+        }
+        Ok(recent)
+    }
+
+    /// find the latest record for an event
+    pub async fn find(&self, event_id: &str) -> io::Result<Option<Sent>> {
+        Ok(self
+            .read_all()
+            .await?
+            .into_iter()
+            .rev()
+            .find(|s| s.event_id == event_id))
+    }
+}
+
+/// runs and events that were announced recently
+///
+/// if the site flakes and a run drops off the board for
+/// a cycle, it comes back looking brand new. this is
+/// used to avoid announcing it a second time.
+///
+/// if the bot dies after sending but before the cache is
+/// written, the next run finds the same events again, so
+/// those are kept by event id too.
+#[derive(Default)]
+pub struct Recent {
+    runs: HashSet<(u32, u32)>,
+    events: HashSet<String>,
+}
+
+impl Recent {
+    /// remember an event that was sent or queued, but isn't in the log
+    pub fn add(&mut self, event_id: &str, user_id: u32, run_id: u32) {
+        self.runs.insert((user_id, run_id));
+        self.events.insert(event_id.to_string());
+    }
+
+    /// check if the run in an Event was already sent
+    ///
+    /// rank and name changes are about a run that was already sent,
+    /// and flapping doesn't cause them, so they always go through.
+    /// neither do removed scores, since the run that's left was
+    /// probably sent not long before the one that got removed.
+    /// getting into a bracket or a close race comes with the run,
+    /// so those don't. the exact same event never goes through twice
+    pub fn contains(&self, board: &str, pb: &Event) -> bool {
+        if self.events.contains(&event_id(board, pb)) {
+            return true;
+        }
+        !matches!(
+            pb.kind(),
+            EventKind::RankChange | EventKind::NameChanged | EventKind::ScoreRemoved
+        ) && self.runs.contains(&(pb.entry().user_id, pb.entry().run_id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+
+    #[test]
+    fn test_recent() {
+        let entry = |rank, run_id| Entry {
+            rank,
+            name: "possm".to_string(),
+            user_id: 1,
+            run_id,
+            score: 390.0,
+        };
+        let (old, new, dropped) = (entry(5, 1), entry(4, 2), entry(6, 2));
+        let pb = Event::Improvement {
+            old: &old,
+            new: &new,
+        };
+        let moved = Event::RankChange {
+            old: &new,
+            new: &dropped,
+        };
+
+        let mut recent = Recent::default();
+        assert!(!recent.contains("main", &pb));
+        recent.add(&event_id("main", &moved), 1, 2);
+        // the run went out with the rank change
+        assert!(recent.contains("main", &pb));
+        assert!(recent.contains("main", &moved));
+        // but rank changes only go by their id
+        let again = Event::RankChange {
+            old: &dropped,
+            new: &new,
+        };
+        assert!(!recent.contains("main", &again));
+        assert!(!recent.contains("hard", &moved));
+    }
+}
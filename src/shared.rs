@@ -0,0 +1,97 @@
+use tokio::io;
+use tokio_postgres::{Client, NoTls};
+use tracing::warn;
+
+use crate::lb::Leaderboard;
+
+/// baselines kept in postgres, so several instances can share them
+///
+/// only one instance polls a board at a time, whoever gets the
+/// board's advisory lock first. the others skip the cycle, and
+/// pick up where it left off once they get the lock, so a pb
+/// only ever gets posted by one of them.
+///
+/// ```toml
+/// postgres = "host=db user=hdget dbname=hdget"
+/// ```
+pub struct Shared {
+    client: Client,
+}
+
+impl Shared {
+    /// connect, and create the table if it isn't there
+    pub async fn connect(config: &str) -> io::Result<Self> {
+        let (client, conn) = tokio_postgres::connect(config, NoTls)
+            .await
+            .map_err(io::Error::other)?;
+        tokio::spawn(async move {
+            if let Err(e) = conn.await {
+                warn!(error = %e, "lost the postgres connection");
+            }
+        });
+
+        client
+            .batch_execute(
+                "CREATE TABLE IF NOT EXISTS baselines (
+                    board TEXT PRIMARY KEY,
+                    data BYTEA NOT NULL
+                )",
+            )
+            .await
+            .map_err(io::Error::other)?;
+
+        Ok(Self { client })
+    }
+
+    /// try to take a board, returns false if another instance has it
+    pub async fn lock(&self, board: &str) -> io::Result<bool> {
+        let row = self
+            .client
+            .query_one("SELECT pg_try_advisory_lock($1)", &[&lock_key(board)])
+            .await
+            .map_err(io::Error::other)?;
+        Ok(row.get(0))
+    }
+
+    pub async fn unlock(&self, board: &str) -> io::Result<()> {
+        self.client
+            .execute("SELECT pg_advisory_unlock($1)", &[&lock_key(board)])
+            .await
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    /// the baseline whoever polled the board last left behind
+    pub async fn baseline(&self, board: &str) -> io::Result<Option<Leaderboard>> {
+        let row = self
+            .client
+            .query_opt("SELECT data FROM baselines WHERE board = $1", &[&board])
+            .await
+            .map_err(io::Error::other)?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let data: Vec<u8> = row.get(0);
+        Ok(Some(Leaderboard::from_bytes(&data).await?))
+    }
+
+    pub async fn save_baseline(&self, board: &str, lb: &Leaderboard) -> io::Result<()> {
+        let data = lb.to_binary().await?;
+        self.client
+            .execute(
+                "INSERT INTO baselines (board, data) VALUES ($1, $2)
+                 ON CONFLICT (board) DO UPDATE SET data = excluded.data",
+                &[&board, &data],
+            )
+            .await
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+/// advisory locks are keyed by a number, so hash the board name.
+/// has to come out the same on every instance
+fn lock_key(board: &str) -> i64 {
+    crc32fast::hash(board.as_bytes()) as i64
+}
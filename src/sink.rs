@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tokio::fs::OpenOptions;
+use tokio::io::{self, AsyncWriteExt};
+
+use crate::lb::Event;
+
+/// where every event gets written as a line of json, for stream
+/// overlays and the like that don't want to go through discord
+///
+/// gets everything the diff finds, before any filtering
+///
+/// ```toml
+/// event_sink = "events.jsonl"
+/// ```
+///
+/// or `"-"` for stdout
+pub enum Sink {
+    Stdout,
+    File(PathBuf),
+}
+
+/// a line of the sink
+#[derive(Serialize)]
+struct Line<'a> {
+    board: &'a str,
+    /// unix time of the leaderboard the event was found on
+    timestamp: u64,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+impl Sink {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().as_os_str() == "-" {
+            true => Self::Stdout,
+            false => Self::File(path.as_ref().to_path_buf()),
+        }
+    }
+
+    /// write out the events from one board
+    pub async fn write(&self, board: &str, timestamp: u64, events: &[Event<'_>]) -> io::Result<()> {
+        let buf = lines(board, timestamp, events)?;
+        match self {
+            Self::Stdout => {
+                let mut stdout = io::stdout();
+                stdout.write_all(buf.as_bytes()).await?;
+                stdout.flush().await
+            }
+            Self::File(path) => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(buf.as_bytes()).await
+            }
+        }
+    }
+}
+
+/// the events as json, one per line
+fn lines(board: &str, timestamp: u64, events: &[Event]) -> io::Result<String> {
+    let mut buf = String::new();
+    for event in events {
+        let line = Line {
+            board,
+            timestamp,
+            event,
+        };
+        buf.push_str(&serde_json::to_string(&line)?);
+        buf.push('\n');
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lb::Entry;
+
+    #[test]
+    fn test_lines() {
+        let new = Entry {
+            rank: 3,
+            name: "possm".to_string(),
+            user_id: 1,
+            run_id: 2,
+            score: 390.5,
+        };
+        let events = [Event::NewPlayer {
+            new: &new,
+            above: None,
+            below: None,
+            out_of: 3,
+        }];
+
+        let line: serde_json::Value =
+            serde_json::from_str(&lines("main", 600, &events).unwrap()).unwrap();
+        assert_eq!(line["board"], "main");
+        assert_eq!(line["timestamp"], 600);
+        assert_eq!(line["kind"], "new_player");
+        assert_eq!(line["new"]["name"], "possm");
+    }
+}
@@ -0,0 +1,103 @@
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tokio::io;
+
+use crate::lb::Leaderboard;
+
+#[cfg(feature = "browser")]
+pub mod browser;
+pub mod site;
+pub mod steam;
+pub mod wayback;
+
+/// where a board's leaderboard comes from
+///
+/// ```toml
+/// [board.source]
+/// type = "steam"
+/// app_id = 1234
+/// leaderboard_id = 5678
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Source {
+    /// scrape the leaderboard page on the site
+    Site(site::Site),
+    /// read the leaderboard out of steam
+    Steam(steam::Steam),
+    /// render the page in a headless browser first
+    #[cfg(feature = "browser")]
+    Browser(browser::Browser),
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Self::Site(site::Site::default())
+    }
+}
+
+/// what came back from fetching a leaderboard
+#[derive(Debug)]
+pub enum FetchOutcome {
+    /// we got a leaderboard
+    Board(Leaderboard),
+    /// the site told us it's down
+    Maintenance,
+    /// we got a response, but couldn't make a leaderboard
+    /// out of it. usually means the site changed
+    Unparseable {
+        /// whatever the response was
+        body: String,
+    },
+}
+
+/// bits of text that show up on the site's "we're down" pages
+const MAINTENANCE_MARKERS: &[&str] = &[
+    "down for maintenance",
+    "under maintenance",
+    "be right back",
+    "temporarily unavailable",
+    "bad gateway",
+];
+
+/// check if a page that didn't parse is a maintenance splash
+pub(crate) fn is_maintenance_page(html: &str) -> bool {
+    let html = html.to_lowercase();
+    MAINTENANCE_MARKERS.iter().any(|m| html.contains(m))
+}
+
+/// statuses a host sends while it's down, rather than broken
+pub(crate) fn is_down(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+impl Source {
+    /// get the current leaderboard
+    pub async fn fetch(&self) -> io::Result<FetchOutcome> {
+        match self {
+            Self::Site(site) => site.fetch().await.map_err(io::Error::other),
+            Self::Steam(steam) => steam.fetch().await.map_err(io::Error::other),
+            #[cfg(feature = "browser")]
+            Self::Browser(browser) => browser.fetch().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_page() {
+        let html = "<html><body><h1>Hyperdemon is down for maintenance</h1></body></html>";
+
+        assert!(Leaderboard::from_html(html, std::time::Duration::ZERO).is_none());
+        assert!(is_maintenance_page(html));
+        assert!(!is_maintenance_page(
+            "<table class=\"leaderboard\"></table>"
+        ));
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::io;
+use tokio::process::Command;
+
+use crate::lb::{now, Leaderboard};
+use crate::source::{is_maintenance_page, FetchOutcome};
+
+/// the leaderboard page, rendered by a headless chromium
+///
+/// for if the site ever starts drawing the rows with javascript.
+/// the rendered dom goes through the same parser as the site.
+///
+/// ```toml
+/// [board.source]
+/// type = "browser"
+/// url = "https://hyprd.mn/leaderboards"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Browser {
+    pub url: String,
+    /// chromium binary to run
+    #[serde(default = "default_chrome")]
+    pub chrome: String,
+    /// how long to let the page's scripts run, in milliseconds
+    #[serde(default = "default_budget")]
+    pub budget: u64,
+}
+
+fn default_chrome() -> String {
+    "chromium".to_string()
+}
+
+fn default_budget() -> u64 {
+    10_000
+}
+
+impl Browser {
+    /// render the page and parse it
+    pub async fn fetch(&self) -> io::Result<FetchOutcome> {
+        let timestamp = now();
+
+        let run = Command::new(&self.chrome)
+            .arg("--headless")
+            .arg("--disable-gpu")
+            .arg(format!("--virtual-time-budget={}", self.budget))
+            .arg("--dump-dom")
+            .arg(&self.url)
+            .kill_on_drop(true)
+            .output();
+
+        // give chromium some time on top of the budget to start up
+        let limit = Duration::from_millis(self.budget) + Duration::from_secs(30);
+        let output = tokio::time::timeout(limit, run)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "chromium took too long"))??;
+
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "chromium exited with {}",
+                output.status
+            )));
+        }
+        let html = String::from_utf8_lossy(&output.stdout).into_owned();
+
+        Ok(match Leaderboard::from_html(&html, timestamp) {
+            Some(lb) => FetchOutcome::Board(lb),
+            None if is_maintenance_page(&html) => FetchOutcome::Maintenance,
+            None => FetchOutcome::Unparseable { body: html },
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::lb::Leaderboard;
+use crate::source::FetchOutcome;
+
+/// the leaderboard page on the site, and any mirrors of it
+///
+/// ```toml
+/// [board.source]
+/// type = "site"
+/// urls = ["https://hyprd.mn/leaderboards", "https://mirror.example/leaderboards"]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Site {
+    /// tried in order until one of them works
+    #[serde(default = "default_urls")]
+    pub urls: Vec<String>,
+    /// index of the mirror that worked last time
+    #[serde(skip)]
+    last_good: Arc<AtomicUsize>,
+}
+
+fn default_urls() -> Vec<String> {
+    vec!["https://hyprd.mn/leaderboards".to_string()]
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        Self {
+            urls: default_urls(),
+            last_good: Arc::default(),
+        }
+    }
+}
+
+impl Site {
+    /// scrape the first mirror that gives us a leaderboard
+    ///
+    /// starts with whichever one worked last time. if none of them
+    /// work, the last thing that went wrong is returned
+    pub async fn fetch(&self) -> reqwest::Result<FetchOutcome> {
+        let start = self.last_good.load(Ordering::Relaxed);
+        let mut last = Ok(FetchOutcome::Unparseable {
+            body: String::new(),
+        });
+
+        for i in (0..self.urls.len()).map(|i| (start + i) % self.urls.len()) {
+            let url = &self.urls[i];
+
+            match Leaderboard::from_site(url).await {
+                Ok(FetchOutcome::Board(lb)) => {
+                    self.last_good.store(i, Ordering::Relaxed);
+                    return Ok(FetchOutcome::Board(lb));
+                }
+                Ok(outcome) => {
+                    warn!(url, "mirror didn't give us a leaderboard");
+                    last = Ok(outcome);
+                }
+                Err(e) => {
+                    warn!(url, error = %e, "mirror failed");
+                    last = Err(e);
+                }
+            }
+        }
+
+        last
+    }
+}
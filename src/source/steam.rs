@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::lb::{now, Entry, Leaderboard};
+use crate::net;
+use crate::source::{is_down, FetchOutcome};
+
+/// a leaderboard on steam
+///
+/// uses the public xml leaderboard feeds, which don't need
+/// any credentials and stay up when the site is down.
+///
+/// steam has no runs to watch, so the run ids of its entries only
+/// tell one score apart from the next. run links, and `hdget
+/// correct` by run, don't mean anything for steam boards
+#[derive(Debug, Clone, Deserialize)]
+pub struct Steam {
+    pub app_id: u32,
+    pub leaderboard_id: u64,
+    /// steam web api key
+    ///
+    /// the feeds only have steam ids in them, so without
+    /// a key the ids are used as names
+    pub api_key: Option<String>,
+    /// steam only stores whole numbers, so scores get divided by this
+    #[serde(default = "default_divisor")]
+    pub score_divisor: f32,
+    /// how many entries to fetch
+    #[serde(default = "default_size")]
+    pub size: u16,
+}
+
+fn default_divisor() -> f32 {
+    1.0
+}
+
+fn default_size() -> u16 {
+    1000
+}
+
+/// `<response>` in the xml feed
+#[derive(Deserialize)]
+struct Feed {
+    entries: FeedEntries,
+}
+
+#[derive(Deserialize)]
+struct FeedEntries {
+    #[serde(default)]
+    entry: Vec<FeedEntry>,
+}
+
+#[derive(Deserialize)]
+struct FeedEntry {
+    steamid: u64,
+    score: i64,
+    rank: u32,
+    /// a handle to whatever the game attached to the score, -1 or
+    /// 18446744073709551615 for nothing. either fits in an i64 or
+    /// a u64, not both, so it's kept as text
+    ugcid: String,
+}
+
+/// response of `ISteamUser/GetPlayerSummaries`
+#[derive(Deserialize)]
+struct Summaries {
+    response: Players,
+}
+
+#[derive(Deserialize)]
+struct Players {
+    players: Vec<Player>,
+}
+
+#[derive(Deserialize)]
+struct Player {
+    steamid: String,
+    personaname: String,
+}
+
+impl Steam {
+    /// fetch the leaderboard from steam
+    pub async fn fetch(&self) -> reqwest::Result<FetchOutcome> {
+        let timestamp = now();
+
+        let req = net::client()
+            .get(format!(
+                "https://steamcommunity.com/stats/{}/leaderboards/{}/",
+                self.app_id, self.leaderboard_id
+            ))
+            .query(&[
+                ("xml", "1"),
+                ("start", "1"),
+                ("end", &self.size.to_string()),
+            ]);
+        let res = net::send(req).await?;
+        // steam goes down for maintenance every week
+        if is_down(res.status()) {
+            return Ok(FetchOutcome::Maintenance);
+        }
+        let xml = res.error_for_status()?.text().await?;
+
+        let Ok(feed) = quick_xml::de::from_str::<Feed>(&xml) else {
+            return Ok(FetchOutcome::Unparseable { body: xml });
+        };
+        let feed = feed.entries.entry;
+
+        let ids: Vec<_> = feed.iter().map(|e| e.steamid).collect();
+        let names = self.names(&ids).await?;
+
+        let Some(entries) = self.entries(&feed, &names) else {
+            return Ok(FetchOutcome::Unparseable { body: xml });
+        };
+
+        Ok(FetchOutcome::Board(Leaderboard { timestamp, entries }))
+    }
+
+    /// the feed as entries, none if a rank doesn't fit
+    fn entries(&self, feed: &[FeedEntry], names: &HashMap<u64, String>) -> Option<Vec<Entry>> {
+        feed.iter()
+            .map(|e| {
+                Some(Entry {
+                    rank: e.rank.try_into().ok()?,
+                    name: names
+                        .get(&e.steamid)
+                        .cloned()
+                        .unwrap_or_else(|| e.steamid.to_string()),
+                    // the low half of a steam id is the account id
+                    user_id: e.steamid as u32,
+                    run_id: run_id(e),
+                    score: e.score as f32 / self.score_divisor,
+                })
+            })
+            .collect()
+    }
+
+    /// look up display names for some steam ids
+    async fn names(&self, ids: &[u64]) -> reqwest::Result<HashMap<u64, String>> {
+        let mut names = HashMap::new();
+        let Some(key) = &self.api_key else {
+            return Ok(names);
+        };
+
+        // the api takes at most 100 ids at a time
+        for chunk in ids.chunks(100) {
+            let ids = chunk
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            let req = net::client()
+                .get("https://api.steampowered.com/ISteamUser/GetPlayerSummaries/v2/")
+                .query(&[("key", key.as_str()), ("steamids", &ids)]);
+            let summaries: Summaries = net::send(req).await?.error_for_status()?.json().await?;
+
+            for player in summaries.response.players {
+                if let Ok(id) = player.steamid.parse() {
+                    names.insert(id, player.personaname);
+                }
+            }
+        }
+
+        Ok(names)
+    }
+}
+
+/// something to tell a score apart from the one before it
+///
+/// steam keeps no run ids, but it only keeps the best score, so a
+/// new score means a new run. the ugc handle changes with it when
+/// there is one, otherwise it's the score, mixed with who got it
+/// so the same score by two people isn't the same run
+fn run_id(e: &FeedEntry) -> u32 {
+    let id = match e.ugcid.parse::<u64>() {
+        Ok(handle) if handle != 0 && handle != u64::MAX => handle,
+        _ => e.steamid ^ (e.score as u64).rotate_left(32),
+    };
+    // both halves, so nothing in the top one is lost
+    (id ^ (id >> 32)) as u32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_feed() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<response>
+<appID>1234</appID>
+<leaderboardID>5678</leaderboardID>
+<totalLeaderboardEntries>3</totalLeaderboardEntries>
+<nextRequestURL><![CDATA[https://steamcommunity.com/stats/1234/leaderboards/5678/?xml=1&start=4&end=5]]></nextRequestURL>
+<entries>
+<entry>
+<steamid>76561197960265729</steamid>
+<score>410500</score>
+<rank>1</rank>
+<ugcid>-1</ugcid>
+<details><![CDATA[]]></details>
+</entry>
+<entry>
+<steamid>76561197960265730</steamid>
+<score>400000</score>
+<rank>2</rank>
+<ugcid>-1</ugcid>
+<details><![CDATA[]]></details>
+</entry>
+<entry>
+<steamid>76561197960265731</steamid>
+<score>390000</score>
+<rank>3</rank>
+<ugcid>18446744073709551615</ugcid>
+<details><![CDATA[]]></details>
+</entry>
+</entries>
+</response>"#;
+
+        let feed: Feed = quick_xml::de::from_str(xml).unwrap();
+
+        assert_eq!(feed.entries.entry.len(), 3);
+        assert_eq!(feed.entries.entry[0].steamid as u32, 1);
+        assert_eq!(feed.entries.entry[1].score, 400000);
+    }
+
+    #[test]
+    fn test_entries() {
+        let steam = Steam {
+            app_id: 1234,
+            leaderboard_id: 5678,
+            api_key: None,
+            score_divisor: 1000.0,
+            size: 1000,
+        };
+        let feed = |steamid, score, ugcid: &str| FeedEntry {
+            steamid,
+            score,
+            rank: 1,
+            ugcid: ugcid.to_string(),
+        };
+        let names = HashMap::from([(76561197960265729, "possm".to_string())]);
+
+        let entries = steam
+            .entries(
+                &[
+                    feed(76561197960265729, 410500, "-1"),
+                    feed(76561197960265730, 410500, "18446744073709551615"),
+                ],
+                &names,
+            )
+            .unwrap();
+        assert_eq!(entries[0].name, "possm");
+        assert_eq!(entries[1].name, "76561197960265730");
+        assert_eq!((entries[0].user_id, entries[1].user_id), (1, 2));
+        assert_eq!(entries[0].score, 410.5);
+        // the same score by someone else is another run
+        assert_ne!(entries[0].run_id, entries[1].run_id);
+
+        // and so is a new score
+        let better = run_id(&feed(76561197960265729, 411000, "-1"));
+        assert_ne!(better, entries[0].run_id);
+        // a ugc handle is used whole
+        let ugc = |handle: u64| run_id(&feed(1, 1, &handle.to_string()));
+        assert_ne!(ugc(1 << 40), ugc(2 << 40));
+    }
+}
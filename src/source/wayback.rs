@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+
+use crate::lb::Leaderboard;
+use crate::net;
+use crate::source::FetchOutcome;
+
+/// a copy of a page archived by the wayback machine
+#[derive(Debug, Clone)]
+pub struct Capture {
+    /// wayback's 14 digit timestamp, like `20230817120000`
+    pub timestamp: String,
+    /// the url that was archived
+    pub original: String,
+}
+
+impl Capture {
+    /// when the capture was taken, as time since the unix epoch
+    pub fn time(&self) -> Option<Duration> {
+        let time = NaiveDateTime::parse_from_str(&self.timestamp, "%Y%m%d%H%M%S").ok()?;
+        let secs = time.and_utc().timestamp().try_into().ok()?;
+        Some(Duration::from_secs(secs))
+    }
+
+    /// get the archived page and parse it with the site's parser
+    pub async fn fetch(&self) -> reqwest::Result<FetchOutcome> {
+        // `id_` gets the page as it was, without wayback's toolbar in it
+        let url = format!(
+            "https://web.archive.org/web/{}id_/{}",
+            self.timestamp, self.original
+        );
+        let html = net::get(url).await?.error_for_status()?.text().await?;
+        let timestamp = self.time().unwrap_or_default();
+
+        Ok(match Leaderboard::from_html(&html, timestamp) {
+            Some(lb) => FetchOutcome::Board(lb),
+            None => FetchOutcome::Unparseable { body: html },
+        })
+    }
+}
+
+/// list every distinct capture of a url
+///
+/// captures that are identical to the one before are skipped
+pub async fn captures(url: &str) -> reqwest::Result<Vec<Capture>> {
+    let req = net::client()
+        .get("https://web.archive.org/cdx/search/cdx")
+        .query(&[
+            ("url", url),
+            ("output", "json"),
+            ("fl", "timestamp,original"),
+            ("filter", "statuscode:200"),
+            ("collapse", "digest"),
+        ]);
+    let rows: Vec<Vec<String>> = net::send(req).await?.error_for_status()?.json().await?;
+
+    Ok(parse(rows))
+}
+
+/// the captures in the rows of a cdx response, skipping any that
+/// don't have both columns
+fn parse(rows: Vec<Vec<String>>) -> Vec<Capture> {
+    rows.into_iter()
+        // the first row is the names of the columns
+        .skip(1)
+        .filter_map(|row| {
+            let [timestamp, original] = <[String; 2]>::try_from(row).ok()?;
+            Some(Capture {
+                timestamp,
+                original,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let rows = |rows: &[&[&str]]| {
+            rows.iter()
+                .map(|row| row.iter().map(|c| c.to_string()).collect())
+                .collect()
+        };
+        let captures = parse(rows(&[
+            &["timestamp", "original"],
+            &["20230817120000", "https://hyperdemon.example/"],
+            &["20230901"],
+            &["20231001000000", "https://hyperdemon.example/", "extra"],
+            &["20240102030405", "https://hyperdemon.example/?"],
+        ]));
+
+        assert_eq!(captures.len(), 2);
+        assert_eq!(captures[0].timestamp, "20230817120000");
+        assert_eq!(captures[1].original, "https://hyperdemon.example/?");
+        // nothing at all, not even the header
+        assert!(parse(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_time() {
+        let capture = |timestamp: &str| Capture {
+            timestamp: timestamp.to_string(),
+            original: String::new(),
+        };
+
+        assert_eq!(
+            capture("20230817120000").time(),
+            Some(Duration::from_secs(1692273600))
+        );
+        assert_eq!(capture("2023").time(), None);
+        assert_eq!(capture("20231341000000").time(), None);
+        // before the epoch
+        assert_eq!(capture("19691231235959").time(), None);
+    }
+}
@@ -0,0 +1,533 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tokio::io;
+
+use crate::lb::{Entry, Event, EventKind, Leaderboard};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    board TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    run_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    score REAL NOT NULL,
+    UNIQUE (board, user_id, run_id)
+);
+CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY,
+    board TEXT NOT NULL,
+    timestamp INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS snapshots_board ON snapshots (board, timestamp);
+CREATE TABLE IF NOT EXISTS entries (
+    snapshot INTEGER NOT NULL REFERENCES snapshots (id),
+    rank INTEGER NOT NULL,
+    run INTEGER NOT NULL REFERENCES runs (id)
+);
+CREATE INDEX IF NOT EXISTS entries_snapshot ON entries (snapshot);
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY,
+    board TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    run_id INTEGER NOT NULL,
+    rank INTEGER NOT NULL,
+    score REAL NOT NULL,
+    old_rank INTEGER,
+    old_score REAL
+);
+CREATE INDEX IF NOT EXISTS events_user ON events (user_id);
+";
+
+#[cfg(feature = "redb")]
+mod kv;
+
+/// an event found by the diff, as kept in the store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Emitted {
+    pub board: String,
+    /// unix time of the leaderboard it was found on
+    pub timestamp: u64,
+    pub kind: EventKind,
+    pub user_id: u32,
+    pub name: String,
+    pub run_id: u32,
+    pub rank: u16,
+    pub score: f32,
+    pub old_rank: Option<u16>,
+    pub old_score: Option<f32>,
+}
+
+impl Emitted {
+    pub fn new(board: &str, timestamp: Duration, pb: &Event) -> Self {
+        Self {
+            board: board.to_string(),
+            timestamp: timestamp.as_secs(),
+            kind: pb.kind(),
+            user_id: pb.entry().user_id,
+            name: pb.entry().name.clone(),
+            run_id: pb.entry().run_id,
+            rank: pb.entry().rank,
+            score: pb.entry().score,
+            old_rank: pb.previous().map(|o| o.rank),
+            old_score: pb.previous().map(|o| o.score),
+        }
+    }
+}
+
+/// who to look up the history of
+pub enum User<'a> {
+    Id(u32),
+    /// matched without caring about case
+    Name(&'a str),
+}
+
+/// what the store keeps everything in
+///
+/// ```toml
+/// store_backend = "redb"
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Sqlite,
+    /// a key-value file, needs the `redb` feature
+    Redb,
+}
+
+/// every board the bot has seen, and every event it found, in sqlite
+/// or redb, see `Backend`
+///
+/// complements the cache: the cache is what the next cycle gets
+/// diffed against, the store is the history.
+///
+/// ```toml
+/// store = "hdget.sqlite"
+/// ```
+#[derive(Clone)]
+pub struct Store {
+    db: Db,
+}
+
+#[derive(Clone)]
+enum Db {
+    Sqlite(Arc<Mutex<Connection>>),
+    #[cfg(feature = "redb")]
+    Kv(kv::Kv),
+}
+
+impl Store {
+    /// open the store at `path`, creating it if needed
+    pub async fn open(path: impl AsRef<Path>, backend: Backend) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        match backend {
+            Backend::Sqlite => {}
+            #[cfg(feature = "redb")]
+            Backend::Redb => {
+                return Ok(Self {
+                    db: Db::Kv(kv::Kv::open(&path).await?),
+                })
+            }
+            #[cfg(not(feature = "redb"))]
+            Backend::Redb => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "hdget was built without the redb feature",
+                ))
+            }
+        }
+
+        let conn = tokio::task::spawn_blocking(move || {
+            let conn = Connection::open(path)?;
+            // lets `hdget history` read while the bot is writing
+            conn.pragma_update(None, "journal_mode", "wal")?;
+            conn.execute_batch(SCHEMA)?;
+            Ok::<_, rusqlite::Error>(conn)
+        })
+        .await?
+        .map_err(io::Error::other)?;
+
+        Ok(Self {
+            db: Db::Sqlite(Arc::new(Mutex::new(conn))),
+        })
+    }
+
+    /// the sqlite connection, or whatever's there instead
+    fn sqlite(&self) -> Option<Arc<Mutex<Connection>>> {
+        match &self.db {
+            Db::Sqlite(conn) => Some(conn.clone()),
+            #[cfg(feature = "redb")]
+            Db::Kv(_) => None,
+        }
+    }
+
+    /// run something against the connection, off of the async threads
+    async fn with<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    ) -> io::Result<T> {
+        let conn = self.sqlite().expect("only called for sqlite");
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+            f(&mut conn)
+        })
+        .await?
+        .map_err(io::Error::other)
+    }
+
+    /// save a leaderboard and the events found on it
+    ///
+    /// all in one transaction, so the store never has a board
+    /// without its events or the other way around. a name change
+    /// renames everyone's older events too
+    pub async fn save(&self, board: &str, lb: &Leaderboard, events: &[Emitted]) -> io::Result<()> {
+        #[cfg(feature = "redb")]
+        if let Db::Kv(kv) = &self.db {
+            return kv.save(board, lb, events).await;
+        }
+        let board = board.to_string();
+        let timestamp = lb.timestamp.as_secs();
+        let entries = lb.entries.clone();
+        let events = events.to_vec();
+
+        self.with(move |conn| {
+            let tx = conn.transaction()?;
+
+            tx.execute(
+                "INSERT INTO snapshots (board, timestamp) VALUES (?1, ?2)",
+                params![board, timestamp],
+            )?;
+            let snapshot = tx.last_insert_rowid();
+
+            {
+                // runs only get stored once, no matter how many
+                // snapshots they show up in
+                let mut run = tx.prepare(
+                    "INSERT INTO runs (board, user_id, run_id, name, score)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT (board, user_id, run_id)
+                     DO UPDATE SET name = excluded.name, score = excluded.score
+                     RETURNING id",
+                )?;
+                let mut entry =
+                    tx.prepare("INSERT INTO entries (snapshot, rank, run) VALUES (?1, ?2, ?3)")?;
+                for e in &entries {
+                    let id: i64 = run.query_row(
+                        params![board, e.user_id, e.run_id, e.name, e.score],
+                        |row| row.get(0),
+                    )?;
+                    entry.execute(params![snapshot, e.rank, id])?;
+                }
+
+                let mut event = tx.prepare(
+                    "INSERT INTO events (board, timestamp, kind, user_id, name,
+                     run_id, rank, score, old_rank, old_score)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                )?;
+                for e in &events {
+                    event.execute(params![
+                        e.board,
+                        e.timestamp,
+                        kind_name(e.kind),
+                        e.user_id,
+                        e.name,
+                        e.run_id,
+                        e.rank,
+                        e.score,
+                        e.old_rank,
+                        e.old_score,
+                    ])?;
+                }
+
+                // everything they did before goes under the new name
+                for e in events.iter().filter(|e| e.kind == EventKind::NameChanged) {
+                    tx.execute(
+                        "UPDATE events SET name = ?2 WHERE user_id = ?1",
+                        params![e.user_id, e.name],
+                    )?;
+                    tx.execute(
+                        "UPDATE runs SET name = ?2 WHERE user_id = ?1",
+                        params![e.user_id, e.name],
+                    )?;
+                }
+            }
+
+            tx.commit()
+        })
+        .await
+    }
+
+    /// the last leaderboard saved for a board
+    pub async fn latest(&self, board: &str) -> io::Result<Option<Leaderboard>> {
+        #[cfg(feature = "redb")]
+        if let Db::Kv(kv) = &self.db {
+            return kv.latest(board).await;
+        }
+        let board = board.to_string();
+
+        self.with(move |conn| {
+            let Some((snapshot, timestamp)) = conn
+                .query_row(
+                    "SELECT id, timestamp FROM snapshots WHERE board = ?1
+                     ORDER BY timestamp DESC, id DESC LIMIT 1",
+                    params![board],
+                    |row| Ok((row.get::<_, i64>(0)?, row.get::<_, u64>(1)?)),
+                )
+                .optional()?
+            else {
+                return Ok(None);
+            };
+
+            let mut stmt = conn.prepare(
+                "SELECT entries.rank, runs.name, runs.user_id, runs.run_id, runs.score
+                 FROM entries JOIN runs ON runs.id = entries.run
+                 WHERE entries.snapshot = ?1 ORDER BY entries.rank",
+            )?;
+            let entries = stmt
+                .query_map(params![snapshot], |row| {
+                    Ok(Entry {
+                        rank: row.get(0)?,
+                        name: row.get(1)?,
+                        user_id: row.get(2)?,
+                        run_id: row.get(3)?,
+                        score: row.get(4)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            Ok(Some(Leaderboard {
+                timestamp: Duration::from_secs(timestamp),
+                entries,
+            }))
+        })
+        .await
+    }
+
+    /// every event a user had, oldest first
+    pub async fn events_of(&self, user: User<'_>, board: Option<&str>) -> io::Result<Vec<Emitted>> {
+        #[cfg(feature = "redb")]
+        if let Db::Kv(kv) = &self.db {
+            return kv.events_of(user, board).await;
+        }
+        let (user_id, name) = match user {
+            User::Id(id) => (Some(id), None),
+            User::Name(name) => (None, Some(name.to_string())),
+        };
+        let board = board.map(|b| b.to_string());
+
+        self.with(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT board, timestamp, kind, user_id, name, run_id,
+                 rank, score, old_rank, old_score FROM events
+                 WHERE (?1 IS NULL OR user_id = ?1)
+                 AND (?2 IS NULL OR user_id IN
+                     (SELECT user_id FROM events WHERE name = ?2 COLLATE NOCASE))
+                 AND (?3 IS NULL OR board = ?3)
+                 ORDER BY timestamp, id",
+            )?;
+            let events = stmt
+                .query_map(params![user_id, name, board], read_event)?
+                .collect::<rusqlite::Result<_>>()?;
+
+            Ok(events)
+        })
+        .await
+    }
+
+    /// every event found between two unix times, oldest first
+    pub async fn events_between(
+        &self,
+        from: u64,
+        to: u64,
+        board: Option<&str>,
+    ) -> io::Result<Vec<Emitted>> {
+        #[cfg(feature = "redb")]
+        if let Db::Kv(kv) = &self.db {
+            return kv.events_between(from, to, board).await;
+        }
+        let board = board.map(|b| b.to_string());
+
+        self.with(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT board, timestamp, kind, user_id, name, run_id,
+                 rank, score, old_rank, old_score FROM events
+                 WHERE timestamp >= ?1 AND timestamp < ?2
+                 AND (?3 IS NULL OR board = ?3)
+                 ORDER BY timestamp, id",
+            )?;
+            let events = stmt
+                .query_map(params![from, to, board], read_event)?
+                .collect::<rusqlite::Result<_>>()?;
+
+            Ok(events)
+        })
+        .await
+    }
+
+    /// write a copy of the whole store to `path`, which can be
+    /// taken while the bot is writing to it
+    pub async fn copy_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref().to_path_buf();
+        #[cfg(feature = "redb")]
+        if let Db::Kv(kv) = &self.db {
+            return kv.copy_to(path).await;
+        }
+
+        self.with(move |conn| {
+            conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// read a row of the events table, in the order the queries select it
+fn read_event(row: &rusqlite::Row) -> rusqlite::Result<Emitted> {
+    let kind: String = row.get(2)?;
+    Ok(Emitted {
+        board: row.get(0)?,
+        timestamp: row.get(1)?,
+        kind: parse_kind(&kind).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(
+                2,
+                rusqlite::types::Type::Text,
+                format!("unknown event kind {}", kind).into(),
+            )
+        })?,
+        user_id: row.get(3)?,
+        name: row.get(4)?,
+        run_id: row.get(5)?,
+        rank: row.get(6)?,
+        score: row.get(7)?,
+        old_rank: row.get(8)?,
+        old_score: row.get(9)?,
+    })
+}
+
+fn kind_name(kind: EventKind) -> &'static str {
+    match kind {
+        EventKind::WorldRecord => "world_record",
+        EventKind::Milestone => "milestone",
+        EventKind::Pb => "pb",
+        EventKind::NewPlayer => "new_player",
+        EventKind::RankChange => "rank_change",
+        EventKind::NameChanged => "name_changed",
+        EventKind::ScoreRemoved => "score_removed",
+        EventKind::EnteredTop => "entered_top",
+        EventKind::CloseRace => "close_race",
+    }
+}
+
+fn parse_kind(name: &str) -> Option<EventKind> {
+    Some(match name {
+        "world_record" => EventKind::WorldRecord,
+        "milestone" => EventKind::Milestone,
+        "pb" => EventKind::Pb,
+        "new_player" => EventKind::NewPlayer,
+        "rank_change" => EventKind::RankChange,
+        "name_changed" => EventKind::NameChanged,
+        "score_removed" => EventKind::ScoreRemoved,
+        "entered_top" => EventKind::EnteredTop,
+        "close_race" => EventKind::CloseRace,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(rank: u16, name: &str, user_id: u32, run_id: u32, score: f32) -> Entry {
+        Entry {
+            rank,
+            name: name.to_string(),
+            user_id,
+            run_id,
+            score,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store() {
+        check(Store::open(":memory:", Backend::Sqlite).await.unwrap()).await;
+    }
+
+    #[cfg(feature = "redb")]
+    #[tokio::test]
+    async fn test_kv_store() {
+        let path = std::env::temp_dir().join(format!("hdget-redb-{}", std::process::id()));
+        check(Store::open(&path, Backend::Redb).await.unwrap()).await;
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    async fn check(store: Store) {
+        assert!(store.latest("main").await.unwrap().is_none());
+
+        let old = Leaderboard {
+            timestamp: Duration::from_secs(0),
+            entries: vec![
+                entry(1, "possm", 1, 1, 400.0),
+                entry(2, "fennekal", 2, 2, 399.0),
+            ],
+        };
+        store.save("main", &old, &[]).await.unwrap();
+
+        let new = Leaderboard {
+            timestamp: Duration::from_secs(600),
+            entries: vec![
+                entry(1, "fennekal", 2, 3, 410.0),
+                entry(2, "possm", 1, 1, 400.0),
+            ],
+        };
+        let events: Vec<_> = old
+            .events(&new)
+            .iter()
+            .map(|pb| Emitted::new("main", new.timestamp, pb))
+            .collect();
+        store.save("main", &new, &events).await.unwrap();
+
+        let latest = store.latest("main").await.unwrap().unwrap();
+        assert_eq!(latest.timestamp, new.timestamp);
+        assert_eq!(latest.entries[0].run_id, 3);
+        assert_eq!(latest.entries[1].name, "possm");
+
+        let history = store.events_of(User::Name("FENNEKAL"), None).await.unwrap();
+        assert_eq!(history, events);
+        assert_eq!(history[0].kind, EventKind::WorldRecord);
+        assert_eq!(history[0].old_rank, Some(2));
+        assert_eq!(store.events_between(600, 601, None).await.unwrap(), events);
+        assert!(store.events_between(0, 600, None).await.unwrap().is_empty());
+        assert!(store
+            .events_of(User::Id(1), Some("main"))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // fennekal's history follows them to their new name
+        let renamed = Leaderboard {
+            timestamp: Duration::from_secs(1200),
+            entries: vec![
+                entry(1, "fennec", 2, 3, 410.0),
+                entry(2, "possm", 1, 1, 400.0),
+            ],
+        };
+        let events: Vec<_> = new
+            .events(&renamed)
+            .iter()
+            .map(|pb| Emitted::new("main", renamed.timestamp, pb))
+            .collect();
+        assert_eq!(events[0].kind, EventKind::NameChanged);
+        store.save("main", &renamed, &events).await.unwrap();
+        let history = store.events_of(User::Name("fennec"), None).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|e| e.name == "fennec"));
+    }
+}
@@ -0,0 +1,238 @@
+// redb's errors are big, but they're turned into io errors right away
+#![allow(clippy::result_large_err)]
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use redb::{Database, ReadableTable, ReadableTableMetadata, TableDefinition};
+use tokio::io;
+
+use super::{Emitted, User};
+use crate::lb::{Encoding, EventKind, Leaderboard};
+
+/// every snapshot in the cache format, by board and timestamp
+const SNAPSHOTS: TableDefinition<(&str, u64), &[u8]> = TableDefinition::new("snapshots");
+/// every event as json, by timestamp and the order it was saved in
+const EVENTS: TableDefinition<(u64, u64), &[u8]> = TableDefinition::new("events");
+/// the keys of `EVENTS`, by user
+const USERS: TableDefinition<(u32, u64, u64), ()> = TableDefinition::new("users");
+
+/// the store, in a single redb file instead of sqlite
+#[derive(Clone)]
+pub(super) struct Kv {
+    db: Arc<Database>,
+}
+
+impl Kv {
+    pub async fn open(path: &Path) -> io::Result<Self> {
+        let path = path.to_path_buf();
+        let db = tokio::task::spawn_blocking(move || {
+            let db = Database::create(path)?;
+            // make sure the tables are there for the readers
+            let tx = db.begin_write()?;
+            tx.open_table(SNAPSHOTS)?;
+            tx.open_table(EVENTS)?;
+            tx.open_table(USERS)?;
+            tx.commit()?;
+            Ok::<_, redb::Error>(db)
+        })
+        .await?
+        .map_err(io::Error::other)?;
+
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    /// run something against the database, off of the async threads
+    async fn with<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Database) -> Result<T, redb::Error> + Send + 'static,
+    ) -> io::Result<T> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || f(&db))
+            .await?
+            .map_err(io::Error::other)
+    }
+
+    pub async fn save(&self, board: &str, lb: &Leaderboard, events: &[Emitted]) -> io::Result<()> {
+        let board = board.to_string();
+        let timestamp = lb.timestamp.as_secs();
+        let snapshot = lb.to_bytes(Encoding::Binary, false).await?;
+        let renames: Vec<_> = events
+            .iter()
+            .filter(|e| e.kind == EventKind::NameChanged)
+            .map(|e| (e.user_id, e.name.clone()))
+            .collect();
+        let events = events
+            .iter()
+            .map(|e| Ok((e.user_id, e.timestamp, serde_json::to_vec(e)?)))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        self.with(move |db| {
+            let tx = db.begin_write()?;
+            {
+                let mut snapshots = tx.open_table(SNAPSHOTS)?;
+                snapshots.insert((board.as_str(), timestamp), snapshot.as_slice())?;
+
+                let mut table = tx.open_table(EVENTS)?;
+                let mut users = tx.open_table(USERS)?;
+                // nothing ever gets deleted, so this keeps going up
+                let start = table.len()?;
+                for (seq, (user_id, timestamp, json)) in (start..).zip(&events) {
+                    table.insert((*timestamp, seq), json.as_slice())?;
+                    users.insert((*user_id, *timestamp, seq), ())?;
+                }
+
+                for (id, name) in &renames {
+                    let mut keys = Vec::new();
+                    for key in users.range((*id, 0, 0)..=(*id, u64::MAX, u64::MAX))? {
+                        let (_, timestamp, seq) = key?.0.value();
+                        keys.push((timestamp, seq));
+                    }
+                    for key in keys {
+                        let Some(json) = table.get(key)?.map(|j| j.value().to_vec()) else {
+                            continue;
+                        };
+                        // anything that doesn't parse is left alone
+                        let Ok(mut event) = serde_json::from_slice::<Emitted>(&json) else {
+                            continue;
+                        };
+                        event.name = name.clone();
+                        if let Ok(json) = serde_json::to_vec(&event) {
+                            table.insert(key, json.as_slice())?;
+                        }
+                    }
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn latest(&self, board: &str) -> io::Result<Option<Leaderboard>> {
+        let board = board.to_string();
+        let bytes = self
+            .with(move |db| {
+                let tx = db.begin_read()?;
+                let snapshots = tx.open_table(SNAPSHOTS)?;
+                let last = snapshots
+                    .range((board.as_str(), 0)..=(board.as_str(), u64::MAX))?
+                    .next_back()
+                    .transpose()?;
+                Ok(last.map(|(_, bytes)| bytes.value().to_vec()))
+            })
+            .await?;
+
+        match bytes {
+            Some(bytes) => Leaderboard::from_bytes(&bytes).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn events_of(&self, user: User<'_>, board: Option<&str>) -> io::Result<Vec<Emitted>> {
+        let events = match user {
+            User::Id(id) => {
+                self.with(move |db| {
+                    let tx = db.begin_read()?;
+                    let users = tx.open_table(USERS)?;
+                    let events = tx.open_table(EVENTS)?;
+
+                    let mut found = Vec::new();
+                    for key in users.range((id, 0, 0)..=(id, u64::MAX, u64::MAX))? {
+                        let (_, timestamp, seq) = key?.0.value();
+                        if let Some(json) = events.get((timestamp, seq))? {
+                            found.push(json.value().to_vec());
+                        }
+                    }
+                    Ok(found)
+                })
+                .await?
+            }
+            // names aren't keyed, so that's a look at everything
+            User::Name(name) => {
+                let all = self.between(0, u64::MAX).await?;
+                let ids: HashSet<_> = all
+                    .iter()
+                    .filter(|e| e.name.eq_ignore_ascii_case(name))
+                    .map(|e| e.user_id)
+                    .collect();
+                return Ok(all
+                    .into_iter()
+                    .filter(|e| ids.contains(&e.user_id) && board.is_none_or(|b| e.board == b))
+                    .collect());
+            }
+        };
+
+        let events = parse(events)?;
+        Ok(events
+            .into_iter()
+            .filter(|e| board.is_none_or(|b| e.board == b))
+            .collect())
+    }
+
+    pub async fn events_between(
+        &self,
+        from: u64,
+        to: u64,
+        board: Option<&str>,
+    ) -> io::Result<Vec<Emitted>> {
+        Ok(self
+            .between(from, to)
+            .await?
+            .into_iter()
+            .filter(|e| board.is_none_or(|b| e.board == b))
+            .collect())
+    }
+
+    pub async fn copy_to(&self, path: PathBuf) -> io::Result<()> {
+        self.with(move |db| {
+            let copy = Database::create(path)?;
+            let from = db.begin_read()?;
+            let to = copy.begin_write()?;
+            {
+                let mut snapshots = to.open_table(SNAPSHOTS)?;
+                for row in from.open_table(SNAPSHOTS)?.iter()? {
+                    let (k, v) = row?;
+                    snapshots.insert(k.value(), v.value())?;
+                }
+                let mut events = to.open_table(EVENTS)?;
+                for row in from.open_table(EVENTS)?.iter()? {
+                    let (k, v) = row?;
+                    events.insert(k.value(), v.value())?;
+                }
+                let mut users = to.open_table(USERS)?;
+                for row in from.open_table(USERS)?.iter()? {
+                    users.insert(row?.0.value(), ())?;
+                }
+            }
+            to.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// every event from `from` up to `to`, oldest first
+    async fn between(&self, from: u64, to: u64) -> io::Result<Vec<Emitted>> {
+        let events = self
+            .with(move |db| {
+                let tx = db.begin_read()?;
+                let events = tx.open_table(EVENTS)?;
+                let mut found = Vec::new();
+                for row in events.range((from, 0)..(to, 0))? {
+                    found.push(row?.1.value().to_vec());
+                }
+                Ok(found)
+            })
+            .await?;
+
+        parse(events)
+    }
+}
+
+fn parse(events: Vec<Vec<u8>>) -> io::Result<Vec<Emitted>> {
+    events
+        .iter()
+        .map(|json| serde_json::from_slice(json).map_err(io::Error::from))
+        .collect()
+}
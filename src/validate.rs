@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::lb::Leaderboard;
+
+/// what to do about a leaderboard that looks wrong
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strictness {
+    /// don't check anything
+    Off,
+    /// log what's wrong, but use the leaderboard anyways
+    Warn,
+    /// throw the leaderboard away, keeping the old baseline
+    #[default]
+    Reject,
+}
+
+/// something wrong with a leaderboard
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// ranks skipped a number or went backwards
+    RankGap { prev: u16, next: u16 },
+    /// the same user shows up more than once
+    DuplicateUser { user_id: u32 },
+    /// a score is NaN or infinite
+    BadScore { rank: u16 },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RankGap { prev, next } => write!(f, "rank #{} is followed by #{}", prev, next),
+            Self::DuplicateUser { user_id } => write!(f, "user {} shows up twice", user_id),
+            Self::BadScore { rank } => write!(f, "rank #{} doesn't have a real score", rank),
+        }
+    }
+}
+
+impl Leaderboard {
+    /// check that the board makes sense
+    ///
+    /// ranks should go up one at a time, every user should
+    /// only be on there once, and every score should be a
+    /// normal number. a half rendered page usually breaks
+    /// at least one of these.
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let mut users = HashSet::new();
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            if let Some(prev) = i.checked_sub(1).map(|i| &self.entries[i]) {
+                if prev.rank.checked_add(1) != Some(entry.rank) {
+                    problems.push(Problem::RankGap {
+                        prev: prev.rank,
+                        next: entry.rank,
+                    });
+                }
+            }
+
+            if !users.insert(entry.user_id) {
+                problems.push(Problem::DuplicateUser {
+                    user_id: entry.user_id,
+                });
+            }
+
+            if !entry.score.is_finite() {
+                problems.push(Problem::BadScore { rank: entry.rank });
+            }
+        }
+
+        problems
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::lb::Entry;
+
+    fn entry(rank: u16, user_id: u32, score: f32) -> Entry {
+        Entry {
+            rank,
+            name: user_id.to_string(),
+            user_id,
+            run_id: user_id,
+            score,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let good = Leaderboard {
+            timestamp: Duration::ZERO,
+            entries: vec![entry(1, 1, 400.0), entry(2, 2, 399.0)],
+        };
+        assert!(good.validate().is_empty());
+
+        let bad = Leaderboard {
+            timestamp: Duration::ZERO,
+            entries: vec![entry(1, 1, 400.0), entry(3, 1, f32::NAN)],
+        };
+        assert_eq!(
+            bad.validate(),
+            vec![
+                Problem::RankGap { prev: 1, next: 3 },
+                Problem::DuplicateUser { user_id: 1 },
+                Problem::BadScore { rank: 3 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use chrono::{DateTime, Datelike, NaiveDate};
+
+use crate::annotate::Annotation;
+use crate::lb::EventKind;
+use crate::store::Emitted;
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// a year in review, put together out of the store
+pub struct Wrapped {
+    year: i32,
+    /// every event of the year, oldest first
+    events: Vec<Emitted>,
+    annotations: Vec<Annotation>,
+}
+
+/// unix times of the start of a year, and the start of the next one
+pub fn year_bounds(year: i32) -> Option<(u64, u64)> {
+    let start = |year| {
+        let t = NaiveDate::from_ymd_opt(year, 1, 1)?
+            .and_hms_opt(0, 0, 0)?
+            .and_utc()
+            .timestamp();
+        u64::try_from(t).ok()
+    };
+
+    Some((start(year)?, start(year + 1)?))
+}
+
+fn date(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// totals for a single player over the year
+#[derive(Default)]
+struct Player<'a> {
+    name: &'a str,
+    /// pbs, milestones and wrs
+    pbs: usize,
+    score_gained: f32,
+    ranks_gained: u32,
+}
+
+impl Wrapped {
+    pub fn new(year: i32, events: Vec<Emitted>, annotations: Vec<Annotation>) -> Self {
+        Self {
+            year,
+            events,
+            annotations,
+        }
+    }
+
+    fn world_records(&self) -> impl Iterator<Item = &Emitted> {
+        self.events
+            .iter()
+            .filter(|e| e.kind == EventKind::WorldRecord)
+    }
+
+    /// the `n` biggest score jumps, biggest first
+    fn improvements(&self, n: usize) -> Vec<(&Emitted, f32)> {
+        let mut jumps: Vec<_> = self
+            .events
+            .iter()
+            .filter_map(|e| Some((e, e.score - e.old_score?)))
+            .collect();
+        jumps.sort_by(|a, b| b.1.total_cmp(&a.1));
+        jumps.truncate(n);
+        jumps
+    }
+
+    /// number of events in each month
+    fn months(&self) -> [usize; 12] {
+        let mut months = [0; 12];
+        for e in &self.events {
+            if let Some(t) = DateTime::from_timestamp(e.timestamp as i64, 0) {
+                months[t.month0() as usize] += 1;
+            }
+        }
+        months
+    }
+
+    /// totals for everyone who did something, keyed by user id
+    fn players(&self) -> HashMap<u32, Player<'_>> {
+        let mut players: HashMap<u32, Player> = HashMap::new();
+        for e in &self.events {
+            // not something they did
+            if !e.kind.is_pb() {
+                continue;
+            }
+            let player = players.entry(e.user_id).or_default();
+            // events are oldest first, so this ends up the latest name
+            player.name = &e.name;
+
+            let (Some(old_score), Some(old_rank)) = (e.old_score, e.old_rank) else {
+                continue;
+            };
+            player.pbs += 1;
+            player.score_gained += e.score - old_score;
+            player.ranks_gained += old_rank.saturating_sub(e.rank) as u32;
+        }
+        players
+    }
+
+    /// who held rank 1 the longest, and for how many days
+    fn longest_reign(&self) -> Option<(&str, u64)> {
+        let end = year_bounds(self.year)?.1;
+        let wrs: Vec<_> = self.world_records().collect();
+
+        let mut reigns: HashMap<u32, (&str, u64)> = HashMap::new();
+        for (i, wr) in wrs.iter().enumerate() {
+            let until = wrs.get(i + 1).map_or(end, |next| next.timestamp);
+            let reign = reigns.entry(wr.user_id).or_insert((&wr.name, 0));
+            reign.1 += until.saturating_sub(wr.timestamp);
+        }
+
+        reigns
+            .into_values()
+            .max_by_key(|r| r.1)
+            .map(|(name, secs)| (name, secs / 86400))
+    }
+
+    /// one liners about the players that stood out
+    fn superlatives(&self) -> Vec<String> {
+        let players = self.players();
+        let best = |key: fn(&Player) -> f32| {
+            players
+                .values()
+                .filter(|p| key(p) > 0.0)
+                .max_by(|a, b| key(a).total_cmp(&key(b)))
+        };
+
+        let mut lines = Vec::new();
+        if let Some(p) = best(|p| p.pbs as f32) {
+            lines.push(format!("**most pbs:** {} with {}", p.name, p.pbs));
+        }
+        if let Some(p) = best(|p| p.score_gained) {
+            lines.push(format!(
+                "**most improved:** {}, up {:.4} over the year",
+                p.name, p.score_gained
+            ));
+        }
+        if let Some(p) = best(|p| p.ranks_gained as f32) {
+            lines.push(format!(
+                "**biggest climber:** {}, {} ranks",
+                p.name, p.ranks_gained
+            ));
+        }
+        if let Some((name, days)) = self.longest_reign() {
+            lines.push(format!(
+                "**longest reign:** {}, {} days at rank 1",
+                name, days
+            ));
+        }
+        let newcomer = self
+            .events
+            .iter()
+            .filter(|e| e.kind == EventKind::NewPlayer)
+            .min_by_key(|e| e.rank);
+        if let Some(e) = newcomer {
+            lines.push(format!(
+                "**best newcomer:** {}, straight in at #{}",
+                e.name, e.rank
+            ));
+        }
+
+        lines
+    }
+
+    /// the report, one markdown section at a time
+    ///
+    /// sections are short enough to each go in their own message
+    pub fn sections(&self) -> Vec<String> {
+        let mut sections = Vec::new();
+
+        let players = self.players().len();
+        sections.push(format!(
+            "# hdget wrapped {}\n\n{} events from {} players",
+            self.year,
+            self.events.len(),
+            players
+        ));
+
+        // discord only takes 2000 characters a message
+        let wrs: Vec<_> = self.world_records().collect();
+        let skipped = wrs.len().saturating_sub(25);
+        let mut s = String::from("## world records\n\n");
+        if skipped > 0 {
+            let _ = writeln!(s, "- ...and {} before these", skipped);
+        }
+        for wr in &wrs[skipped..] {
+            let _ = writeln!(
+                s,
+                "- {} **{}** took rank 1 on {} with {}",
+                date(wr.timestamp),
+                wr.name,
+                wr.board,
+                wr.score
+            );
+        }
+        if wrs.is_empty() {
+            s.push_str("nobody took rank 1 this year\n");
+        }
+        sections.push(s);
+
+        let mut s = String::from("## biggest improvements\n\n");
+        for (i, (e, jump)) in self.improvements(10).iter().enumerate() {
+            let _ = writeln!(
+                s,
+                "{}. **{}** +{:.4}, to {} on {}",
+                i + 1,
+                e.name,
+                jump,
+                e.score,
+                date(e.timestamp)
+            );
+        }
+        sections.push(s);
+
+        // a chart that works anywhere markdown does
+        let months = self.months();
+        let most = months.iter().copied().max().unwrap_or(0).max(1);
+        let mut s = String::from("## most active months\n\n```\n");
+        for (name, n) in MONTHS.iter().zip(months) {
+            let bar = "█".repeat(n * 30 / most);
+            let _ = writeln!(s, "{} {:<30} {}", name, bar, n);
+        }
+        s.push_str("```\n");
+        sections.push(s);
+
+        let superlatives = self.superlatives();
+        if !superlatives.is_empty() {
+            sections.push(format!("## superlatives\n\n{}\n", superlatives.join("\n")));
+        }
+
+        if !self.annotations.is_empty() {
+            let mut s = String::from("## what else happened\n\n");
+            for a in &self.annotations {
+                let _ = writeln!(s, "- {} {}", date(a.at), a.text);
+            }
+            sections.push(s);
+        }
+
+        sections
+    }
+
+    /// the whole report as one markdown document
+    pub fn markdown(&self) -> String {
+        self.sections().join("\n")
+    }
+
+    /// events per month as an svg bar chart
+    pub fn chart(&self) -> String {
+        let months = self.months();
+        let most = months.iter().copied().max().unwrap_or(0).max(1);
+
+        let mut svg = String::from(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="600" height="240" viewBox="0 0 600 240">
+<rect width="600" height="240" fill="#1b2631"/>
+"##,
+        );
+        for (i, (name, n)) in MONTHS.iter().zip(months).enumerate() {
+            let height = n * 180 / most;
+            let x = 20 + i * 48;
+            let _ = writeln!(
+                svg,
+                r##"<rect x="{}" y="{}" width="36" height="{}" fill="#f4d03f"/>"##,
+                x,
+                200 - height,
+                height
+            );
+            let _ = writeln!(
+                svg,
+                r##"<text x="{}" y="220" font-family="sans-serif" font-size="12" fill="#ffffff" text-anchor="middle">{}</text>"##,
+                x + 18,
+                name
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(
+        timestamp: u64,
+        kind: EventKind,
+        user_id: u32,
+        name: &str,
+        old: Option<(u16, f32)>,
+    ) -> Emitted {
+        Emitted {
+            board: "main".to_string(),
+            timestamp,
+            kind,
+            user_id,
+            name: name.to_string(),
+            run_id: timestamp as u32,
+            rank: if kind == EventKind::WorldRecord { 1 } else { 5 },
+            score: 400.0,
+            old_rank: old.map(|o| o.0),
+            old_score: old.map(|o| o.1),
+        }
+    }
+
+    #[test]
+    fn test_wrapped() {
+        let (start, end) = year_bounds(2024).unwrap();
+        assert_eq!(end - start, 366 * 86400);
+
+        let wrapped = Wrapped::new(
+            2024,
+            vec![
+                event(start, EventKind::NewPlayer, 1, "possm", None),
+                event(
+                    start + 86400,
+                    EventKind::WorldRecord,
+                    1,
+                    "possm",
+                    Some((5, 390.0)),
+                ),
+                event(
+                    start + 40 * 86400,
+                    EventKind::WorldRecord,
+                    2,
+                    "fennekal",
+                    Some((2, 399.0)),
+                ),
+                event(
+                    start + 41 * 86400,
+                    EventKind::Pb,
+                    2,
+                    "fennekal",
+                    Some((8, 350.0)),
+                ),
+            ],
+            vec![],
+        );
+
+        assert_eq!(wrapped.months()[0], 2);
+        assert_eq!(wrapped.months()[1], 2);
+        assert_eq!(wrapped.improvements(1)[0].1, 50.0);
+        assert_eq!(wrapped.longest_reign(), Some(("fennekal", 326)));
+
+        let md = wrapped.markdown();
+        assert!(md.contains("**most pbs:** fennekal with 2"));
+        assert!(md.contains("**best newcomer:** possm"));
+    }
+}